@@ -152,6 +152,16 @@ resource "aws_iam_policy" "dotsdb_ingestion_lambda_role_policy" {
         Effect   = "Allow"
         Resource = "*"
       },
+      {
+        Effect = "Allow",
+        Action = [
+          "glue:GetTable",
+          "glue:UpdateTable"
+        ],
+        Resource = [
+          "arn:aws:glue:${data.aws_region.current.name}:${data.aws_caller_identity.current.account_id}:*"
+        ]
+      },
       {
         Effect = "Allow",
         Action = [
@@ -201,7 +211,10 @@ resource "aws_lambda_function" "dotsdb_ingestion_lambda" {
   environment {
     variables = {
       # TODO: Add all env vars here for lambda
-      DOTSDB_DATA_BUCKET = var.data_s3_bucket
+      DOTSDB_CATALOG   = "glue"
+      DOTSDB_STORAGE   = "s3"
+      DOTSDB_NAMESPACE = "dotsdb"
+      DOTSDB_TABLE     = "books"
     }
   }
 }
\ No newline at end of file
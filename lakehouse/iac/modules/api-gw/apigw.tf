@@ -24,6 +24,13 @@ resource "aws_apigatewayv2_route" "dotsdb_ingest_route" {
   target = "integrations/${aws_apigatewayv2_integration.dotsdb_ingest_integration.id}"
 }
 
+resource "aws_apigatewayv2_route" "dotsdb_delete_route" {
+  api_id    = aws_apigatewayv2_api.dotsdb_apigw_api.id
+  route_key = "DELETE /book-reviews"
+
+  target = "integrations/${aws_apigatewayv2_integration.dotsdb_ingest_integration.id}"
+}
+
 resource "aws_apigatewayv2_stage" "dotsdb_ingest_stage" {
   api_id = aws_apigatewayv2_api.dotsdb_apigw_api.id
   name   = "dotsdb_books_stage"
@@ -48,7 +55,8 @@ resource "aws_apigatewayv2_stage" "dotsdb_ingest_stage" {
   }
 
   depends_on = [
-    aws_apigatewayv2_route.dotsdb_ingest_route
+    aws_apigatewayv2_route.dotsdb_ingest_route,
+    aws_apigatewayv2_route.dotsdb_delete_route
   ]
 }
 
@@ -60,6 +68,7 @@ resource "aws_apigatewayv2_deployment" "dots_db_deployment" {
     redeployment = sha1(join(",", tolist([
       jsonencode(aws_apigatewayv2_integration.dotsdb_ingest_integration),
       jsonencode(aws_apigatewayv2_route.dotsdb_ingest_route),
+      jsonencode(aws_apigatewayv2_route.dotsdb_delete_route),
     ])))
   }
 
@@ -33,6 +33,23 @@ resource "aws_s3_bucket_versioning" "dotsdb_bucket_versioning" {
   }
 }
 
+# Backstop for multipart uploads of data files that neither completed nor got aborted, e.g. when
+# the lambda timed out mid-write
+resource "aws_s3_bucket_lifecycle_configuration" "dotsdb_bucket_lifecycle" {
+  bucket = aws_s3_bucket.dotsdb_bucket.id
+
+  rule {
+    id     = "abort-incomplete-multipart-uploads"
+    status = "Enabled"
+
+    filter {}
+
+    abort_incomplete_multipart_upload {
+      days_after_initiation = 1
+    }
+  }
+}
+
 output "data" {
   value = aws_s3_bucket.dotsdb_bucket
 }
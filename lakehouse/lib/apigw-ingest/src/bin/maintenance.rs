@@ -0,0 +1,84 @@
+use std::env;
+
+use anyhow::Context as _;
+use apigw_ingest::catalog::{self, TableIdent};
+use apigw_ingest::compact::{self, RewriteOptions, Rewritten};
+use apigw_ingest::iceberg::metadata::now_ms;
+use apigw_ingest::iceberg::parquet::WriteProperties;
+use apigw_ingest::maintenance;
+use apigw_ingest::storage;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use log::{info, warn};
+use serde_json::{json, Value};
+
+// MAINTAIN THE TABLE THE INGEST LAMBDA WRITES TO - see maintenance.rs
+// Invoked on a schedule, e.g. daily by an EventBridge rule, with any event. Uses the same
+// DOTSDB_* configuration as the ingest lambda.
+// 1. Compact the small files of the ingests into target-sized ones - see compact.rs
+// 2. Expire old snapshots and delete the files only they referenced
+// 3. Delete the files no metadata references that are older than DOTSDB_ORPHAN_MIN_AGE_HOURS
+
+/// Files younger than this may belong to a commit in progress, so they're never orphans.
+const DEFAULT_ORPHAN_MIN_AGE_HOURS: i64 = 72;
+
+async fn function_handler(_event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let namespace = env::var("DOTSDB_NAMESPACE").context("DOTSDB_NAMESPACE is not set")?;
+    let name = env::var("DOTSDB_TABLE").context("DOTSDB_TABLE is not set")?;
+    let orphan_min_age_hours = match env::var("DOTSDB_ORPHAN_MIN_AGE_HOURS") {
+        Ok(hours) => hours
+            .parse::<i64>()
+            .context("DOTSDB_ORPHAN_MIN_AGE_HOURS is not a number of hours")?,
+        Err(_) => DEFAULT_ORPHAN_MIN_AGE_HOURS,
+    };
+    let table = TableIdent::new(&namespace, &name);
+    let storage = storage::from_env().await?;
+    let catalog = catalog::from_env(storage.clone()).await?;
+
+    let base = catalog.load_table(&table).await?;
+    let options = RewriteOptions::from_write_properties(&WriteProperties::from_properties(&base.metadata.properties));
+    // A compaction that lost a race is redone on the next run, so it doesn't hold up the rest
+    let rewritten = compact::rewrite_data_files(storage.as_ref(), catalog.as_ref(), &table, base, &options)
+        .await
+        .unwrap_or_else(|err| {
+            warn!("Failed to compact {}: {:#}", table, err);
+            Rewritten::default()
+        });
+    info!(
+        "Compacted {} data files of {} into {}",
+        rewritten.rewritten_files, table, rewritten.added_files
+    );
+
+    let now = now_ms();
+    let base = catalog.load_table(&table).await?;
+    let expired = maintenance::expire_snapshots(storage.as_ref(), catalog.as_ref(), &table, base, now).await?;
+    info!(
+        "Expired {} snapshots of {}, deleting {} files",
+        expired.snapshot_ids.len(),
+        table,
+        expired.files.len()
+    );
+
+    let base = catalog.load_table(&table).await?;
+    let older_than_ms = now - orphan_min_age_hours * 60 * 60 * 1000;
+    let orphans = maintenance::delete_orphan_files(storage.as_ref(), &base, older_than_ms).await?;
+    info!("Deleted {} orphan files of {}", orphans.len(), table);
+
+    Ok(json!({
+        "rewritten_data_files": rewritten.rewritten_files,
+        "added_data_files": rewritten.added_files,
+        "expired_snapshots": expired.snapshot_ids.len(),
+        "deleted_files": expired.files.len(),
+        "deleted_orphan_files": orphans.len(),
+    }))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .without_time()
+        .init();
+
+    run(service_fn(function_handler)).await
+}
@@ -0,0 +1,184 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use tokio::fs;
+use uuid::Uuid;
+
+use crate::catalog::{Catalog, CommitConflict, LoadedTable, TableCreation, TableIdent};
+use crate::iceberg::metadata::TableMetadata;
+use crate::iceberg::update::TableUpdate;
+
+// Hadoop-style catalog: a table is the directory <warehouse>/<namespace>/<table>, its metadata
+// files are metadata/v<N>.metadata.json and metadata/version-hint.text holds the current N.
+// Committing creates v<N+1> exclusively, so two writers can't both commit on top of v<N>.
+
+const VERSION_HINT: &str = "version-hint.text";
+
+pub struct FileSystemCatalog {
+    warehouse: PathBuf,
+}
+
+impl FileSystemCatalog {
+    pub fn new(warehouse: impl Into<PathBuf>) -> Self {
+        FileSystemCatalog { warehouse: warehouse.into() }
+    }
+
+    fn metadata_dir(&self, table: &TableIdent) -> PathBuf {
+        self.warehouse.join(&table.namespace).join(&table.name).join("metadata")
+    }
+
+    async fn current_version(&self, metadata_dir: &Path) -> anyhow::Result<Option<i32>> {
+        match fs::read_to_string(metadata_dir.join(VERSION_HINT)).await {
+            Ok(hint) => Ok(Some(hint.trim().parse().context("Invalid version hint")?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes `metadata` as version `version`, failing with [`CommitConflict`] if that version
+    /// already exists, then points the version hint at it.
+    async fn write_version(&self, metadata_dir: &Path, version: i32, metadata: &TableMetadata) -> anyhow::Result<String> {
+        let path = metadata_dir.join(format!("v{}.metadata.json", version));
+        let temp_path = metadata_dir.join(format!(".{}.metadata.json.tmp", Uuid::new_v4()));
+        fs::write(&temp_path, serde_json::to_vec(metadata)?).await?;
+
+        // Hard links are never replaced, which makes them an atomic "create if absent"
+        let linked = fs::hard_link(&temp_path, &path).await;
+        fs::remove_file(&temp_path).await?;
+        match linked {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                return Err(CommitConflict(format!("{} was committed concurrently", path.display())).into())
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let temp_hint = metadata_dir.join(format!(".{}-{}", Uuid::new_v4(), VERSION_HINT));
+        fs::write(&temp_hint, version.to_string()).await?;
+        fs::rename(&temp_hint, metadata_dir.join(VERSION_HINT)).await?;
+        Ok(path.display().to_string())
+    }
+}
+
+fn version_of(metadata_location: &str) -> anyhow::Result<i32> {
+    metadata_location
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.strip_prefix('v'))
+        .and_then(|name| name.strip_suffix(".metadata.json"))
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(|| anyhow!("Not a versioned metadata file: {}", metadata_location))
+}
+
+#[async_trait]
+impl Catalog for FileSystemCatalog {
+    async fn load_table(&self, table: &TableIdent) -> anyhow::Result<LoadedTable> {
+        let metadata_dir = self.metadata_dir(table);
+        let version = self
+            .current_version(&metadata_dir)
+            .await?
+            .ok_or_else(|| anyhow!("Table {} not found in {}", table, self.warehouse.display()))?;
+        let path = metadata_dir.join(format!("v{}.metadata.json", version));
+        let bytes = fs::read(&path).await.with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(LoadedTable {
+            metadata_location: path.display().to_string(),
+            metadata: TableMetadata::from_slice(&bytes)?,
+        })
+    }
+
+    async fn commit_table(&self, table: &TableIdent, base: &LoadedTable, updates: Vec<TableUpdate>) -> anyhow::Result<LoadedTable> {
+        let metadata_dir = self.metadata_dir(table);
+        let base_version = version_of(&base.metadata_location)?;
+        if self.current_version(&metadata_dir).await? != Some(base_version) {
+            return Err(CommitConflict(format!("{} is no longer at version {}", table, base_version)).into());
+        }
+
+        let metadata = base.metadata.update(&updates, &base.metadata_location)?;
+        let metadata_location = self.write_version(&metadata_dir, base_version + 1, &metadata).await?;
+        Ok(LoadedTable { metadata_location, metadata })
+    }
+
+    async fn create_table(&self, table: &TableIdent, creation: TableCreation) -> anyhow::Result<LoadedTable> {
+        let metadata_dir = self.metadata_dir(table);
+        if self.current_version(&metadata_dir).await?.is_some() {
+            anyhow::bail!("Table {} already exists", table);
+        }
+        fs::create_dir_all(&metadata_dir).await?;
+
+        let location = match creation.location {
+            Some(location) => location,
+            None => self.warehouse.join(&table.namespace).join(&table.name).display().to_string(),
+        };
+        let metadata = TableMetadata::new(
+            location,
+            creation.schema,
+            creation.partition_spec,
+            creation.sort_order,
+            creation.properties,
+        );
+        let metadata_location = self.write_version(&metadata_dir, 1, &metadata).await?;
+        Ok(LoadedTable { metadata_location, metadata })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::iceberg::metadata::{Operation, Snapshot, Summary};
+    use crate::iceberg::partition::PartitionSpec;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+    use crate::iceberg::sort::SortOrder;
+
+    fn snapshot(snapshot_id: i64, sequence_number: i64) -> Snapshot {
+        Snapshot {
+            sequence_number,
+            snapshot_id,
+            parent_snapshot_id: None,
+            timestamp_ms: 0,
+            summary: Summary {
+                operation: Operation::Append,
+                properties: HashMap::new(),
+            },
+            manifest_list: format!("/tmp/snap-{}.avro", snapshot_id),
+            schema_id: Some(0),
+        }
+    }
+
+    #[tokio::test]
+    async fn commits_on_top_of_the_current_version_only() {
+        let warehouse = std::env::temp_dir().join(format!("dotsdb-{}", Uuid::new_v4()));
+        let catalog = FileSystemCatalog::new(&warehouse);
+        let ident = TableIdent::new("dotsdb", "books");
+        let creation = TableCreation {
+            schema: Schema::new(0, vec![NestedField::optional(1, "review_id", Type::Primitive(PrimitiveType::String))]),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::new(),
+        };
+
+        let created = catalog.create_table(&ident, creation.clone()).await.unwrap();
+        assert!(created.metadata_location.ends_with("metadata/v1.metadata.json"));
+        assert!(catalog.create_table(&ident, creation).await.is_err());
+
+        let base = catalog.load_table(&ident).await.unwrap();
+        let updates = vec![TableUpdate::AddSnapshot { snapshot: snapshot(7, 1) }, TableUpdate::set_main_branch(7)];
+        let committed = catalog.commit_table(&ident, &base, updates).await.unwrap();
+        assert!(committed.metadata_location.ends_with("metadata/v2.metadata.json"));
+
+        let stale = vec![TableUpdate::AddSnapshot { snapshot: snapshot(8, 1) }, TableUpdate::set_main_branch(8)];
+        let err = catalog.commit_table(&ident, &base, stale).await.unwrap_err();
+        assert!(err.is::<CommitConflict>());
+
+        let loaded = catalog.load_table(&ident).await.unwrap();
+        assert_eq!(loaded, committed);
+        assert_eq!(loaded.metadata.current_snapshot_id, Some(7));
+        assert_eq!(loaded.metadata.metadata_log[0].metadata_file, base.metadata_location);
+
+        std::fs::remove_dir_all(warehouse).unwrap();
+    }
+}
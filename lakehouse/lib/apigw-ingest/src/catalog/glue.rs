@@ -0,0 +1,330 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use aws_sdk_glue::model::{StorageDescriptor, Table, TableInput};
+use aws_sdk_glue::types::SdkError;
+use aws_sdk_glue::Client;
+use log::warn;
+use uuid::Uuid;
+
+use crate::catalog::{Catalog, CommitConflict, LoadedTable, TableCreation, TableIdent};
+use crate::error::sdk_error;
+use crate::iceberg::metadata::TableMetadata;
+use crate::iceberg::update::TableUpdate;
+use crate::storage::ObjectStore;
+
+// Iceberg tables registered in Glue keep a pointer to their current metadata.json in the
+// table parameters, the same way GlueCatalog in Handler.kt creates them.
+pub const METADATA_LOCATION: &str = "metadata_location";
+pub const PREVIOUS_METADATA_LOCATION: &str = "previous_metadata_location";
+
+/// The Glue calls a commit needs, so the commit path can run against a stand-in instead of AWS.
+#[async_trait]
+pub trait GlueApi: Send + Sync {
+    async fn get_table(&self, database: &str, table: &str) -> anyhow::Result<Table>;
+
+    async fn create_table(&self, database: &str, table_input: TableInput) -> anyhow::Result<()>;
+
+    /// Replaces the table definition. Fails with [`CommitConflict`] when `version_id` is given
+    /// and the table has been updated since that version was read.
+    async fn update_table(&self, database: &str, table_input: TableInput, version_id: Option<&str>) -> anyhow::Result<()>;
+}
+
+#[async_trait]
+impl GlueApi for Client {
+    async fn get_table(&self, database: &str, table: &str) -> anyhow::Result<Table> {
+        let output = self
+            .get_table()
+            .database_name(database)
+            .name(table)
+            .send()
+            .await
+            .map_err(sdk_error)
+            .with_context(|| format!("Failed to load Glue table {}.{}", database, table))?;
+
+        output
+            .table()
+            .cloned()
+            .ok_or_else(|| anyhow!("Glue table {}.{} not found", database, table))
+    }
+
+    async fn create_table(&self, database: &str, table_input: TableInput) -> anyhow::Result<()> {
+        let name = table_input.name().unwrap_or_default().to_string();
+        self.create_table()
+            .database_name(database)
+            .table_input(table_input)
+            .send()
+            .await
+            .map_err(sdk_error)
+            .with_context(|| format!("Failed to create Glue table {}.{}", database, name))?;
+        Ok(())
+    }
+
+    async fn update_table(&self, database: &str, table_input: TableInput, version_id: Option<&str>) -> anyhow::Result<()> {
+        let name = table_input.name().unwrap_or_default().to_string();
+        let result = self
+            .update_table()
+            .database_name(database)
+            .table_input(table_input)
+            .set_version_id(version_id.map(str::to_string))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(err)) if err.err().is_concurrent_modification_exception() => {
+                Err(CommitConflict(format!("Glue table {}.{} was updated concurrently", database, name)).into())
+            }
+            Err(err) => Err(sdk_error(err).context(format!("Failed to update Glue table {}.{}", database, name))),
+        }
+    }
+}
+
+pub fn metadata_location(table: &Table) -> anyhow::Result<String> {
+    table
+        .parameters()
+        .and_then(|p| p.get(METADATA_LOCATION))
+        .cloned()
+        .ok_or_else(|| anyhow!("Glue table {} is not an Iceberg table", table.name().unwrap_or_default()))
+}
+
+/// Points the Glue table at `new_metadata_location`, but only if it still points at
+/// `expected_metadata_location`, keeping every other table setting as-is.
+///
+/// The update is pinned to the Glue table version that was checked, so a writer that swaps
+/// the pointer in between makes this fail with [`CommitConflict`] rather than being overwritten.
+pub async fn swap_metadata_location(
+    glue: &dyn GlueApi,
+    database: &str,
+    table_name: &str,
+    expected_metadata_location: &str,
+    new_metadata_location: &str,
+) -> anyhow::Result<()> {
+    let table = glue.get_table(database, table_name).await?;
+    let current = metadata_location(&table)?;
+    if current != expected_metadata_location {
+        return Err(CommitConflict(format!(
+            "Glue table {}.{} points at {}, expected {}",
+            database, table_name, current, expected_metadata_location
+        ))
+        .into());
+    }
+
+    let mut parameters = table.parameters().cloned().unwrap_or_default();
+    parameters.insert(PREVIOUS_METADATA_LOCATION.to_string(), current);
+    parameters.insert(METADATA_LOCATION.to_string(), new_metadata_location.to_string());
+
+    let table_input = TableInput::builder()
+        .set_name(table.name().map(str::to_string))
+        .set_description(table.description().map(str::to_string))
+        .set_owner(table.owner().map(str::to_string))
+        .retention(table.retention())
+        .set_storage_descriptor(table.storage_descriptor().cloned())
+        .set_partition_keys(table.partition_keys().map(|k| k.to_vec()))
+        .set_table_type(table.table_type().map(str::to_string))
+        .set_parameters(Some(parameters))
+        .build();
+
+    glue.update_table(database, table_input, table.version_id()).await
+}
+
+/// Iceberg tables registered in Glue, with their metadata files in S3, like
+/// DotsDBGlueCatalog in Handler.kt.
+pub struct GlueCatalog {
+    glue: Box<dyn GlueApi>,
+    storage: Arc<dyn ObjectStore>,
+    warehouse: Option<String>,
+}
+
+impl GlueCatalog {
+    /// `warehouse` is where tables created without an explicit location go, as `<warehouse>/<db>.db/<table>`.
+    pub fn new(glue: impl GlueApi + 'static, storage: Arc<dyn ObjectStore>, warehouse: Option<String>) -> Self {
+        GlueCatalog {
+            glue: Box::new(glue),
+            storage,
+            warehouse,
+        }
+    }
+
+    async fn write_metadata(&self, location: &str, metadata: &TableMetadata) -> anyhow::Result<()> {
+        self.storage.put(location, serde_json::to_vec(metadata)?).await
+    }
+}
+
+#[async_trait]
+impl Catalog for GlueCatalog {
+    async fn load_table(&self, table: &TableIdent) -> anyhow::Result<LoadedTable> {
+        let glue_table = self.glue.get_table(&table.namespace, &table.name).await?;
+        let metadata_location = metadata_location(&glue_table)?;
+        let metadata = TableMetadata::from_slice(&self.storage.get(&metadata_location).await?)?;
+        Ok(LoadedTable { metadata_location, metadata })
+    }
+
+    async fn commit_table(&self, table: &TableIdent, base: &LoadedTable, updates: Vec<TableUpdate>) -> anyhow::Result<LoadedTable> {
+        let metadata = base.metadata.update(&updates, &base.metadata_location)?;
+        let metadata_location = base.metadata.next_metadata_location(&base.metadata_location);
+        self.write_metadata(&metadata_location, &metadata).await?;
+
+        let swapped = swap_metadata_location(
+            self.glue.as_ref(),
+            &table.namespace,
+            &table.name,
+            &base.metadata_location,
+            &metadata_location,
+        )
+        .await;
+        if let Err(err) = swapped {
+            // Nothing references the new metadata file if the swap lost, so don't leave it behind
+            if err.is::<CommitConflict>() {
+                if let Err(err) = self.storage.delete(&metadata_location).await {
+                    warn!("Failed to clean up {}: {}", metadata_location, err);
+                }
+            }
+            return Err(err);
+        }
+
+        Ok(LoadedTable { metadata_location, metadata })
+    }
+
+    async fn create_table(&self, table: &TableIdent, creation: TableCreation) -> anyhow::Result<LoadedTable> {
+        let location = match (creation.location, &self.warehouse) {
+            (Some(location), _) => location,
+            (None, Some(warehouse)) => format!("{}/{}.db/{}", warehouse.trim_end_matches('/'), table.namespace, table.name),
+            (None, None) => anyhow::bail!("No location given for {} and the catalog has no warehouse", table),
+        };
+        let metadata = TableMetadata::new(
+            location.clone(),
+            creation.schema,
+            creation.partition_spec,
+            creation.sort_order,
+            creation.properties,
+        );
+        let metadata_location = format!("{}/00000-{}.metadata.json", metadata.metadata_dir(), Uuid::new_v4());
+        self.write_metadata(&metadata_location, &metadata).await?;
+
+        let table_input = TableInput::builder()
+            .name(&table.name)
+            .table_type("EXTERNAL_TABLE")
+            .parameters("table_type", "ICEBERG")
+            .parameters(METADATA_LOCATION, &metadata_location)
+            .storage_descriptor(StorageDescriptor::builder().location(location).build())
+            .build();
+        self.glue.create_table(&table.namespace, table_input).await?;
+
+        Ok(LoadedTable { metadata_location, metadata })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// A single-database Glue stand-in that versions tables the way Glue does.
+    #[derive(Default)]
+    pub(crate) struct LocalGlue {
+        tables: Mutex<HashMap<String, Table>>,
+    }
+
+    impl LocalGlue {
+        pub(crate) fn with_table(name: &str, metadata_location: &str) -> Self {
+            let table = Table::builder()
+                .name(name)
+                .table_type("EXTERNAL_TABLE")
+                .parameters("table_type", "ICEBERG")
+                .parameters(METADATA_LOCATION, metadata_location)
+                .version_id("1")
+                .build();
+            let glue = LocalGlue::default();
+            glue.tables.lock().unwrap().insert(name.to_string(), table);
+            glue
+        }
+    }
+
+    #[async_trait]
+    impl GlueApi for LocalGlue {
+        async fn get_table(&self, _database: &str, table: &str) -> anyhow::Result<Table> {
+            let tables = self.tables.lock().unwrap();
+            tables.get(table).cloned().ok_or_else(|| anyhow!("Table {} not found", table))
+        }
+
+        async fn create_table(&self, _database: &str, table_input: TableInput) -> anyhow::Result<()> {
+            let mut tables = self.tables.lock().unwrap();
+            let name = table_input.name().unwrap_or_default().to_string();
+            if tables.contains_key(&name) {
+                anyhow::bail!("Table {} already exists", name);
+            }
+            let table = Table::builder()
+                .name(&name)
+                .set_table_type(table_input.table_type().map(str::to_string))
+                .set_parameters(table_input.parameters().cloned())
+                .version_id("1")
+                .build();
+            tables.insert(name, table);
+            Ok(())
+        }
+
+        async fn update_table(&self, _database: &str, table_input: TableInput, version_id: Option<&str>) -> anyhow::Result<()> {
+            let mut tables = self.tables.lock().unwrap();
+            let name = table_input.name().unwrap_or_default().to_string();
+            let table = tables.get_mut(&name).ok_or_else(|| anyhow!("Table {} not found", name))?;
+            let version = table.version_id().unwrap_or_default().to_string();
+            if version_id.is_some_and(|v| v != version) {
+                return Err(CommitConflict(format!("{} is no longer at version {:?}", name, version_id)).into());
+            }
+            *table = Table::builder()
+                .set_name(table_input.name().map(str::to_string))
+                .set_table_type(table_input.table_type().map(str::to_string))
+                .set_parameters(table_input.parameters().cloned())
+                .version_id((version.parse::<i64>()? + 1).to_string())
+                .build();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn swaps_only_from_the_expected_location() {
+        let glue = LocalGlue::with_table("books", "s3://bucket/books/metadata/00000-a.metadata.json");
+
+        swap_metadata_location(
+            &glue,
+            "dotsdb",
+            "books",
+            "s3://bucket/books/metadata/00000-a.metadata.json",
+            "s3://bucket/books/metadata/00001-b.metadata.json",
+        )
+        .await
+        .unwrap();
+
+        let stale = swap_metadata_location(
+            &glue,
+            "dotsdb",
+            "books",
+            "s3://bucket/books/metadata/00000-a.metadata.json",
+            "s3://bucket/books/metadata/00001-c.metadata.json",
+        )
+        .await
+        .unwrap_err();
+        assert!(stale.is::<CommitConflict>());
+
+        let table = glue.get_table("dotsdb", "books").await.unwrap();
+        let parameters = table.parameters().unwrap();
+        assert_eq!(parameters[METADATA_LOCATION], "s3://bucket/books/metadata/00001-b.metadata.json");
+        assert_eq!(parameters[PREVIOUS_METADATA_LOCATION], "s3://bucket/books/metadata/00000-a.metadata.json");
+        assert_eq!(parameters["table_type"], "ICEBERG");
+    }
+
+    #[tokio::test]
+    async fn rejects_updates_to_a_stale_version() {
+        let glue = LocalGlue::with_table("books", "s3://bucket/books/metadata/00000-a.metadata.json");
+        let table = glue.get_table("dotsdb", "books").await.unwrap();
+        let input = TableInput::builder().name("books").build();
+
+        glue.update_table("dotsdb", input.clone(), table.version_id()).await.unwrap();
+        let err = glue.update_table("dotsdb", input, table.version_id()).await.unwrap_err();
+        assert!(err.is::<CommitConflict>());
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::{env, fmt};
+
+use async_trait::async_trait;
+
+use crate::iceberg::metadata::TableMetadata;
+use crate::iceberg::partition::PartitionSpec;
+use crate::iceberg::schema::Schema;
+use crate::iceberg::sort::SortOrder;
+use crate::iceberg::update::TableUpdate;
+use crate::storage::ObjectStore;
+
+pub mod filesystem;
+pub mod glue;
+pub mod rest;
+
+// Catalogs track where each table's current metadata.json is and swap it atomically on commit.
+// The lakehouse itself uses Glue (see DotsDBGlueCatalog in Handler.kt); the REST and filesystem
+// catalogs let the same ingest code run against a local setup without AWS.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableIdent {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl TableIdent {
+    pub fn new(namespace: impl Into<String>, name: impl Into<String>) -> Self {
+        TableIdent {
+            namespace: namespace.into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl fmt::Display for TableIdent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.namespace, self.name)
+    }
+}
+
+/// A table's metadata together with the file it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedTable {
+    pub metadata_location: String,
+    pub metadata: TableMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableCreation {
+    pub schema: Schema,
+    pub partition_spec: PartitionSpec,
+    pub sort_order: SortOrder,
+    /// Defaults to a location under the catalog's warehouse
+    pub location: Option<String>,
+    pub properties: HashMap<String, String>,
+}
+
+#[async_trait]
+pub trait Catalog: Send + Sync {
+    async fn load_table(&self, table: &TableIdent) -> anyhow::Result<LoadedTable>;
+
+    /// Applies `updates` on top of `base` and makes the result the table's current metadata.
+    /// Fails with [`CommitConflict`] if the table no longer is at `base`.
+    async fn commit_table(&self, table: &TableIdent, base: &LoadedTable, updates: Vec<TableUpdate>) -> anyhow::Result<LoadedTable>;
+
+    async fn create_table(&self, table: &TableIdent, creation: TableCreation) -> anyhow::Result<LoadedTable>;
+}
+
+/// Another writer committed to the table first; the commit should be rebased and retried.
+#[derive(Debug)]
+pub struct CommitConflict(pub String);
+
+impl fmt::Display for CommitConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Commit conflict: {}", self.0)
+    }
+}
+
+impl std::error::Error for CommitConflict {}
+
+/// The catalog failed during a commit, after which the table may or may not hold the new
+/// snapshot. Retrying could apply the same changes twice.
+#[derive(Debug)]
+pub struct CommitStateUnknown(pub anyhow::Error);
+
+impl fmt::Display for CommitStateUnknown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Commit state unknown: {:#}", self.0)
+    }
+}
+
+impl std::error::Error for CommitStateUnknown {}
+
+/// The catalog selected by `DOTSDB_CATALOG`: `glue` (the default), `rest` (at `DOTSDB_CATALOG_URI`,
+/// optionally authenticated with `DOTSDB_CATALOG_TOKEN`) or `filesystem` (a directory, `DOTSDB_WAREHOUSE`).
+pub async fn from_env(storage: Arc<dyn ObjectStore>) -> anyhow::Result<Box<dyn Catalog>> {
+    let warehouse = env::var("DOTSDB_WAREHOUSE").ok();
+    let catalog: Box<dyn Catalog> = match env::var("DOTSDB_CATALOG").as_deref().unwrap_or("glue") {
+        "glue" => Box::new(glue::GlueCatalog::new(
+            aws_sdk_glue::Client::new(&aws_config::load_from_env().await),
+            storage,
+            warehouse,
+        )),
+        "rest" => Box::new(rest::RestCatalog::new(
+            &env::var("DOTSDB_CATALOG_URI")?,
+            env::var("DOTSDB_CATALOG_TOKEN").ok(),
+        )?),
+        "filesystem" => Box::new(filesystem::FileSystemCatalog::new(
+            warehouse.ok_or_else(|| anyhow::anyhow!("DOTSDB_WAREHOUSE is required for the filesystem catalog"))?,
+        )),
+        other => anyhow::bail!("Unknown catalog type {}", other),
+    };
+    Ok(catalog)
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::{Catalog, CommitConflict, LoadedTable, TableCreation, TableIdent};
+use crate::error::Transient;
+use crate::iceberg::metadata::TableMetadata;
+use crate::iceberg::partition::PartitionSpec;
+use crate::iceberg::schema::Schema;
+use crate::iceberg::sort::SortOrder;
+use crate::iceberg::update::{TableRequirement, TableUpdate};
+
+// Iceberg REST catalog client - https://github.com/apache/iceberg/blob/main/open-api/rest-catalog-open-api.yaml
+// The server owns the metadata files; commits send the updates plus the requirements that
+// make the server reject them if someone else committed first.
+
+pub struct RestCatalog {
+    client: reqwest::Client,
+    uri: Url,
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LoadTableResult {
+    metadata_location: Option<String>,
+    metadata: TableMetadata,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CreateTableRequest {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+    schema: Schema,
+    partition_spec: PartitionSpec,
+    write_order: SortOrder,
+    properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommitTableRequest {
+    requirements: Vec<TableRequirement>,
+    updates: Vec<TableUpdate>,
+}
+
+impl RestCatalog {
+    pub fn new(uri: &str, token: Option<String>) -> anyhow::Result<Self> {
+        // A trailing slash keeps the last path segment when joining, e.g. https://host/catalog/
+        let uri = Url::parse(&format!("{}/", uri.trim_end_matches('/'))).context("Invalid catalog URI")?;
+        Ok(RestCatalog {
+            client: reqwest::Client::new(),
+            uri,
+            token,
+        })
+    }
+
+    fn url(&self, table: &TableIdent, table_path: bool) -> anyhow::Result<Url> {
+        let mut url = self.uri.join("v1/namespaces")?;
+        {
+            let mut segments = url.path_segments_mut().map_err(|_| anyhow!("Invalid catalog URI {}", self.uri))?;
+            segments.push(&table.namespace).push("tables");
+            if table_path {
+                segments.push(&table.name);
+            }
+        }
+        Ok(url)
+    }
+
+    async fn send<T: Serialize>(&self, method: Method, url: Url, body: Option<&T>) -> anyhow::Result<LoadedTable> {
+        let mut request = self.client.request(method, url.clone());
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| {
+                if err.is_timeout() || err.is_connect() || err.is_request() {
+                    Transient(err.into()).into()
+                } else {
+                    anyhow::Error::new(err)
+                }
+            })
+            .with_context(|| format!("Request to {} failed", url))?;
+        let status = response.status();
+        if status == StatusCode::CONFLICT {
+            return Err(CommitConflict(response.text().await.unwrap_or_default()).into());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let err = anyhow!("Catalog returned {} for {}: {}", status, url, body);
+            // Server errors are usually temporary, unlike a table that doesn't exist
+            return Err(if status.is_server_error() { Transient(err.into()).into() } else { err });
+        }
+
+        let result: LoadTableResult = response.json().await.with_context(|| format!("Invalid response from {}", url))?;
+        Ok(LoadedTable {
+            metadata_location: result
+                .metadata_location
+                .ok_or_else(|| anyhow!("Catalog returned no metadata location for {}", url))?,
+            metadata: result.metadata,
+        })
+    }
+}
+
+#[async_trait]
+impl Catalog for RestCatalog {
+    async fn load_table(&self, table: &TableIdent) -> anyhow::Result<LoadedTable> {
+        self.send::<()>(Method::GET, self.url(table, true)?, None).await
+    }
+
+    async fn commit_table(&self, table: &TableIdent, base: &LoadedTable, updates: Vec<TableUpdate>) -> anyhow::Result<LoadedTable> {
+        let request = CommitTableRequest {
+            requirements: TableRequirement::for_base(&base.metadata),
+            updates,
+        };
+        self.send(Method::POST, self.url(table, true)?, Some(&request)).await
+    }
+
+    async fn create_table(&self, table: &TableIdent, creation: TableCreation) -> anyhow::Result<LoadedTable> {
+        let request = CreateTableRequest {
+            name: table.name.clone(),
+            location: creation.location,
+            schema: creation.schema,
+            partition_spec: creation.partition_spec,
+            write_order: creation.sort_order,
+            properties: creation.properties,
+        };
+        self.send(Method::POST, self.url(table, false)?, Some(&request)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::error::is_transient;
+    use crate::testing::MockServer;
+
+    #[tokio::test]
+    async fn commits_with_the_requirements_of_the_base() {
+        let schema = Schema::new(0, vec![]);
+        let metadata = TableMetadata::new(
+            "s3://bucket/dotsdb.db/books".to_string(),
+            schema,
+            PartitionSpec::unpartitioned(),
+            SortOrder::unsorted(),
+            HashMap::new(),
+        );
+        let base = LoadedTable {
+            metadata_location: "s3://bucket/dotsdb.db/books/metadata/v1.metadata.json".to_string(),
+            metadata: metadata.clone(),
+        };
+        let committed = json!({ "metadata-location": "s3://bucket/dotsdb.db/books/metadata/v2.metadata.json", "metadata": metadata });
+        let server = MockServer::with_responses(vec![
+            (200, committed.to_string()),
+            (409, "Requirement failed: branch main was created concurrently".to_string()),
+            (503, "Slow down".to_string()),
+            (200, json!({ "metadata": metadata }).to_string()),
+        ])
+        .await;
+        let catalog = RestCatalog::new(&format!("{}/catalog", server.uri), Some("secret".to_string())).unwrap();
+        let table = TableIdent::new("dotsdb", "books");
+        let updates = || vec![TableUpdate::set_main_branch(42)];
+
+        let loaded = catalog.commit_table(&table, &base, updates()).await.unwrap();
+        assert_eq!(loaded.metadata_location, "s3://bucket/dotsdb.db/books/metadata/v2.metadata.json");
+        let request = &server.received()[0];
+        assert_eq!(request.request_line, "POST /catalog/v1/namespaces/dotsdb/tables/books HTTP/1.1");
+        assert!(request.headers.contains("authorization: bearer secret"));
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&request.body).unwrap(),
+            json!({
+                "requirements": TableRequirement::for_base(&metadata),
+                "updates": updates(),
+            })
+        );
+
+        // Someone else committed first
+        let conflict = catalog.commit_table(&table, &base, updates()).await.unwrap_err();
+        assert!(conflict.is::<CommitConflict>());
+
+        // Server errors are worth retrying, answers without a metadata location aren't
+        let unavailable = catalog.commit_table(&table, &base, updates()).await.unwrap_err();
+        assert!(is_transient(&unavailable));
+        let invalid = catalog.commit_table(&table, &base, updates()).await.unwrap_err();
+        assert!(!is_transient(&invalid) && invalid.to_string().contains("no metadata location"));
+        assert_eq!(server.received().len(), 4);
+    }
+
+    #[test]
+    fn builds_table_urls() {
+        let catalog = RestCatalog::new("http://localhost:8181/catalog", None).unwrap();
+        let ident = TableIdent::new("dotsdb", "books");
+        assert_eq!(
+            catalog.url(&ident, true).unwrap().as_str(),
+            "http://localhost:8181/catalog/v1/namespaces/dotsdb/tables/books"
+        );
+        assert_eq!(
+            catalog.url(&ident, false).unwrap().as_str(),
+            "http://localhost:8181/catalog/v1/namespaces/dotsdb/tables"
+        );
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use log::warn;
+use uuid::Uuid;
+
+use crate::catalog::{Catalog, CommitConflict, CommitStateUnknown, LoadedTable, TableIdent};
+use crate::iceberg::manifest::{DataContentType, DataFile, ManifestEntry, ManifestStatus, ManifestWriter};
+use crate::iceberg::manifest_list::{read_manifest_list, write_manifest_list, ManifestContent, ManifestFile};
+use crate::iceberg::metadata::{generate_snapshot_id, now_ms, Operation, Snapshot, TableMetadata};
+use crate::iceberg::scan::read_manifest_entries;
+use crate::iceberg::snapshot::SummaryBuilder;
+use crate::iceberg::update::TableUpdate;
+use crate::storage::ObjectStore;
+
+// TELL ICEBERG THAT DATA WAS INSERTED PER SPEC - https://iceberg.apache.org/spec/#specification
+// 1. Write a manifest that references the new data files, and one for new delete files
+// 2. Write a manifest list with the new manifest plus every manifest of the parent snapshot,
+//    rewriting those that hold files the snapshot removes with the files marked as deleted
+// 3. Add a snapshot pointing at the manifest list and write the next metadata.json
+// 4. Have the catalog make it the current metadata, if nobody else committed in the meantime
+//
+// When another invocation wins the race in step 4, steps 2-4 are redone on top of its
+// metadata. The manifests from step 1 are reused: their entries inherit the sequence number
+// from the manifest list, so it is valid on top of any parent. Commits that were worked out
+// from existing data files, like position deletes or compaction, are only rebased if the files
+// are still live. Rewritten files keep the sequence number they were read at - see rewrite_files.
+
+/// How often and how patiently a conflicting commit is retried, configured with the same
+/// `commit.retry.*` table properties as the Java implementation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub num_retries: u32,
+    pub min_wait: Duration,
+    pub max_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            num_retries: 4,
+            min_wait: Duration::from_millis(100),
+            max_wait: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let default = RetryPolicy::default();
+        let property = |key: &str| properties.get(key).and_then(|v| v.parse::<u64>().ok());
+        RetryPolicy {
+            num_retries: property("commit.retry.num-retries").map_or(default.num_retries, |v| v as u32),
+            min_wait: property("commit.retry.min-wait-ms").map_or(default.min_wait, Duration::from_millis),
+            max_wait: property("commit.retry.max-wait-ms").map_or(default.max_wait, Duration::from_millis),
+        }
+    }
+
+    /// Exponential backoff for the given retry (starting at 1), capped at `max_wait`, with up
+    /// to 10% jitter so concurrent invocations don't retry in lockstep.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.min_wait.saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        let wait = exponential.min(self.max_wait);
+        let jitter_ms = (wait.as_millis() / 10) as u64;
+        let jitter = match jitter_ms {
+            0 => 0,
+            _ => (Uuid::new_v4().as_u128() % jitter_ms as u128) as u64,
+        };
+        wait + Duration::from_millis(jitter)
+    }
+}
+
+/// Commits `data_files` to the table as a new `append` snapshot on top of `base`, or on top of
+/// the latest metadata if other commits got in first, returning the snapshot.
+pub async fn append_files(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    base: LoadedTable,
+    data_files: Vec<DataFile>,
+) -> anyhow::Result<Snapshot> {
+    let changes = Changes {
+        operation: Operation::Append,
+        data_files,
+        delete_files: vec![],
+        referenced_data_files: vec![],
+        removed_data_files: vec![],
+        starting_sequence_number: None,
+    };
+    commit_files(storage, catalog, table, base, changes).await
+}
+
+/// Commits `data_files` and `delete_files` together, like a Java `RowDelta`: an `overwrite`
+/// snapshot, or a `delete` snapshot if there are only delete files. The deletes get the same
+/// sequence number as the new data files, so they only apply to rows committed before.
+///
+/// `referenced_data_files` are the paths of data files the deletes were worked out from, such
+/// as the files position deletes point into. The commit fails with a [`CommitConflict`] rather
+/// than being rebased if a concurrent commit removed any of them.
+pub async fn row_delta(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    base: LoadedTable,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+    referenced_data_files: Vec<String>,
+) -> anyhow::Result<Snapshot> {
+    let operation = match data_files.is_empty() {
+        true => Operation::Delete,
+        false => Operation::Overwrite,
+    };
+    let changes = Changes {
+        operation,
+        data_files,
+        delete_files,
+        referenced_data_files,
+        removed_data_files: vec![],
+        starting_sequence_number: None,
+    };
+    commit_files(storage, catalog, table, base, changes).await
+}
+
+/// Commits `data_files` in place of `removed_data_files` as a `replace` snapshot, like a Java
+/// `RewriteFiles`. The new files hold the rows of the removed ones, read from the snapshot with
+/// `starting_sequence_number`, and keep it as their data sequence number so that equality deletes
+/// committed since still apply to them.
+///
+/// The commit fails with a [`CommitConflict`] if a concurrent commit removed one of the files, or
+/// added position deletes to their partitions, as those would be lost.
+pub async fn rewrite_files(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    base: LoadedTable,
+    removed_data_files: Vec<DataFile>,
+    data_files: Vec<DataFile>,
+    starting_sequence_number: i64,
+) -> anyhow::Result<Snapshot> {
+    let changes = Changes {
+        operation: Operation::Replace,
+        data_files,
+        delete_files: vec![],
+        referenced_data_files: vec![],
+        removed_data_files,
+        starting_sequence_number: Some(starting_sequence_number),
+    };
+    commit_files(storage, catalog, table, base, changes).await
+}
+
+/// What a new snapshot changes.
+struct Changes {
+    operation: Operation,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+    /// Data files the changes were worked out from, which have to stay live
+    referenced_data_files: Vec<String>,
+    /// Live data files the snapshot removes
+    removed_data_files: Vec<DataFile>,
+    /// The sequence number of the snapshot rewritten data files were read from
+    starting_sequence_number: Option<i64>,
+}
+
+async fn commit_files(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    mut base: LoadedTable,
+    changes: Changes,
+) -> anyhow::Result<Snapshot> {
+    let Changes {
+        operation,
+        data_files,
+        delete_files,
+        referenced_data_files,
+        removed_data_files,
+        starting_sequence_number,
+    } = changes;
+    let snapshot_id = generate_snapshot_id();
+    let commit_uuid = Uuid::new_v4();
+    let schema = base.metadata.current_schema()?;
+    let spec = base.metadata.default_spec()?;
+
+    // A data manifest for the data files and a delete manifest for the delete files
+    let mut summary = SummaryBuilder::new();
+    for file in &removed_data_files {
+        summary.remove_file(file);
+    }
+    let mut manifests = vec![];
+    if !data_files.is_empty() || (delete_files.is_empty() && removed_data_files.is_empty()) {
+        manifests.push((ManifestWriter::new(snapshot_id, schema, spec), data_files));
+    }
+    if !delete_files.is_empty() {
+        manifests.push((ManifestWriter::deletes(snapshot_id, schema, spec), delete_files));
+    }
+    let mut manifest_files = vec![];
+    for (i, (mut manifest, files)) in manifests.into_iter().enumerate() {
+        for file in files {
+            summary.add_file(&file);
+            match starting_sequence_number {
+                Some(sequence_number) => manifest.add_with_sequence_number(file, sequence_number),
+                None => manifest.add(file),
+            }
+        }
+        let manifest_bytes = manifest.to_bytes()?;
+        let manifest_location = format!("{}/{}-m{}.avro", base.metadata.metadata_dir(), commit_uuid, i);
+        manifest_files.push(manifest.manifest_file(manifest_location.clone(), manifest_bytes.len() as i64)?);
+        storage.put(&manifest_location, manifest_bytes).await?;
+    }
+
+    let retry_policy = RetryPolicy::from_properties(&base.metadata.properties);
+    let mut attempt = 1;
+    loop {
+        let pending = PendingSnapshot {
+            snapshot_id,
+            commit_uuid,
+            attempt,
+            manifest_files: manifest_files.clone(),
+            operation,
+            summary: &summary,
+            removed_data_files: &removed_data_files,
+            starting_sequence_number,
+        };
+        let result = pending.commit(storage, catalog, table, &base).await;
+
+        match result {
+            Err(err) if err.is::<CommitConflict>() && attempt <= retry_policy.num_retries => {
+                let wait = retry_policy.backoff(attempt);
+                warn!("{}, retrying in {:?}", err, wait);
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+                base = catalog.load_table(table).await?;
+                validate_data_files_live(storage, &base, &referenced_data_files).await?;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Fails with a [`CommitConflict`] if one of `data_files` isn't live in the current snapshot of
+/// `base` anymore, e.g. because compaction rewrote it, so deletes pointing into it would be lost.
+async fn validate_data_files_live(
+    storage: &dyn ObjectStore,
+    base: &LoadedTable,
+    data_files: &[String],
+) -> anyhow::Result<()> {
+    if data_files.is_empty() {
+        return Ok(());
+    }
+    let mut live = HashSet::new();
+    if let Some(snapshot) = base.metadata.current_snapshot() {
+        let manifests = read_manifest_list(&storage.get(&snapshot.manifest_list).await?)?;
+        for manifest in manifests.iter().filter(|manifest| manifest.content == ManifestContent::Data) {
+            for entry in read_manifest_entries(storage, &base.metadata, manifest).await? {
+                if entry.status != ManifestStatus::Deleted {
+                    live.insert(entry.data_file.file_path);
+                }
+            }
+        }
+    }
+    match data_files.iter().find(|data_file| !live.contains(*data_file)) {
+        Some(removed) => Err(CommitConflict(format!("{} was removed by a concurrent commit", removed)).into()),
+        None => Ok(()),
+    }
+}
+
+/// A snapshot whose manifests are written, waiting to be added on top of the latest metadata.
+struct PendingSnapshot<'a> {
+    snapshot_id: i64,
+    commit_uuid: Uuid,
+    attempt: u32,
+    manifest_files: Vec<ManifestFile>,
+    operation: Operation,
+    summary: &'a SummaryBuilder,
+    removed_data_files: &'a [DataFile],
+    starting_sequence_number: Option<i64>,
+}
+
+impl PendingSnapshot<'_> {
+    async fn commit(
+        self,
+        storage: &dyn ObjectStore,
+        catalog: &dyn Catalog,
+        table: &TableIdent,
+        base: &LoadedTable,
+    ) -> anyhow::Result<Snapshot> {
+        let base_metadata = &base.metadata;
+        let parent = base_metadata.current_snapshot();
+        let manifests = match parent {
+            Some(parent) => read_manifest_list(&storage.get(&parent.manifest_list).await?)?,
+            None => vec![],
+        };
+        let (rewritten, kept) = self.remove_data_files(storage, base_metadata, manifests).await?;
+        let sequence_number = base_metadata.next_sequence_number();
+        let mut written = rewritten.iter().map(|m| m.manifest_path.clone()).collect::<Vec<_>>();
+        let mut manifests = self.manifest_files;
+        manifests.extend(rewritten);
+        for manifest_file in &mut manifests {
+            manifest_file.sequence_number = sequence_number;
+            manifest_file.min_sequence_number = match manifest_file.min_sequence_number {
+                -1 => sequence_number,
+                min_sequence_number => min_sequence_number.min(sequence_number),
+            };
+        }
+        manifests.extend(kept);
+
+        let manifest_list_location = format!(
+            "{}/snap-{}-{}-{}.avro",
+            base_metadata.metadata_dir(),
+            self.snapshot_id,
+            self.attempt,
+            self.commit_uuid
+        );
+        let parent_snapshot_id = parent.map(|p| p.snapshot_id);
+        let manifest_list = write_manifest_list(self.snapshot_id, parent_snapshot_id, sequence_number, &manifests)?;
+        storage.put(&manifest_list_location, manifest_list).await?;
+
+        let summary = self.summary.build(self.operation, parent);
+        let snapshot = Snapshot {
+            sequence_number,
+            snapshot_id: self.snapshot_id,
+            parent_snapshot_id,
+            timestamp_ms: now_ms(),
+            summary,
+            manifest_list: manifest_list_location.clone(),
+            schema_id: Some(base_metadata.current_schema_id),
+        };
+
+        let updates = vec![
+            TableUpdate::AddSnapshot { snapshot: snapshot.clone() },
+            TableUpdate::set_main_branch(self.snapshot_id),
+        ];
+        match catalog.commit_table(table, base, updates).await {
+            Ok(_) => Ok(snapshot),
+            Err(err) if err.is::<CommitConflict>() => {
+                // Nothing references the manifest list or rewritten manifests if the commit lost,
+                // so don't leave them behind
+                written.push(manifest_list_location);
+                for location in written {
+                    if let Err(err) = storage.delete(&location).await {
+                        warn!("Failed to clean up {}: {}", location, err);
+                    }
+                }
+                Err(err)
+            }
+            // The catalog may have swapped the metadata before failing, e.g. with a timeout. Only
+            // a snapshot found in the table is known to be committed, and nothing is cleaned up.
+            Err(err) => match catalog.load_table(table).await {
+                Ok(current) if current.metadata.snapshots.iter().any(|s| s.snapshot_id == self.snapshot_id) => {
+                    warn!("Committed snapshot {} despite: {:#}", self.snapshot_id, err);
+                    Ok(snapshot)
+                }
+                _ => Err(CommitStateUnknown(err).into()),
+            },
+        }
+    }
+
+    /// Splits the parent's `manifests` into rewritten copies of those holding removed data files,
+    /// with the files marked as deleted by this snapshot, and those kept as they are. Fails with
+    /// a [`CommitConflict`] if a removed file isn't live anymore, or position deletes committed
+    /// after the starting snapshot may apply to it.
+    async fn remove_data_files(
+        &self,
+        storage: &dyn ObjectStore,
+        metadata: &TableMetadata,
+        manifests: Vec<ManifestFile>,
+    ) -> anyhow::Result<(Vec<ManifestFile>, Vec<ManifestFile>)> {
+        if self.removed_data_files.is_empty() {
+            return Ok((vec![], manifests));
+        }
+        let removed = self.removed_data_files.iter().map(|f| f.file_path.as_str()).collect::<HashSet<_>>();
+        let is_removed = |entry: &ManifestEntry| {
+            entry.status != ManifestStatus::Deleted && removed.contains(entry.data_file.file_path.as_str())
+        };
+        let mut to_rewrite = vec![];
+        let mut kept = vec![];
+        let mut found = HashSet::new();
+        for manifest in manifests {
+            let entries = read_manifest_entries(storage, metadata, &manifest).await?;
+            if manifest.content == ManifestContent::Deletes {
+                let new_deletes = entries.iter().find(|entry| {
+                    entry.status != ManifestStatus::Deleted
+                        && entry.data_file.content == DataContentType::PositionDeletes
+                        && self
+                            .starting_sequence_number
+                            .is_none_or(|starting| entry.sequence_number.is_none_or(|s| s > starting))
+                        && self.removed_data_files.iter().any(|f| f.partition == entry.data_file.partition)
+                });
+                if let Some(entry) = new_deletes {
+                    let message = format!("{} was added by a concurrent commit", entry.data_file.file_path);
+                    return Err(CommitConflict(message).into());
+                }
+                kept.push(manifest);
+            } else if entries.iter().any(is_removed) {
+                found.extend(entries.iter().filter(|e| is_removed(e)).map(|e| e.data_file.file_path.clone()));
+                to_rewrite.push((manifest, entries));
+            } else {
+                kept.push(manifest);
+            }
+        }
+        if let Some(missing) = removed.iter().find(|path| !found.contains(**path)) {
+            return Err(CommitConflict(format!("{} was removed by a concurrent commit", missing)).into());
+        }
+
+        let schema = metadata.current_schema()?;
+        let mut rewritten = vec![];
+        for (i, (manifest, entries)) in to_rewrite.into_iter().enumerate() {
+            let spec = metadata
+                .partition_specs
+                .iter()
+                .find(|spec| spec.spec_id == manifest.partition_spec_id)
+                .ok_or_else(|| anyhow!("Partition spec {} not found", manifest.partition_spec_id))?;
+            let mut writer = ManifestWriter::new(self.snapshot_id, schema, spec);
+            for entry in entries {
+                // Files deleted by earlier snapshots are dropped, their snapshots still list them
+                if is_removed(&entry) {
+                    writer.delete(entry);
+                } else if entry.status != ManifestStatus::Deleted {
+                    writer.existing(entry);
+                }
+            }
+            let bytes = writer.to_bytes()?;
+            let location = format!(
+                "{}/{}-r{}-{}.avro",
+                metadata.metadata_dir(),
+                self.commit_uuid,
+                i,
+                self.attempt
+            );
+            rewritten.push(writer.manifest_file(location.clone(), bytes.len() as i64)?);
+            storage.put(&location, bytes).await?;
+        }
+        Ok((rewritten, kept))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::catalog::glue::tests::LocalGlue;
+    use crate::catalog::glue::GlueCatalog;
+    use crate::catalog::TableCreation;
+    use crate::iceberg::manifest::{DataContentType, DataFileFormat};
+    use crate::iceberg::manifest_list::ManifestContent;
+    use crate::iceberg::partition::PartitionSpec;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+    use crate::iceberg::sort::SortOrder;
+    use crate::storage::memory::MemoryStore;
+
+    fn data_file(name: &str, record_count: i64) -> DataFile {
+        DataFile::new(
+            DataContentType::Data,
+            format!("s3://bucket/books/data/{}.parquet", name),
+            DataFileFormat::Parquet,
+            vec![],
+            record_count,
+            1024,
+        )
+    }
+
+    #[tokio::test]
+    async fn rebases_appends_that_lose_the_race() {
+        let storage = Arc::new(MemoryStore::new());
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let table = TableIdent::new("dotsdb", "books");
+        let creation = TableCreation {
+            schema: Schema::new(0, vec![NestedField::optional(1, "review_id", Type::Primitive(PrimitiveType::String))]),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::from([("commit.retry.min-wait-ms".to_string(), "1".to_string())]),
+        };
+        let created = catalog.create_table(&table, creation).await.unwrap();
+
+        // Both writers start from the empty table; the second has to rebase onto the first
+        let first = append_files(storage.as_ref(), &catalog, &table, created.clone(), vec![data_file("a", 10)]).await.unwrap();
+        let second = append_files(storage.as_ref(), &catalog, &table, created, vec![data_file("b", 5)]).await.unwrap();
+
+        assert_eq!(first.sequence_number, 1);
+        assert_eq!(second.sequence_number, 2);
+        assert_eq!(second.parent_snapshot_id, Some(first.snapshot_id));
+        assert_eq!(second.summary.properties["total-records"], "15");
+
+        let loaded = catalog.load_table(&table).await.unwrap();
+        assert_eq!(loaded.metadata.current_snapshot_id, Some(second.snapshot_id));
+        assert_eq!(loaded.metadata.snapshots.len(), 2);
+        let manifests = read_manifest_list(&storage.get(&second.manifest_list).await.unwrap()).unwrap();
+        let sequence_numbers: Vec<i64> = manifests.iter().map(|m| m.sequence_number).collect();
+        assert_eq!(sequence_numbers, vec![2, 1]);
+    }
+
+    /// A catalog that fails every commit as if it timed out, after or before applying it.
+    struct TimingOut<C> {
+        catalog: C,
+        applies: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl<C: Catalog> Catalog for TimingOut<C> {
+        async fn load_table(&self, table: &TableIdent) -> anyhow::Result<LoadedTable> {
+            self.catalog.load_table(table).await
+        }
+
+        async fn commit_table(&self, table: &TableIdent, base: &LoadedTable, updates: Vec<TableUpdate>) -> anyhow::Result<LoadedTable> {
+            if self.applies {
+                self.catalog.commit_table(table, base, updates).await?;
+            }
+            Err(crate::error::Transient("UpdateTable timed out".into()).into())
+        }
+
+        async fn create_table(&self, table: &TableIdent, creation: TableCreation) -> anyhow::Result<LoadedTable> {
+            self.catalog.create_table(table, creation).await
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_commits_the_catalog_failed_as_unknown() {
+        let storage = Arc::new(MemoryStore::new());
+        let table = TableIdent::new("dotsdb", "books");
+        let creation = TableCreation {
+            schema: Schema::new(0, vec![NestedField::optional(1, "review_id", Type::Primitive(PrimitiveType::String))]),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::new(),
+        };
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let mut timing_out = TimingOut { catalog, applies: true };
+        let created = timing_out.create_table(&table, creation).await.unwrap();
+
+        // A snapshot that made it into the table is committed, whatever the catalog answered
+        let snapshot = append_files(storage.as_ref(), &timing_out, &table, created, vec![data_file("a", 10)]).await.unwrap();
+        let loaded = timing_out.load_table(&table).await.unwrap();
+        assert_eq!(loaded.metadata.current_snapshot_id, Some(snapshot.snapshot_id));
+
+        // Otherwise it may still land, so it's neither retried nor cleaned up
+        timing_out.applies = false;
+        let err = append_files(storage.as_ref(), &timing_out, &table, loaded, vec![data_file("b", 5)]).await.unwrap_err();
+        assert!(err.is::<CommitStateUnknown>());
+        let reloaded = timing_out.load_table(&table).await.unwrap();
+        assert_eq!(reloaded.metadata.snapshots.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn commits_row_deltas_with_a_delete_manifest() {
+        let storage = Arc::new(MemoryStore::new());
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let table = TableIdent::new("dotsdb", "books");
+        let creation = TableCreation {
+            schema: Schema::new(0, vec![NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String))]),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::new(),
+        };
+        let created = catalog.create_table(&table, creation).await.unwrap();
+        let first = append_files(storage.as_ref(), &catalog, &table, created, vec![data_file("a", 10)]).await.unwrap();
+
+        let mut deletes = data_file("a-deletes", 2);
+        deletes.content = DataContentType::EqualityDeletes;
+        deletes.equality_ids = Some(vec![1]);
+        let loaded = catalog.load_table(&table).await.unwrap();
+        let stale = loaded.clone();
+        let delta = row_delta(storage.as_ref(), &catalog, &table, loaded, vec![data_file("b", 2)], vec![deletes], vec![]).await.unwrap();
+
+        assert_eq!(delta.summary.operation, Operation::Overwrite);
+        assert_eq!(delta.summary.properties["added-equality-deletes"], "2");
+        assert_eq!(delta.summary.properties["total-delete-files"], "1");
+        let manifests = read_manifest_list(&storage.get(&delta.manifest_list).await.unwrap()).unwrap();
+        let contents: Vec<_> = manifests.iter().map(|m| (m.content, m.sequence_number)).collect();
+        assert_eq!(contents, vec![(ManifestContent::Data, 2), (ManifestContent::Deletes, 2), (ManifestContent::Data, 1)]);
+        assert_eq!(manifests[2].added_snapshot_id, first.snapshot_id);
+
+        // Position deletes are only rebased if the files they point into are still live
+        let mut positions = data_file("a-positions", 1);
+        positions.content = DataContentType::PositionDeletes;
+        let referenced = |name: &str| vec![data_file(name, 0).file_path];
+        let rebased = row_delta(storage.as_ref(), &catalog, &table, stale.clone(), vec![], vec![positions.clone()], referenced("a")).await.unwrap();
+        assert_eq!((rebased.summary.operation, rebased.sequence_number), (Operation::Delete, 3));
+        let err = row_delta(storage.as_ref(), &catalog, &table, stale, vec![], vec![positions], referenced("c")).await.unwrap_err();
+        assert!(err.is::<CommitConflict>());
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_max_wait() {
+        let properties = HashMap::from([
+            ("commit.retry.min-wait-ms".to_string(), "100".to_string()),
+            ("commit.retry.max-wait-ms".to_string(), "1000".to_string()),
+        ]);
+        let policy = RetryPolicy::from_properties(&properties);
+        assert_eq!(policy.num_retries, 4);
+
+        let within = |retry: u32, wait_ms: u64| {
+            let backoff = policy.backoff(retry);
+            backoff >= Duration::from_millis(wait_ms) && backoff <= Duration::from_millis(wait_ms + wait_ms / 10)
+        };
+        assert!(within(1, 100));
+        assert!(within(2, 200));
+        assert!(within(3, 400));
+        assert!(within(5, 1000));
+        assert!(within(40, 1000));
+    }
+}
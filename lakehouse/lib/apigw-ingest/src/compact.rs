@@ -0,0 +1,295 @@
+use arrow2::array::{Array, BooleanArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::concatenate::concatenate;
+use arrow2::compute::filter::filter_chunk;
+use uuid::Uuid;
+
+use crate::catalog::{Catalog, LoadedTable, TableIdent};
+use crate::commit;
+use crate::iceberg::manifest::{DataContentType, DataFile, DataFileFormat};
+use crate::iceberg::metadata::Snapshot;
+use crate::iceberg::parquet::{self, WriteProperties};
+use crate::iceberg::scan::{plan_files, FileScanTask};
+use crate::iceberg::schema::Schema;
+use crate::iceberg::values::Literal;
+use crate::storage::ObjectStore;
+
+// COMPACT THE SMALL FILES EVERY INGEST LEAVES BEHIND
+// 1. Pick the live data files of the current snapshot that are smaller than the minimum file size,
+//    and pack those of each partition into groups that add up to about write.target-file-size-bytes
+// 2. Read the groups with enough files, drop the rows their delete files delete, and write the rest
+//    sorted by the table's sort order, as target-sized files - see parquet.rs
+// 3. Commit a `replace` snapshot swapping the old files for the new ones - see commit.rs. The new
+//    files keep the sequence number of the snapshot they were read from, so deletes committed
+//    meanwhile still apply to them. Position deletes committed meanwhile fail the compaction.
+
+/// Which data files to compact, like the options of the Java `RewriteDataFiles` action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewriteOptions {
+    /// Groups with fewer small files than this are left alone
+    pub min_input_files: usize,
+    /// Files smaller than this are small
+    pub min_file_size_bytes: u64,
+    /// Small files are grouped up to about this size, which each group is rewritten as
+    pub target_file_size_bytes: u64,
+}
+
+impl RewriteOptions {
+    /// The Java defaults for writing files of `write.target-file-size-bytes`.
+    pub fn from_write_properties(properties: &WriteProperties) -> Self {
+        RewriteOptions {
+            min_input_files: 5,
+            min_file_size_bytes: properties.target_file_size_bytes / 4 * 3,
+            target_file_size_bytes: properties.target_file_size_bytes,
+        }
+    }
+}
+
+/// What a compaction did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rewritten {
+    /// The new snapshot, or `None` if no files needed compacting and nothing was committed
+    pub snapshot: Option<Snapshot>,
+    pub rewritten_files: usize,
+    pub added_files: usize,
+}
+
+/// Rewrites the small data files of the current snapshot into target-sized files without the
+/// deleted rows.
+pub async fn rewrite_data_files(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    base: LoadedTable,
+    options: &RewriteOptions,
+) -> anyhow::Result<Rewritten> {
+    let Some(snapshot) = base.metadata.current_snapshot() else {
+        return Ok(Rewritten::default());
+    };
+    let schema = base.metadata.current_schema()?;
+    let spec = base.metadata.default_spec()?;
+    let sort_order = base.metadata.default_sort_order()?;
+
+    // Files written with older partition specs would have to be split up by the current one, so
+    // they're left alone
+    let mut partitions: Vec<(Vec<Option<Literal>>, Vec<FileScanTask>)> = vec![];
+    for task in plan_files(storage, &base.metadata, snapshot).await? {
+        let small = (task.data_file.file_size_in_bytes as u64) < options.min_file_size_bytes;
+        if task.spec_id != spec.spec_id || !small {
+            continue;
+        }
+        match partitions
+            .iter_mut()
+            .find(|(values, _)| *values == task.data_file.partition)
+        {
+            Some((_, tasks)) => tasks.push(task),
+            None => partitions.push((task.data_file.partition.clone(), vec![task])),
+        }
+    }
+
+    let properties = WriteProperties::from_properties(&base.metadata.properties);
+    let locations = base.metadata.location_provider();
+    let mut removed_data_files = vec![];
+    let mut data_files = vec![];
+    for (values, tasks) in partitions {
+        let partition_path = spec.partition_path(schema, &values)?;
+        for group in pack(tasks, options.target_file_size_bytes) {
+            if group.len() < options.min_input_files.max(2) {
+                continue;
+            }
+            let chunk = sort_order.sort_chunk(schema, read_live_rows(storage, schema, &group).await?)?;
+            if !chunk.is_empty() {
+                let next_location =
+                    || locations.new_data_location(&partition_path, &format!("{}.parquet", Uuid::new_v4()));
+                for written in parquet::write_chunk_files(storage, next_location, schema, &properties, chunk).await? {
+                    let mut data_file = DataFile::new(
+                        DataContentType::Data,
+                        written.location,
+                        DataFileFormat::Parquet,
+                        values.clone(),
+                        written.record_count as i64,
+                        written.file_size_in_bytes as i64,
+                    )
+                    .with_metrics(written.metrics);
+                    data_file.sort_order_id = (!sort_order.is_unsorted()).then_some(sort_order.order_id);
+                    data_files.push(data_file);
+                }
+            }
+            removed_data_files.extend(group.into_iter().map(|task| task.data_file));
+        }
+    }
+    if removed_data_files.is_empty() {
+        return Ok(Rewritten::default());
+    }
+
+    let rewritten_files = removed_data_files.len();
+    let added_files = data_files.len();
+    let starting_sequence_number = snapshot.sequence_number;
+    let snapshot = commit::rewrite_files(
+        storage,
+        catalog,
+        table,
+        base,
+        removed_data_files,
+        data_files,
+        starting_sequence_number,
+    )
+    .await?;
+    Ok(Rewritten {
+        snapshot: Some(snapshot),
+        rewritten_files,
+        added_files,
+    })
+}
+
+/// Packs `tasks` into groups of files that add up to no more than `target_size_bytes`, apart
+/// from single files that are bigger on their own.
+fn pack(tasks: Vec<FileScanTask>, target_size_bytes: u64) -> Vec<Vec<FileScanTask>> {
+    let mut groups: Vec<(u64, Vec<FileScanTask>)> = vec![];
+    for task in tasks {
+        let size = task.data_file.file_size_in_bytes as u64;
+        match groups.last_mut() {
+            Some((group_size, group)) if *group_size + size <= target_size_bytes => {
+                *group_size += size;
+                group.push(task);
+            }
+            _ => groups.push((size, vec![task])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// The rows of the files of `tasks` that their delete files don't delete, as one chunk.
+async fn read_live_rows(
+    storage: &dyn ObjectStore,
+    schema: &Schema,
+    tasks: &[FileScanTask],
+) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+    let mut chunks = vec![];
+    for task in tasks {
+        let (chunk, live) = task.read(storage, schema).await?;
+        chunks.push(filter_chunk(&chunk, &BooleanArray::from_slice(live))?);
+    }
+    let columns = (0..schema.fields.len())
+        .map(|i| {
+            concatenate(
+                &chunks
+                    .iter()
+                    .map(|chunk| chunk.arrays()[i].as_ref())
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Chunk::new(columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::catalog::glue::tests::LocalGlue;
+    use crate::catalog::glue::GlueCatalog;
+    use crate::catalog::{CommitConflict, TableCreation};
+    use crate::delete::delete_rows;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::expression::Predicate;
+    use crate::iceberg::metadata::Operation;
+    use crate::iceberg::partition::PartitionSpec;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, Type};
+    use crate::iceberg::sort::SortOrder;
+    use crate::iceberg::values::Value;
+    use crate::storage::memory::MemoryStore;
+
+    #[tokio::test]
+    async fn rewrites_small_files_without_deleted_rows() {
+        let storage = Arc::new(MemoryStore::new());
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let table = TableIdent::new("dotsdb", "books");
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "customer_id", Type::Primitive(PrimitiveType::String)),
+            ],
+        );
+        let creation = TableCreation {
+            schema: schema.clone(),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::new(),
+        };
+        let mut base = catalog.create_table(&table, creation).await.unwrap();
+
+        // Three small files, as three ingests would leave behind
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let properties = WriteProperties::default();
+        for (name, customers) in [("a", ["C1", "C2"]), ("b", ["C3", "C1"]), ("c", ["C2", "C3"])] {
+            let records = customers
+                .iter()
+                .enumerate()
+                .map(|(i, customer)| vec![string(&format!("{}{}", name, i)), string(customer)])
+                .collect::<Vec<_>>();
+            let chunk = records_to_chunk(&schema, &records).unwrap();
+            let location = format!("s3://bucket/dotsdb/books/data/{}.parquet", name);
+            let written = parquet::write_chunk(storage.as_ref(), &location, &schema, &properties, chunk)
+                .await
+                .unwrap();
+            let data_file = DataFile::new(
+                DataContentType::Data,
+                written.location,
+                DataFileFormat::Parquet,
+                vec![],
+                written.record_count as i64,
+                written.file_size_in_bytes as i64,
+            )
+            .with_metrics(written.metrics);
+            commit::append_files(storage.as_ref(), &catalog, &table, base, vec![data_file])
+                .await
+                .unwrap();
+            base = catalog.load_table(&table).await.unwrap();
+        }
+
+        // A compaction planned before a delete would lose it, so it fails
+        let stale = base.clone();
+        let predicate = Predicate::parse(&schema, "customer_id = 'C1'").unwrap();
+        let deleted = delete_rows(storage.as_ref(), &catalog, &table, base, &predicate)
+            .await
+            .unwrap();
+        let options = RewriteOptions {
+            min_input_files: 3,
+            ..RewriteOptions::from_write_properties(&properties)
+        };
+        let err = rewrite_data_files(storage.as_ref(), &catalog, &table, stale, &options)
+            .await
+            .unwrap_err();
+        assert!(err.is::<CommitConflict>());
+
+        let base = catalog.load_table(&table).await.unwrap();
+        let rewritten = rewrite_data_files(storage.as_ref(), &catalog, &table, base, &options)
+            .await
+            .unwrap();
+        assert_eq!((rewritten.rewritten_files, rewritten.added_files), (3, 1));
+        let snapshot = rewritten.snapshot.unwrap();
+        assert_eq!(snapshot.summary.operation, Operation::Replace);
+        assert_eq!(snapshot.summary.properties["deleted-data-files"], "3");
+        assert_eq!(snapshot.summary.properties["total-data-files"], "1");
+
+        // The new file has the live rows and the sequence number of the snapshot it was read
+        // from, so the position deletes it already applied don't point into it
+        let base = catalog.load_table(&table).await.unwrap();
+        let tasks = plan_files(storage.as_ref(), &base.metadata, &snapshot).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].sequence_number, deleted.snapshot.unwrap().sequence_number);
+        let (chunk, live) = tasks[0].read(storage.as_ref(), &schema).await.unwrap();
+        assert_eq!((chunk.len(), live), (4, vec![true; 4]));
+
+        // Nothing is left to compact
+        let rewritten = rewrite_data_files(storage.as_ref(), &catalog, &table, base, &options)
+            .await
+            .unwrap();
+        assert_eq!(rewritten.snapshot, None);
+    }
+}
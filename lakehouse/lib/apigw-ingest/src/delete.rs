@@ -0,0 +1,252 @@
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use arrow2::chunk::Chunk;
+use uuid::Uuid;
+
+use crate::catalog::{Catalog, LoadedTable, TableIdent};
+use crate::commit;
+use crate::error::IngestError;
+use crate::iceberg::deletes::position_delete_schema;
+use crate::iceberg::expression::Predicate;
+use crate::iceberg::manifest::{DataContentType, DataFile, DataFileFormat};
+use crate::iceberg::metadata::Snapshot;
+use crate::iceberg::parquet::{self, WriteProperties};
+use crate::iceberg::scan::plan_files;
+use crate::iceberg::values::Literal;
+use crate::storage::ObjectStore;
+
+// DELETE THE ROWS THAT MATCH A FILTER, E.G. A CUSTOMER'S DATA FOR A GDPR REQUEST
+// 1. List the live data files of the current snapshot, skipping those whose metrics rule out a match
+// 2. Read the rest, drop the rows their delete files already delete, and find the rows that match
+// 3. Write a position delete file per partition, with the path and position of every such row
+// 4. Commit the delete files as a `delete` snapshot - see commit.rs. If another commit got in
+//    first, it's rebased unless the files the positions point into were rewritten meanwhile.
+
+/// The path of a data file and the positions of rows to delete from it.
+type FilePositions = (String, Vec<i64>);
+
+/// What a delete did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deleted {
+    /// The new snapshot, or `None` if no rows matched and nothing was committed
+    pub snapshot: Option<Snapshot>,
+    pub deleted_rows: u64,
+}
+
+/// Deletes the rows of the table that match `predicate` with position deletes.
+pub async fn delete_rows(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    base: LoadedTable,
+    predicate: &Predicate,
+) -> anyhow::Result<Deleted> {
+    let Some(snapshot) = base.metadata.current_snapshot() else {
+        return Ok(Deleted {
+            snapshot: None,
+            deleted_rows: 0,
+        });
+    };
+    let schema = base.metadata.current_schema()?;
+    let spec = base.metadata.default_spec()?;
+
+    // The positions of the matching rows of every data file, by partition
+    let mut partitions: Vec<(Vec<Option<Literal>>, Vec<FilePositions>)> = vec![];
+    let mut deleted_rows = 0;
+    for task in plan_files(storage, &base.metadata, snapshot).await? {
+        if !predicate.might_match(schema, &task.data_file) {
+            continue;
+        }
+        let (chunk, live) = task.read(storage, schema).await?;
+        let positions = predicate
+            .matches(schema, &chunk)?
+            .into_iter()
+            .zip(live)
+            .enumerate()
+            .filter(|(_, (matches, live))| *matches && *live)
+            .map(|(row, _)| row as i64)
+            .collect::<Vec<_>>();
+        if positions.is_empty() {
+            continue;
+        }
+        // The delete manifest is written with the default spec
+        let path = task.data_file.file_path;
+        if task.spec_id != spec.spec_id {
+            let message = format!(
+                "Can't delete rows from {}, it was written with an older partition spec",
+                path
+            );
+            return Err(IngestError::InvalidRequest(message).into());
+        }
+        deleted_rows += positions.len() as u64;
+        match partitions
+            .iter_mut()
+            .find(|(values, _)| *values == task.data_file.partition)
+        {
+            Some((_, files)) => files.push((path, positions)),
+            None => partitions.push((task.data_file.partition, vec![(path, positions)])),
+        }
+    }
+    if partitions.is_empty() {
+        return Ok(Deleted {
+            snapshot: None,
+            deleted_rows: 0,
+        });
+    }
+
+    let properties = WriteProperties::from_properties(&base.metadata.properties);
+    let locations = base.metadata.location_provider();
+    let delete_schema = position_delete_schema();
+    let mut delete_files = vec![];
+    let mut referenced_data_files = vec![];
+    for (values, mut files) in partitions {
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let partition_path = spec.partition_path(schema, &values)?;
+        let next_location =
+            || locations.new_data_location(&partition_path, &format!("{}-deletes.parquet", Uuid::new_v4()));
+        let chunk = position_deletes(&files);
+        for written in parquet::write_chunk_files(storage, next_location, &delete_schema, &properties, chunk).await? {
+            let delete_file = DataFile::new(
+                DataContentType::PositionDeletes,
+                written.location,
+                DataFileFormat::Parquet,
+                values.clone(),
+                written.record_count as i64,
+                written.file_size_in_bytes as i64,
+            )
+            .with_metrics(written.metrics);
+            delete_files.push(delete_file);
+        }
+        referenced_data_files.extend(files.into_iter().map(|(path, _)| path));
+    }
+
+    let snapshot = commit::row_delta(
+        storage,
+        catalog,
+        table,
+        base,
+        vec![],
+        delete_files,
+        referenced_data_files,
+    )
+    .await?;
+    Ok(Deleted {
+        snapshot: Some(snapshot),
+        deleted_rows,
+    })
+}
+
+/// The rows of a position delete file for the positions of every file in `files`, which are
+/// sorted by path and then position as the spec asks.
+fn position_deletes(files: &[FilePositions]) -> Chunk<Box<dyn Array>> {
+    let paths = files
+        .iter()
+        .flat_map(|(path, positions)| positions.iter().map(move |_| path.as_str()));
+    let positions = files
+        .iter()
+        .flat_map(|(_, positions)| positions.iter().copied())
+        .collect();
+    Chunk::new(vec![
+        Utf8Array::<i32>::from_iter_values(paths).boxed(),
+        Int64Array::from_vec(positions).boxed(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::catalog::glue::tests::LocalGlue;
+    use crate::catalog::glue::GlueCatalog;
+    use crate::catalog::TableCreation;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::metadata::Operation;
+    use crate::iceberg::partition::PartitionSpec;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+    use crate::iceberg::sort::SortOrder;
+    use crate::iceberg::values::Value;
+    use crate::storage::memory::MemoryStore;
+
+    #[tokio::test]
+    async fn deletes_matching_rows_with_position_deletes() {
+        let storage = Arc::new(MemoryStore::new());
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let table = TableIdent::new("dotsdb", "books");
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "customer_id", Type::Primitive(PrimitiveType::String)),
+            ],
+        );
+        let creation = TableCreation {
+            schema: schema.clone(),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::new(),
+        };
+        let mut base = catalog.create_table(&table, creation).await.unwrap();
+
+        // Two data files, both with rows of the customer
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let properties = WriteProperties::default();
+        for (name, customers) in [("a", ["C1", "C2", "C1"]), ("b", ["C3", "C1", "C3"])] {
+            let records = customers
+                .iter()
+                .enumerate()
+                .map(|(i, customer)| vec![string(&format!("{}{}", name, i)), string(customer)])
+                .collect::<Vec<_>>();
+            let chunk = records_to_chunk(&schema, &records).unwrap();
+            let location = format!("s3://bucket/dotsdb/books/data/{}.parquet", name);
+            let written = parquet::write_chunk(storage.as_ref(), &location, &schema, &properties, chunk)
+                .await
+                .unwrap();
+            let data_file = DataFile::new(
+                DataContentType::Data,
+                written.location,
+                DataFileFormat::Parquet,
+                vec![],
+                written.record_count as i64,
+                written.file_size_in_bytes as i64,
+            )
+            .with_metrics(written.metrics);
+            commit::append_files(storage.as_ref(), &catalog, &table, base, vec![data_file])
+                .await
+                .unwrap();
+            base = catalog.load_table(&table).await.unwrap();
+        }
+
+        let predicate = Predicate::parse(&schema, "customer_id = 'C1'").unwrap();
+        let deleted = delete_rows(storage.as_ref(), &catalog, &table, base, &predicate)
+            .await
+            .unwrap();
+        assert_eq!(deleted.deleted_rows, 3);
+        let snapshot = deleted.snapshot.unwrap();
+        assert_eq!(snapshot.summary.operation, Operation::Delete);
+        assert_eq!(snapshot.summary.properties["added-position-deletes"], "3");
+
+        // The deleted rows are gone for readers, so deleting them again does nothing
+        let base = catalog.load_table(&table).await.unwrap();
+        let tasks = plan_files(storage.as_ref(), &base.metadata, &snapshot).await.unwrap();
+        let mut live = vec![];
+        for task in &tasks {
+            assert_eq!(task.deletes.len(), 1);
+            live.extend(task.read(storage.as_ref(), &schema).await.unwrap().1);
+        }
+        // The newest manifest comes first, with file b
+        assert_eq!(live, [true, false, true, false, true, false]);
+        let deleted = delete_rows(storage.as_ref(), &catalog, &table, base, &predicate)
+            .await
+            .unwrap();
+        assert_eq!(
+            deleted,
+            Deleted {
+                snapshot: None,
+                deleted_rows: 0
+            }
+        );
+    }
+
+}
@@ -0,0 +1,248 @@
+use std::error::Error;
+use std::{fmt, io};
+
+use aws_sdk_s3::types::SdkError;
+use serde_json::json;
+
+use crate::catalog::{CommitConflict, CommitStateUnknown};
+use crate::formats::InvalidBody;
+
+/// Seconds a client is asked to wait before retrying a request that failed for a transient reason.
+pub const RETRY_AFTER_SECS: u64 = 5;
+
+/// Why an ingest request failed, split into what the client has to fix (4xx) and what went
+/// wrong on our side (5xx). Transient failures are 503s the client can retry after a while.
+#[derive(Debug)]
+pub enum IngestError {
+    /// The body isn't in the format or doesn't match the table schema.
+    InvalidBody(InvalidBody),
+    /// The request asks for something the table can't do, e.g. an upsert without identifier fields.
+    InvalidRequest(String),
+    /// The request's method is neither `POST` nor `DELETE`.
+    MethodNotAllowed(String),
+    /// The body, or what it decompresses to, is bigger than we accept.
+    PayloadTooLarge { limit: usize },
+    /// The body is in a format we can't read.
+    UnsupportedMediaType(String),
+    /// The body is compressed in a way we can't decompress.
+    UnsupportedEncoding(String),
+    /// Other writers kept committing to the table first, even after retrying.
+    CommitConflict(String),
+    /// Storage or the catalog failed in a way that's usually temporary, see [`is_transient`].
+    Unavailable(anyhow::Error),
+    /// The catalog failed while committing, so the records may have been committed. Retrying
+    /// could add them twice, so the client isn't asked to.
+    CommitStateUnknown(anyhow::Error),
+    /// The lambda's configuration is wrong, e.g. a missing environment variable.
+    Misconfigured(anyhow::Error),
+    Internal(anyhow::Error),
+}
+
+impl IngestError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            IngestError::InvalidBody(_) | IngestError::InvalidRequest(_) => 400,
+            IngestError::MethodNotAllowed(_) => 405,
+            IngestError::PayloadTooLarge { .. } => 413,
+            IngestError::UnsupportedMediaType(_) | IngestError::UnsupportedEncoding(_) => 415,
+            IngestError::CommitConflict(_) | IngestError::Unavailable(_) => 503,
+            IngestError::CommitStateUnknown(_) | IngestError::Misconfigured(_) | IngestError::Internal(_) => 500,
+        }
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        self.status_code() < 500
+    }
+
+    /// Seconds to send in the `Retry-After` header, for failures worth retrying.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            IngestError::CommitConflict(_) | IngestError::Unavailable(_) => Some(RETRY_AFTER_SECS),
+            _ => None,
+        }
+    }
+
+    /// The JSON response body. Server errors only say what kind of failure it was; the details
+    /// go to the logs.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            IngestError::InvalidBody(invalid) => serde_json::to_value(invalid).unwrap_or_else(|_| json!({})),
+            IngestError::InvalidRequest(message) => json!({ "message": message }),
+            IngestError::MethodNotAllowed(method) => json!({ "message": format!("Method {} is not allowed", method) }),
+            IngestError::PayloadTooLarge { limit } => {
+                json!({ "message": format!("Request body is larger than {} bytes", limit) })
+            }
+            IngestError::UnsupportedMediaType(content_type) => {
+                json!({ "message": format!("Unsupported content type {}", content_type) })
+            }
+            IngestError::UnsupportedEncoding(encoding) => {
+                json!({ "message": format!("Unsupported content encoding {}", encoding) })
+            }
+            IngestError::CommitConflict(_) => json!({ "message": "The table is busy, try again later" }),
+            IngestError::Unavailable(_) => json!({ "message": "Storage or catalog unavailable, try again later" }),
+            IngestError::CommitStateUnknown(_) => {
+                json!({ "message": "The commit may have been applied, check the table before retrying" })
+            }
+            IngestError::Misconfigured(_) | IngestError::Internal(_) => json!({ "message": "Internal error" }),
+        }
+    }
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::InvalidBody(invalid) => write!(f, "{}", invalid),
+            IngestError::InvalidRequest(message) => write!(f, "{}", message),
+            IngestError::MethodNotAllowed(method) => write!(f, "Method {} is not allowed", method),
+            IngestError::PayloadTooLarge { limit } => write!(f, "Request body is larger than {} bytes", limit),
+            IngestError::UnsupportedMediaType(content_type) => write!(f, "Unsupported content type {}", content_type),
+            IngestError::UnsupportedEncoding(encoding) => write!(f, "Unsupported content encoding {}", encoding),
+            IngestError::CommitConflict(message) => write!(f, "Commit conflict: {}", message),
+            IngestError::Unavailable(err) => write!(f, "Unavailable: {:#}", err),
+            IngestError::CommitStateUnknown(err) => write!(f, "Commit state unknown: {:#}", err),
+            IngestError::Misconfigured(err) => write!(f, "Misconfigured: {:#}", err),
+            IngestError::Internal(err) => write!(f, "{:#}", err),
+        }
+    }
+}
+
+impl Error for IngestError {}
+
+/// Picks out the errors that have their own kind, anything else is internal.
+impl From<anyhow::Error> for IngestError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<IngestError>() {
+            Ok(ingest_error) => return ingest_error,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<InvalidBody>() {
+            Ok(invalid) => return IngestError::InvalidBody(invalid),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<CommitStateUnknown>() {
+            Ok(CommitStateUnknown(err)) => return IngestError::CommitStateUnknown(err),
+            Err(err) => err,
+        };
+        match err.downcast::<CommitConflict>() {
+            Ok(CommitConflict(message)) => IngestError::CommitConflict(message),
+            Err(err) => IngestError::Internal(err),
+        }
+    }
+}
+
+impl From<InvalidBody> for IngestError {
+    fn from(invalid: InvalidBody) -> Self {
+        IngestError::InvalidBody(invalid)
+    }
+}
+
+/// A storage or catalog failure that's likely to pass, such as a request that couldn't be sent,
+/// timed out or was answered with a 5xx. Displays as the error it wraps.
+#[derive(Debug)]
+pub struct Transient(pub Box<dyn Error + Send + Sync>);
+
+impl fmt::Display for Transient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for Transient {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// An AWS SDK error, as a [`Transient`] one if the request couldn't be sent, timed out or got a 5xx.
+pub fn sdk_error<E: Error + Send + Sync + 'static>(err: SdkError<E>) -> anyhow::Error {
+    let transient = match &err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => true,
+        SdkError::ResponseError(response) => response.raw().http().status().is_server_error(),
+        SdkError::ServiceError(service) => service.raw().http().status().is_server_error(),
+        _ => false,
+    };
+    if transient {
+        Transient(Box::new(err)).into()
+    } else {
+        err.into()
+    }
+}
+
+/// Whether `err` was caused by a [`Transient`] failure, or by I/O that timed out or lost its
+/// connection, so that the request is worth retrying.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(is_transient_cause)
+}
+
+fn is_transient_cause(cause: &(dyn Error + 'static)) -> bool {
+    if cause.is::<Transient>() {
+        return true;
+    }
+    match cause.downcast_ref::<io::Error>() {
+        Some(err) => {
+            matches!(
+                err.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+            ) || err.get_ref().is_some_and(|inner| {
+                // An I/O error's source is its inner error's source, which skips the inner error
+                let mut cause: Option<&(dyn Error + 'static)> = Some(inner);
+                while let Some(current) = cause {
+                    if is_transient_cause(current) {
+                        return true;
+                    }
+                    cause = current.source();
+                }
+                false
+            })
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn maps_errors_to_status_codes() {
+        let invalid = IngestError::from(anyhow::Error::new(InvalidBody::new("Request body is not valid JSON")));
+        assert_eq!(invalid.status_code(), 400);
+        assert_eq!(invalid.to_json()["message"], "Request body is not valid JSON");
+
+        let conflict = IngestError::from(anyhow::Error::new(CommitConflict("books".to_string())));
+        assert_eq!((conflict.status_code(), conflict.retry_after()), (503, Some(RETRY_AFTER_SECS)));
+
+        let request = IngestError::InvalidRequest("Unsupported mode merge".to_string());
+        assert_eq!((request.status_code(), request.to_json()["message"].as_str()), (400, Some("Unsupported mode merge")));
+
+        let encoding = IngestError::UnsupportedEncoding("compress".to_string());
+        assert_eq!(encoding.status_code(), 415);
+        assert_eq!(encoding.to_json()["message"], "Unsupported content encoding compress");
+
+        let internal = IngestError::from(anyhow!("secret bucket name"));
+        assert_eq!((internal.status_code(), internal.retry_after()), (500, None));
+        assert_eq!(internal.to_json()["message"], "Internal error");
+    }
+
+    #[test]
+    fn finds_transient_failures_in_the_chain() {
+        let timeout = anyhow::Error::new(io::Error::from(io::ErrorKind::TimedOut)).context("Failed to write s3://a/b");
+        assert!(is_transient(&timeout));
+        let transient = anyhow::Error::new(Transient("Catalog returned 503".into())).context("Failed to load books");
+        assert!(is_transient(&transient));
+        assert_eq!(format!("{:#}", transient), "Failed to load books: Catalog returned 503");
+        // As the S3 multipart writer reports failures through AsyncWrite
+        let written = anyhow::Error::new(io::Error::other(transient)).context("Failed to write data file");
+        assert!(is_transient(&written));
+
+        assert!(!is_transient(&anyhow!("Glue table dotsdb.books not found")));
+        let missing = anyhow::Error::new(io::Error::from(io::ErrorKind::NotFound)).context("Failed to read a.json");
+        assert!(!is_transient(&missing));
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::{anyhow, bail};
+use apache_avro::types::Value as AvroValue;
+use apache_avro::Reader;
+use serde_json::Value as JsonValue;
+
+use crate::formats::json::{read_literal, RecordReader};
+use crate::formats::{InvalidBody, ReadOptions, UnknownFields, MAX_ERRORS};
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+use crate::iceberg::values::{Literal, Record, Value};
+
+// Avro object container files (https://avro.apache.org/docs/1.11.1/specification/#object-container-files)
+// holding records with the table's column names. Avro values are converted to the column types
+// directly, with the logical types Iceberg writes for each
+// (https://iceberg.apache.org/spec/#avro), and strings are parsed like JSON strings.
+
+/// The records in the container file in `body`, converted to the types of `schema`. Fails with
+/// [`InvalidBody`] listing every value that doesn't match the schema.
+pub fn read_records(schema: &Schema, body: &[u8], options: &ReadOptions) -> anyhow::Result<Vec<Record>> {
+    let reader = Reader::new(body)
+        .map_err(|err| InvalidBody::new(format!("Request body is not an Avro object container file: {}", err)))?;
+    let mut errors = vec![];
+    let mut records = vec![];
+    for value in reader {
+        let mut reader = AvroRecordReader {
+            reader: RecordReader::new(records.len(), options, &mut errors),
+            unknown_fields: options.unknown_fields,
+        };
+        match value {
+            Ok(value) => records.push(reader.read_record(schema, &value)),
+            Err(err) => {
+                // The rest of the block can't be found without the length of this record
+                reader.reader.error("", format!("Record can't be decoded: {}", err));
+                break;
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        errors.truncate(MAX_ERRORS);
+        Err(InvalidBody::with_errors(errors).into())
+    }
+}
+
+/// Converts the Avro values of one record, reporting mismatches through a [`RecordReader`].
+struct AvroRecordReader<'a> {
+    reader: RecordReader<'a>,
+    unknown_fields: UnknownFields,
+}
+
+impl<'a> AvroRecordReader<'a> {
+    fn read_record(&mut self, schema: &Schema, value: &AvroValue) -> Record {
+        match self.read_struct(&schema.fields, value, "") {
+            Value::Struct(values) => values,
+            _ => vec![Value::Null; schema.fields.len()],
+        }
+    }
+
+    fn read_struct(&mut self, fields: &[NestedField], value: &AvroValue, path: &str) -> Value {
+        let AvroValue::Record(values) = value else {
+            self.reader
+                .error(path, format!("Expected a record, got {}", kind(value)));
+            return Value::Null;
+        };
+        let child_path = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", path, name)
+            }
+        };
+        if self.unknown_fields == UnknownFields::Reject {
+            for (name, _) in values
+                .iter()
+                .filter(|(name, _)| !fields.iter().any(|field| &field.name == name))
+            {
+                self.reader.error(&child_path(name), "Unknown field".to_string());
+            }
+        }
+        let values = fields
+            .iter()
+            .map(|field| {
+                let value = values
+                    .iter()
+                    .find(|(name, _)| name == &field.name)
+                    .map(|(_, value)| value);
+                self.read_field(
+                    &field.field_type,
+                    field.required,
+                    value.unwrap_or(&AvroValue::Null),
+                    &child_path(&field.name),
+                )
+            })
+            .collect();
+        Value::Struct(values)
+    }
+
+    fn read_field(&mut self, field_type: &Type, required: bool, value: &AvroValue, path: &str) -> Value {
+        let value = match value {
+            AvroValue::Union(_, value) => value.as_ref(),
+            value => value,
+        };
+        if *value == AvroValue::Null {
+            if required {
+                self.reader.error(path, "Required field is missing or null".to_string());
+            }
+            return Value::Null;
+        }
+        match field_type {
+            Type::Primitive(primitive) => match read_avro_literal(primitive, value) {
+                Ok(literal) => Value::Primitive(literal),
+                Err(err) => {
+                    self.reader.error(path, format!("{:#}", err));
+                    Value::Null
+                }
+            },
+            Type::Struct(struct_type) => self.read_struct(&struct_type.fields, value, path),
+            Type::List(list) => {
+                let AvroValue::Array(items) = value else {
+                    self.reader
+                        .error(path, format!("Expected an array, got {}", kind(value)));
+                    return Value::Null;
+                };
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        self.read_field(&list.element, list.element_required, item, &format!("{}[{}]", path, i))
+                    })
+                    .collect();
+                Value::List(items)
+            }
+            Type::Map(map) => {
+                // Avro maps have string keys; maps with other keys are arrays of key/value records
+                let entries: Vec<(AvroValue, &AvroValue)> = match value {
+                    AvroValue::Map(entries) => {
+                        let mut entries: Vec<_> = entries.iter().collect();
+                        entries.sort_by_key(|(key, _)| *key);
+                        entries
+                            .into_iter()
+                            .map(|(key, value)| (AvroValue::String(key.clone()), value))
+                            .collect()
+                    }
+                    AvroValue::Array(items) => {
+                        let entry = |item| Some((record_field(item, "key")?.clone(), record_field(item, "value")?));
+                        match items.iter().map(entry).collect() {
+                            Some(entries) => entries,
+                            None => {
+                                self.reader
+                                    .error(path, "Expected an array of key/value records".to_string());
+                                return Value::Null;
+                            }
+                        }
+                    }
+                    _ => {
+                        self.reader.error(path, format!("Expected a map, got {}", kind(value)));
+                        return Value::Null;
+                    }
+                };
+                let entries = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (key, value))| {
+                        let path = format!("{}[{}]", path, i);
+                        let key = self.read_field(&map.key, true, key, &path);
+                        let value = self.read_field(&map.value, map.value_required, value, &path);
+                        (key, value)
+                    })
+                    .collect();
+                Value::Map(entries)
+            }
+        }
+    }
+}
+
+fn record_field<'v>(record: &'v AvroValue, name: &str) -> Option<&'v AvroValue> {
+    match record {
+        AvroValue::Record(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+fn read_avro_literal(primitive: &PrimitiveType, value: &AvroValue) -> anyhow::Result<Literal> {
+    let mismatch = || anyhow!("Expected {}, got {}", primitive, kind(value));
+    let literal = match (primitive, value) {
+        (PrimitiveType::String, AvroValue::String(s) | AvroValue::Enum(_, s)) => Literal::String(s.clone()),
+        (PrimitiveType::String, _) => return Err(mismatch()),
+        // Strings are parsed the way JSON strings are
+        (_, AvroValue::String(s)) => read_literal(primitive, &JsonValue::String(s.clone()))?,
+        (PrimitiveType::Boolean, AvroValue::Boolean(b)) => Literal::Boolean(*b),
+        (PrimitiveType::Int, AvroValue::Int(i)) => Literal::Int(*i),
+        (PrimitiveType::Int, AvroValue::Long(l)) => Literal::Int((*l).try_into().map_err(|_| mismatch())?),
+        (PrimitiveType::Long, AvroValue::Int(i)) => Literal::Long(*i as i64),
+        (PrimitiveType::Long, AvroValue::Long(l)) => Literal::Long(*l),
+        (PrimitiveType::Float, AvroValue::Float(f)) => Literal::Float(*f),
+        (PrimitiveType::Double, AvroValue::Float(f)) => Literal::Double(*f as f64),
+        (PrimitiveType::Double, AvroValue::Double(d)) => Literal::Double(*d),
+        (PrimitiveType::Decimal { precision, .. }, AvroValue::Decimal(decimal)) => {
+            // The scale isn't in the value, so it's taken to be the table's
+            let bytes: Vec<u8> = decimal.try_into()?;
+            Literal::Decimal(unscaled_decimal(&bytes, *precision)?)
+        }
+        (PrimitiveType::Decimal { .. }, AvroValue::BigDecimal(decimal)) => {
+            read_literal(primitive, &JsonValue::String(decimal.to_string()))?
+        }
+        (PrimitiveType::Date, AvroValue::Date(days) | AvroValue::Int(days)) => Literal::Date(*days),
+        (PrimitiveType::Time, AvroValue::TimeMicros(micros)) => Literal::Time(*micros),
+        (PrimitiveType::Time, AvroValue::TimeMillis(millis)) => Literal::Time(*millis as i64 * 1000),
+        (PrimitiveType::Timestamp, value) => Literal::Timestamp(timestamp_micros(value).ok_or_else(mismatch)?),
+        (PrimitiveType::Timestamptz, value) => Literal::TimestampTz(timestamp_micros(value).ok_or_else(mismatch)?),
+        (PrimitiveType::Uuid, AvroValue::Uuid(uuid)) => Literal::Uuid(uuid.as_u128()),
+        (PrimitiveType::Uuid, AvroValue::Fixed(16, bytes)) => Literal::Uuid(u128::from_be_bytes(bytes[..].try_into()?)),
+        (PrimitiveType::Fixed(length), AvroValue::Fixed(_, bytes) | AvroValue::Bytes(bytes)) => {
+            if bytes.len() as u64 != *length {
+                bail!("Expected {} bytes, got {}", length, bytes.len());
+            }
+            Literal::Fixed(bytes.clone())
+        }
+        (PrimitiveType::Binary, AvroValue::Bytes(bytes) | AvroValue::Fixed(_, bytes)) => Literal::Binary(bytes.clone()),
+        _ => return Err(mismatch()),
+    };
+    Ok(literal)
+}
+
+/// Microseconds from the epoch of any of the Avro timestamps. Iceberg tells `timestamp` and
+/// `timestamptz` apart by `adjust-to-utc`, which producers rarely set, so either is accepted.
+fn timestamp_micros(value: &AvroValue) -> Option<i64> {
+    match value {
+        AvroValue::TimestampMicros(micros) | AvroValue::LocalTimestampMicros(micros) | AvroValue::Long(micros) => {
+            Some(*micros)
+        }
+        AvroValue::TimestampMillis(millis) | AvroValue::LocalTimestampMillis(millis) => millis.checked_mul(1000),
+        AvroValue::TimestampNanos(nanos) | AvroValue::LocalTimestampNanos(nanos) => Some(nanos.div_euclid(1000)),
+        _ => None,
+    }
+}
+
+/// The unscaled value of a decimal stored as big-endian two's complement bytes.
+fn unscaled_decimal(bytes: &[u8], precision: u32) -> anyhow::Result<i128> {
+    if bytes.len() > 16 {
+        bail!("Decimal of {} bytes doesn't fit decimal({})", bytes.len(), precision);
+    }
+    let sign = if bytes.first().is_some_and(|b| b & 0x80 != 0) {
+        0xff
+    } else {
+        0x00
+    };
+    let mut be = [sign; 16];
+    be[16 - bytes.len()..].copy_from_slice(bytes);
+    let unscaled = i128::from_be_bytes(be);
+    if unscaled.unsigned_abs() >= 10u128.pow(precision) {
+        bail!("Decimal {} has more than {} digits", unscaled, precision);
+    }
+    Ok(unscaled)
+}
+
+/// The name of an Avro value's type, for error messages.
+fn kind(value: &AvroValue) -> &'static str {
+    match value {
+        AvroValue::Null => "null",
+        AvroValue::Boolean(_) => "boolean",
+        AvroValue::Int(_) => "int",
+        AvroValue::Long(_) => "long",
+        AvroValue::Float(_) => "float",
+        AvroValue::Double(_) => "double",
+        AvroValue::Bytes(_) => "bytes",
+        AvroValue::String(_) => "string",
+        AvroValue::Fixed(..) => "fixed",
+        AvroValue::Enum(..) => "enum",
+        AvroValue::Union(..) => "union",
+        AvroValue::Array(_) => "array",
+        AvroValue::Map(_) => "map",
+        AvroValue::Record(_) => "record",
+        AvroValue::Date(_) => "date",
+        AvroValue::Decimal(_) | AvroValue::BigDecimal(_) => "decimal",
+        AvroValue::TimeMillis(_) | AvroValue::TimeMicros(_) => "time",
+        AvroValue::TimestampMillis(_) | AvroValue::TimestampMicros(_) | AvroValue::TimestampNanos(_) => "timestamp",
+        AvroValue::LocalTimestampMillis(_) | AvroValue::LocalTimestampMicros(_) | AvroValue::LocalTimestampNanos(_) => {
+            "local-timestamp"
+        }
+        AvroValue::Duration(_) => "duration",
+        AvroValue::Uuid(_) => "uuid",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apache_avro::{Codec, Writer};
+
+    use super::*;
+    use crate::formats::FieldError;
+    use crate::iceberg::schema::MapType;
+
+    #[test]
+    fn reads_records_of_a_container_file() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "review_date", Type::Primitive(PrimitiveType::Date)),
+                NestedField::optional(
+                    4,
+                    "votes",
+                    Type::Map(MapType {
+                        key_id: 5,
+                        key: Box::new(Type::Primitive(PrimitiveType::String)),
+                        value_id: 6,
+                        value_required: true,
+                        value: Box::new(Type::Primitive(PrimitiveType::Long)),
+                    }),
+                ),
+            ],
+        );
+        let avro_schema = apache_avro::Schema::parse_str(
+            r#"{"type": "record", "name": "review", "fields": [
+                {"name": "review_id", "type": ["null", "string"]},
+                {"name": "star_rating", "type": "long"},
+                {"name": "review_date", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "votes", "type": {"type": "map", "values": "int"}}
+            ]}"#,
+        )
+        .unwrap();
+        let record = |id: Option<&str>, rating: i64, votes: Vec<(&str, i32)>| {
+            let id = match id {
+                Some(id) => AvroValue::Union(1, Box::new(AvroValue::String(id.to_string()))),
+                None => AvroValue::Union(0, Box::new(AvroValue::Null)),
+            };
+            let votes = votes
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), AvroValue::Int(v)))
+                .collect();
+            AvroValue::Record(vec![
+                ("review_id".to_string(), id),
+                ("star_rating".to_string(), AvroValue::Long(rating)),
+                ("review_date".to_string(), AvroValue::Date(13310)),
+                ("votes".to_string(), AvroValue::Map(votes)),
+            ])
+        };
+        let container = |records: Vec<AvroValue>| {
+            let mut writer = Writer::with_codec(&avro_schema, vec![], Codec::Deflate);
+            writer.extend(records).unwrap();
+            writer.into_inner().unwrap()
+        };
+
+        let body = container(vec![record(Some("R1"), 5, vec![("total", 169), ("helpful", 153)])]);
+        let records = read_records(&schema, &body, &ReadOptions::default()).unwrap();
+        assert_eq!(
+            records,
+            [vec![
+                Value::Primitive(Literal::String("R1".to_string())),
+                Value::Primitive(Literal::Int(5)),
+                Value::Primitive(Literal::Date(13310)),
+                Value::Map(vec![
+                    (
+                        Value::Primitive(Literal::String("helpful".to_string())),
+                        Value::Primitive(Literal::Long(153))
+                    ),
+                    (
+                        Value::Primitive(Literal::String("total".to_string())),
+                        Value::Primitive(Literal::Long(169))
+                    ),
+                ]),
+            ]]
+        );
+
+        let body = container(vec![record(Some("R1"), 1, vec![]), record(None, 1 << 40, vec![])]);
+        let error = read_records(&schema, &body, &ReadOptions::default()).unwrap_err();
+        let errors = error.downcast::<InvalidBody>().unwrap().errors;
+        let at = |e: &FieldError| (e.record, e.field.clone().unwrap());
+        assert_eq!(
+            errors.iter().map(at).collect::<Vec<_>>(),
+            [(1, "review_id".to_string()), (1, "star_rating".to_string())]
+        );
+
+        let error = read_records(&schema, b"{\"review_id\": \"R1\"}", &ReadOptions::default()).unwrap_err();
+        assert!(error.downcast::<InvalidBody>().unwrap().errors.is_empty());
+    }
+}
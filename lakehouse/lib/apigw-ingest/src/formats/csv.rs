@@ -0,0 +1,281 @@
+use csv::{ReaderBuilder, StringRecord};
+use serde_json::Value as JsonValue;
+
+use crate::formats::json::RecordReader;
+use crate::formats::{FieldError, InvalidBody, ReadOptions, UnknownFields, MAX_ERRORS};
+use crate::iceberg::schema::{NestedField, Schema, Type};
+use crate::iceberg::values::{Record, Value};
+
+// CSV and TSV bodies (https://www.rfc-editor.org/rfc/rfc4180). Cells are read like JSON strings,
+// so they're coerced to the column types the same way, and empty cells are null. Structs, lists
+// and maps are written as JSON in their cell.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Whether the first row names the columns. When unknown, it's taken to be a header if
+    /// every non-empty cell in it is the name of a table column.
+    pub header: Option<bool>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            quote: b'"',
+            header: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn tsv() -> Self {
+        CsvOptions {
+            delimiter: b'\t',
+            ..Default::default()
+        }
+    }
+
+    /// Applies the parameters of a content type such as `text/csv; header=absent; delimiter=;`.
+    /// `header` is the parameter from RFC 4180, `delimiter` and `quote` take a single character.
+    pub fn with_parameters<'a>(mut self, parameters: impl Iterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        for (name, value) in parameters {
+            let value = value.trim_matches('"');
+            match name.to_ascii_lowercase().as_str() {
+                "header" => match value.to_ascii_lowercase().as_str() {
+                    "present" => self.header = Some(true),
+                    "absent" => self.header = Some(false),
+                    _ => return None,
+                },
+                "delimiter" => self.delimiter = single_byte(value)?,
+                "quote" => self.quote = single_byte(value)?,
+                _ => {}
+            }
+        }
+        Some(self)
+    }
+}
+
+fn single_byte(value: &str) -> Option<u8> {
+    match value {
+        "tab" | "\\t" => Some(b'\t'),
+        _ if value.len() == 1 => Some(value.as_bytes()[0]),
+        _ => None,
+    }
+}
+
+/// The records in the rows of `body`, converted to the types of `schema`. Without a header the
+/// cells are the table's columns in order. Fails with [`InvalidBody`] listing every row that
+/// can't be read and every cell that doesn't match the schema.
+pub fn read_records(
+    schema: &Schema,
+    body: &[u8],
+    csv_options: &CsvOptions,
+    options: &ReadOptions,
+) -> anyhow::Result<Vec<Record>> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(csv_options.delimiter)
+        .quote(csv_options.quote)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body);
+    let mut rows = reader.records().peekable();
+    let mut errors = vec![];
+
+    let header = match rows.peek() {
+        Some(Ok(first)) => match csv_options.header {
+            Some(true) => true,
+            Some(false) => false,
+            None => {
+                let mut names = first.iter().map(str::trim).filter(|name| !name.is_empty()).peekable();
+                names.peek().is_some() && names.all(|name| schema.field_by_name(name).is_some())
+            }
+        },
+        _ => false,
+    };
+    // Each cell's column, or None for columns that aren't in the table
+    let columns: Vec<Option<&NestedField>> = if header {
+        let names = rows.next().unwrap()?;
+        let unknown = names
+            .iter()
+            .filter(|name| !name.trim().is_empty() && schema.field_by_name(name.trim()).is_none());
+        for name in unknown {
+            if options.unknown_fields == UnknownFields::Reject {
+                errors.push(FieldError {
+                    record: 0,
+                    line: Some(1),
+                    field: Some(name.to_string()),
+                    message: "Unknown column".to_string(),
+                });
+            }
+        }
+        names.iter().map(|name| schema.field_by_name(name.trim())).collect()
+    } else {
+        schema.fields.iter().map(Some).collect()
+    };
+
+    let mut records = vec![];
+    for row in rows {
+        let index = records.len();
+        let row = match row {
+            Ok(row) => row,
+            Err(err) => {
+                let line = err.position().map(|position| position.line() as usize);
+                errors.push(FieldError {
+                    record: index,
+                    line,
+                    field: None,
+                    message: format!("Row can't be read: {}", err),
+                });
+                records.push(vec![]);
+                continue;
+            }
+        };
+        let line = row
+            .position()
+            .map(|position| position.line() as usize)
+            .unwrap_or_default();
+        let reader = RecordReader::new(index, options, &mut errors).at_line(line);
+        records.push(read_row(reader, schema, &columns, &row));
+    }
+
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        errors.truncate(MAX_ERRORS);
+        Err(InvalidBody::with_errors(errors).into())
+    }
+}
+
+fn read_row(mut reader: RecordReader, schema: &Schema, columns: &[Option<&NestedField>], row: &StringRecord) -> Record {
+    if row.len() > columns.len() {
+        reader.error(
+            "",
+            format!("Expected at most {} cells, got {}", columns.len(), row.len()),
+        );
+    }
+    let mut cells = vec![""; schema.fields.len()];
+    for (cell, column) in row.iter().zip(columns) {
+        if let Some(column) = column {
+            let position = schema.fields.iter().position(|field| field.id == column.id).unwrap();
+            cells[position] = cell;
+        }
+    }
+    schema
+        .fields
+        .iter()
+        .zip(cells)
+        .map(|(field, cell)| {
+            let json = match (&field.field_type, cell) {
+                (_, "") => JsonValue::Null,
+                (Type::Primitive(_), cell) => JsonValue::String(cell.to_string()),
+                (_, cell) => match serde_json::from_str(cell) {
+                    Ok(json) => json,
+                    Err(err) => {
+                        reader.error(&field.name, format!("Expected JSON for a nested value: {}", err));
+                        return Value::Null;
+                    }
+                },
+            };
+            reader.read_field(&field.field_type, field.required, &json, &field.name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::schema::{ListType, PrimitiveType};
+    use crate::iceberg::values::Literal;
+
+    fn reviews() -> Schema {
+        Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "review_date", Type::Primitive(PrimitiveType::Date)),
+                NestedField::optional(
+                    4,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 5,
+                        element_required: true,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn reads_rows_by_header() {
+        let body = b"star_rating,review_id,tags\n5,R1,\"[\"\"history\"\"]\"\n,\"R2, the sequel\",\n";
+        let records = read_records(&reviews(), body, &CsvOptions::default(), &ReadOptions::default()).unwrap();
+        assert_eq!(
+            records,
+            [
+                vec![
+                    Value::Primitive(Literal::String("R1".to_string())),
+                    Value::Primitive(Literal::Int(5)),
+                    Value::Null,
+                    Value::List(vec![Value::Primitive(Literal::String("history".to_string()))]),
+                ],
+                vec![
+                    Value::Primitive(Literal::String("R2, the sequel".to_string())),
+                    Value::Null,
+                    Value::Null,
+                    Value::Null,
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_headerless_tsv_in_column_order() {
+        let body = b"R1\t1\t2006-06-11\nR2\tfive\t\n\t3\n";
+        let error = read_records(&reviews(), body, &CsvOptions::tsv(), &ReadOptions::default()).unwrap_err();
+        let errors = error.downcast::<InvalidBody>().unwrap().errors;
+        let at = |e: &FieldError| (e.record, e.line, e.field.clone());
+        assert_eq!(
+            errors.iter().map(at).collect::<Vec<_>>(),
+            [
+                (1, Some(2), Some("star_rating".to_string())),
+                (2, Some(3), Some("review_id".to_string()))
+            ]
+        );
+
+        let records = read_records(
+            &reviews(),
+            b"R1\t1\t2006-06-11",
+            &CsvOptions::tsv(),
+            &ReadOptions::default(),
+        );
+        assert_eq!(records.unwrap()[0][2], Value::Primitive(Literal::Date(13310)));
+    }
+
+    #[test]
+    fn takes_options_from_content_type_parameters() {
+        let options = CsvOptions::default()
+            .with_parameters([("header", "absent"), ("delimiter", ";"), ("quote", "'")].into_iter())
+            .unwrap();
+        assert_eq!(
+            options,
+            CsvOptions {
+                delimiter: b';',
+                quote: b'\'',
+                header: Some(false),
+            }
+        );
+        assert!(CsvOptions::default()
+            .with_parameters([("delimiter", "::")].into_iter())
+            .is_none());
+
+        // With the header forced off, a first row of column names is data
+        let body = b"review_id\n'R1;2';1";
+        let records = read_records(&reviews(), body, &options, &ReadOptions::default()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1][0], Value::Primitive(Literal::String("R1;2".to_string())));
+    }
+}
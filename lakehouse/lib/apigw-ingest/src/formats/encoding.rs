@@ -0,0 +1,105 @@
+use std::io::Read;
+
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+
+use crate::error::IngestError;
+use crate::formats::InvalidBody;
+
+// Compressed request bodies (https://www.rfc-editor.org/rfc/rfc9110#field.content-encoding).
+// They're decompressed up to a limit, since a small body can expand to far more than the lambda
+// has memory for.
+
+/// A compression a body can be sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+    Zstd,
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// The encodings listed in a `Content-Encoding` header, in the order they were applied, or
+    /// the first one that isn't supported. `identity` stands for no encoding.
+    pub fn parse_header(header: Option<&str>) -> Result<Vec<ContentEncoding>, String> {
+        let codings = header.unwrap_or_default().split(',').map(str::trim).filter(|coding| !coding.is_empty());
+        codings
+            .filter(|coding| !coding.eq_ignore_ascii_case("identity"))
+            .map(|coding| match coding.to_ascii_lowercase().as_str() {
+                "gzip" | "x-gzip" => Ok(ContentEncoding::Gzip),
+                "deflate" => Ok(ContentEncoding::Deflate),
+                "zstd" => Ok(ContentEncoding::Zstd),
+                "br" => Ok(ContentEncoding::Brotli),
+                _ => Err(coding.to_string()),
+            })
+            .collect()
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Zstd => "zstd",
+            ContentEncoding::Brotli => "br",
+        }
+    }
+
+    /// `body` decompressed. Fails with [`IngestError::PayloadTooLarge`] if it comes to more
+    /// than `limit` bytes, without decompressing any further than that.
+    pub fn decode(self, body: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            ContentEncoding::Gzip => Box::new(MultiGzDecoder::new(body)),
+            ContentEncoding::Deflate => Box::new(ZlibDecoder::new(body)),
+            ContentEncoding::Zstd => Box::new(zstd::stream::read::Decoder::new(body)?),
+            ContentEncoding::Brotli => Box::new(brotli::Decompressor::new(body, 4096)),
+        };
+        let mut decoded = vec![];
+        decoder
+            .take(limit as u64 + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|err| InvalidBody::new(format!("Request body is not valid {}: {}", self.name(), err)))?;
+        if decoded.len() > limit {
+            return Err(IngestError::PayloadTooLarge { limit }.into());
+        }
+        Ok(decoded)
+    }
+}
+
+/// `body` with the encodings of a `Content-Encoding` header undone, last applied first.
+pub fn decode_body(encodings: &[ContentEncoding], body: Vec<u8>, limit: usize) -> anyhow::Result<Vec<u8>> {
+    encodings.iter().rev().try_fold(body, |body, encoding| encoding.decode(&body, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn decodes_up_to_the_limit() {
+        assert_eq!(ContentEncoding::parse_header(None), Ok(vec![]));
+        assert_eq!(
+            ContentEncoding::parse_header(Some("identity, gzip, BR")),
+            Ok(vec![ContentEncoding::Gzip, ContentEncoding::Brotli])
+        );
+        assert_eq!(ContentEncoding::parse_header(Some("gzip, compress")), Err("compress".to_string()));
+
+        let body = br#"[{"review_id": "R1"}]"#.repeat(100);
+        let mut gzip = GzEncoder::new(vec![], Compression::default());
+        gzip.write_all(&body).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::encode_all(&gzip[..], 0).unwrap();
+        let encodings = ContentEncoding::parse_header(Some("gzip, zstd")).unwrap();
+        assert_eq!(decode_body(&encodings, zstd.clone(), body.len()).unwrap(), body);
+
+        let error = decode_body(&encodings, zstd, body.len() - 1).unwrap_err();
+        assert!(matches!(error.downcast::<IngestError>(), Ok(IngestError::PayloadTooLarge { .. })));
+
+        let error = ContentEncoding::Brotli.decode(&body, body.len()).unwrap_err();
+        assert_eq!(error.downcast::<InvalidBody>().unwrap().errors, vec![]);
+    }
+}
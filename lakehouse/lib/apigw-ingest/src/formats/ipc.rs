@@ -0,0 +1,284 @@
+use std::io::Cursor;
+
+use arrow2::array::{new_null_array, Array, ListArray, MapArray, StructArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::cast::{can_cast_types, cast, CastOptions};
+use arrow2::compute::concatenate::concatenate;
+use arrow2::datatypes::{DataType, Field};
+use arrow2::io::ipc::read::{read_stream_metadata, StreamReader, StreamState};
+
+use crate::formats::{FieldError, InvalidBody, ReadOptions, UnknownFields, MAX_ERRORS};
+use crate::iceberg::arrow::schema_to_arrow;
+use crate::iceberg::schema::Schema;
+
+// Arrow IPC streams (https://arrow.apache.org/docs/format/Columnar.html#ipc-streaming-format).
+// The batches are already columns, so rather than going through records they're matched to the
+// table's columns by name and cast to the table's types.
+
+/// The batches of the stream in `body` as one chunk of the table's columns. Fails with
+/// [`InvalidBody`] listing every column that can't be converted to the table's type and every
+/// required column with nulls.
+pub fn read_chunk(schema: &Schema, body: &[u8], options: &ReadOptions) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+    let invalid =
+        |err: arrow2::error::Error| InvalidBody::new(format!("Request body is not an Arrow IPC stream: {}", err));
+    let mut reader = Cursor::new(body);
+    let metadata = read_stream_metadata(&mut reader).map_err(invalid)?;
+    let fields = metadata.schema.fields.clone();
+    let mut batches = vec![];
+    for state in StreamReader::new(reader, metadata, None) {
+        match state.map_err(invalid)? {
+            StreamState::Some(batch) => batches.push(batch),
+            StreamState::Waiting => break,
+        }
+    }
+    let columns = (0..fields.len())
+        .map(|i| match batches.as_slice() {
+            [batch] => Ok(batch.arrays()[i].clone()),
+            _ => concatenate(
+                &batches
+                    .iter()
+                    .map(|batch| batch.arrays()[i].as_ref())
+                    .collect::<Vec<_>>(),
+            ),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let length = batches.iter().map(|batch| batch.len()).sum();
+
+    let mut errors = vec![];
+    if options.unknown_fields == UnknownFields::Reject {
+        for field in fields
+            .iter()
+            .filter(|field| schema.field_by_name(&field.name).is_none())
+        {
+            errors.push(column_error(0, &field.name, "Unknown column".to_string()));
+        }
+    }
+    let mut conformer = Conformer { errors: &mut errors };
+    let columns = schema_to_arrow(schema)
+        .fields
+        .iter()
+        .map(|target| {
+            let source = fields.iter().position(|field| field.name == target.name);
+            conformer.column(source.map(|i| columns[i].as_ref()), target, length, &target.name)
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(Chunk::try_new(columns)?)
+    } else {
+        errors.truncate(MAX_ERRORS);
+        Err(InvalidBody::with_errors(errors).into())
+    }
+}
+
+fn column_error(record: usize, path: &str, message: String) -> FieldError {
+    FieldError {
+        record,
+        line: None,
+        field: Some(path.to_string()),
+        message,
+    }
+}
+
+/// Converts arrays to the types of the table's arrow fields, collecting what doesn't fit. Arrays
+/// that don't are replaced with nulls so the other columns can still be checked.
+struct Conformer<'a> {
+    errors: &'a mut Vec<FieldError>,
+}
+
+impl<'a> Conformer<'a> {
+    fn error(&mut self, record: usize, path: &str, message: String) {
+        self.errors.push(column_error(record, path, message));
+    }
+
+    /// `source` as an array of `target`'s type, or nulls if it's missing.
+    fn column(&mut self, source: Option<&dyn Array>, target: &Field, length: usize, path: &str) -> Box<dyn Array> {
+        let Some(source) = source else {
+            if !target.is_nullable && length > 0 {
+                self.error(0, path, "Required column is missing".to_string());
+            }
+            return new_null_array(target.data_type.clone(), length);
+        };
+        let array = self.array(source, target, path);
+        if !target.is_nullable {
+            if let Some(validity) = array.validity().filter(|validity| validity.unset_bits() > 0) {
+                let record = validity.iter().position(|valid| !valid).unwrap_or_default();
+                self.error(record, path, "Required field is null".to_string());
+            }
+        }
+        array
+    }
+
+    fn array(&mut self, source: &dyn Array, target: &Field, path: &str) -> Box<dyn Array> {
+        let result = match &target.data_type {
+            DataType::Struct(fields) => self.struct_array(source, &target.data_type, fields, path),
+            DataType::List(element) => self.list_array(source, &target.data_type, element, path),
+            data_type => self.cast(source, data_type, path),
+        };
+        result.unwrap_or_else(|message| {
+            self.error(0, path, message);
+            new_null_array(target.data_type.clone(), source.len())
+        })
+    }
+
+    fn struct_array(
+        &mut self,
+        source: &dyn Array,
+        data_type: &DataType,
+        fields: &[Field],
+        path: &str,
+    ) -> Result<Box<dyn Array>, String> {
+        let source = source
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .ok_or_else(|| format!("Expected a struct, got {:?}", source.data_type()))?;
+        let values = fields
+            .iter()
+            .map(|field| {
+                let child = source.fields().iter().position(|child| child.name == field.name);
+                let child = child.map(|i| source.values()[i].as_ref());
+                self.column(child, field, source.len(), &format!("{}.{}", path, field.name))
+            })
+            .collect();
+        let array = StructArray::try_new(data_type.clone(), values, source.validity().cloned())
+            .map_err(|err| err.to_string())?;
+        Ok(array.boxed())
+    }
+
+    /// Lists, and the lists of key/value structs maps are written as. Arrow maps are read as
+    /// those lists, taking the first two fields of their entries as the key and value.
+    fn list_array(
+        &mut self,
+        source: &dyn Array,
+        data_type: &DataType,
+        element: &Field,
+        path: &str,
+    ) -> Result<Box<dyn Array>, String> {
+        let (offsets, values, validity) = if let Some(list) = source.as_any().downcast_ref::<ListArray<i32>>() {
+            (list.offsets().clone(), list.values().clone(), list.validity().cloned())
+        } else if let Some(large) = source.as_any().downcast_ref::<ListArray<i64>>() {
+            let small = ListArray::<i32>::get_child_field(large.data_type()).clone();
+            let list =
+                cast(large, &DataType::List(Box::new(small)), CastOptions::default()).map_err(|err| err.to_string())?;
+            let list = list.as_any().downcast_ref::<ListArray<i32>>().unwrap();
+            (list.offsets().clone(), list.values().clone(), list.validity().cloned())
+        } else if let Some(map) = source.as_any().downcast_ref::<MapArray>() {
+            let entries = map.field().as_any().downcast_ref::<StructArray>().unwrap();
+            let DataType::Struct(names) = &element.data_type else {
+                return Err(format!("Expected a list, got {:?}", source.data_type()));
+            };
+            // Rename the entry fields to the key and value the table's entries have
+            let fields = entries
+                .fields()
+                .iter()
+                .zip(names)
+                .map(|(field, name)| Field::new(&name.name, field.data_type.clone(), field.is_nullable))
+                .collect();
+            let entries = StructArray::new(
+                DataType::Struct(fields),
+                entries.values().to_vec(),
+                entries.validity().cloned(),
+            );
+            (map.offsets().clone(), entries.boxed(), map.validity().cloned())
+        } else {
+            return Err(format!("Expected a list, got {:?}", source.data_type()));
+        };
+        let values = self.column(Some(values.as_ref()), element, values.len(), &format!("{}[]", path));
+        let array =
+            ListArray::<i32>::try_new(data_type.clone(), offsets, values, validity).map_err(|err| err.to_string())?;
+        Ok(array.boxed())
+    }
+
+    /// Casts a primitive array. Values the cast can't convert come out as nulls, so any new
+    /// null is a value that doesn't fit.
+    fn cast(&mut self, source: &dyn Array, data_type: &DataType, path: &str) -> Result<Box<dyn Array>, String> {
+        if source.data_type() == data_type {
+            return Ok(source.to_boxed());
+        }
+        if !can_cast_types(source.data_type(), data_type) {
+            return Err(format!("Can't convert {:?} to {:?}", source.data_type(), data_type));
+        }
+        let array = cast(source, data_type, CastOptions::default()).map_err(|err| err.to_string())?;
+        if array.null_count() > source.null_count() {
+            let record = (0..source.len())
+                .find(|&i| array.is_null(i) && !source.is_null(i))
+                .unwrap_or_default();
+            self.error(record, path, format!("Value doesn't fit {:?}", data_type));
+        }
+        Ok(array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow2::array::{Int32Array, Int64Array, PrimitiveArray, Utf8Array};
+    use arrow2::datatypes::Schema as ArrowSchema;
+    use arrow2::io::ipc::write::{StreamWriter, WriteOptions};
+
+    use super::*;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, StructType, Type};
+
+    fn stream(fields: Vec<Field>, columns: Vec<Box<dyn Array>>) -> Vec<u8> {
+        let mut writer = StreamWriter::new(vec![], WriteOptions { compression: None });
+        writer.start(&ArrowSchema::from(fields), None).unwrap();
+        writer.write(&Chunk::new(columns), None).unwrap();
+        writer.finish().unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn casts_columns_to_the_table_types() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(
+                    3,
+                    "author",
+                    Type::Struct(StructType {
+                        fields: vec![NestedField::optional(4, "name", Type::Primitive(PrimitiveType::String))],
+                    }),
+                ),
+            ],
+        );
+        let body = stream(
+            vec![
+                Field::new("star_rating", DataType::Int64, true),
+                Field::new("review_id", DataType::Utf8, false),
+                Field::new("vine", DataType::Utf8, true),
+            ],
+            vec![
+                Int64Array::from([Some(5), None]).boxed(),
+                Utf8Array::<i32>::from_slice(["R1", "R2"]).boxed(),
+                Utf8Array::<i32>::from_slice(["N", "Y"]).boxed(),
+            ],
+        );
+        let chunk = read_chunk(&schema, &body, &ReadOptions::default()).unwrap();
+        assert_eq!(chunk.len(), 2);
+        assert_eq!(chunk.arrays()[0].data_type(), &DataType::Utf8);
+        assert_eq!(
+            chunk.arrays()[1].as_ref(),
+            &Int32Array::from([Some(5), None]) as &dyn Array
+        );
+        assert_eq!(chunk.arrays()[2].null_count(), 2);
+
+        let body = stream(
+            vec![
+                Field::new("review_id", DataType::Utf8, true),
+                Field::new("star_rating", DataType::Int64, true),
+            ],
+            vec![
+                Utf8Array::<i32>::from([Some("R1"), None]).boxed(),
+                PrimitiveArray::from_slice([1i64, 1 << 40]).boxed(),
+            ],
+        );
+        let error = read_chunk(&schema, &body, &ReadOptions::default()).unwrap_err();
+        let errors = error.downcast::<InvalidBody>().unwrap().errors;
+        let at = |e: &FieldError| (e.record, e.field.clone().unwrap());
+        assert_eq!(
+            errors.iter().map(at).collect::<Vec<_>>(),
+            [(1, "review_id".to_string()), (1, "star_rating".to_string())]
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde_json::Value as JsonValue;
+
+use crate::formats::{FieldError, InvalidBody, ReadOptions, UnknownFields, MAX_ERRORS};
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+use crate::iceberg::values::{Literal, Record, Value};
+
+// JSON request bodies: either one object or an array of objects, each object being a record
+// keyed by column name. Values are read the way the Iceberg JSON single-value serialization
+// writes them (https://iceberg.apache.org/spec/#json-single-value-serialization), and
+// numbers and booleans are also accepted as strings since that's what many clients send.
+
+/// The records in `body`, converted to the types of `schema`. Fails with [`InvalidBody`] listing
+/// every value that doesn't match the schema.
+pub fn read_records(schema: &Schema, body: &[u8], options: &ReadOptions) -> anyhow::Result<Vec<Record>> {
+    let json: JsonValue = serde_json::from_slice(body)
+        .map_err(|err| InvalidBody::new(format!("Request body is not valid JSON: {}", err)))?;
+    let objects = match json {
+        JsonValue::Array(objects) => objects,
+        object @ JsonValue::Object(_) => vec![object],
+        _ => return Err(InvalidBody::new("Request body must be a JSON object or an array of objects").into()),
+    };
+    let mut errors = vec![];
+    let records = objects
+        .iter()
+        .enumerate()
+        .map(|(index, object)| RecordReader::new(index, options, &mut errors).read_record(schema, object))
+        .collect();
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        errors.truncate(MAX_ERRORS);
+        Err(InvalidBody::with_errors(errors).into())
+    }
+}
+
+/// Converts the JSON values of one record, collecting every mismatch with the schema. Values that
+/// don't match are read as null so the rest of the record can still be checked.
+pub(crate) struct RecordReader<'a> {
+    record: usize,
+    line: Option<usize>,
+    options: &'a ReadOptions,
+    errors: &'a mut Vec<FieldError>,
+}
+
+impl<'a> RecordReader<'a> {
+    pub(crate) fn new(record: usize, options: &'a ReadOptions, errors: &'a mut Vec<FieldError>) -> Self {
+        RecordReader {
+            record,
+            line: None,
+            options,
+            errors,
+        }
+    }
+
+    /// Reports errors at `line` of the body too, for formats with a record per line.
+    pub(crate) fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    pub(crate) fn error(&mut self, path: &str, message: String) {
+        self.errors.push(FieldError {
+            record: self.record,
+            line: self.line,
+            field: (!path.is_empty()).then(|| path.to_string()),
+            message,
+        });
+    }
+
+    /// Converts one JSON object to a record of `schema`. Columns missing from the object are null.
+    pub(crate) fn read_record(&mut self, schema: &Schema, object: &JsonValue) -> Record {
+        match self.read_struct(&schema.fields, object, "") {
+            Value::Struct(values) => values,
+            _ => vec![Value::Null; schema.fields.len()],
+        }
+    }
+
+    fn read_struct(&mut self, fields: &[NestedField], json: &JsonValue, path: &str) -> Value {
+        let JsonValue::Object(object) = json else {
+            self.error(path, format!("Expected an object, got {}", json));
+            return Value::Null;
+        };
+        let child_path = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", path, name)
+            }
+        };
+        if self.options.unknown_fields == UnknownFields::Reject {
+            for name in object.keys().filter(|name| !fields.iter().any(|field| &field.name == *name)) {
+                self.error(&child_path(name), "Unknown field".to_string());
+            }
+        }
+        let values = fields
+            .iter()
+            .map(|field| {
+                let value = object.get(&field.name).unwrap_or(&JsonValue::Null);
+                self.read_field(&field.field_type, field.required, value, &child_path(&field.name))
+            })
+            .collect();
+        Value::Struct(values)
+    }
+
+    pub(crate) fn read_field(&mut self, field_type: &Type, required: bool, json: &JsonValue, path: &str) -> Value {
+        if json.is_null() {
+            if required {
+                self.error(path, "Required field is missing or null".to_string());
+            }
+            return Value::Null;
+        }
+        self.read_value(field_type, json, path)
+    }
+
+    fn read_value(&mut self, field_type: &Type, json: &JsonValue, path: &str) -> Value {
+        match field_type {
+            Type::Primitive(primitive) => match read_literal(primitive, json) {
+                Ok(literal) => Value::Primitive(literal),
+                Err(err) => {
+                    self.error(path, format!("{:#}", err));
+                    Value::Null
+                }
+            },
+            Type::Struct(struct_type) => self.read_struct(&struct_type.fields, json, path),
+            Type::List(list) => {
+                let JsonValue::Array(items) = json else {
+                    self.error(path, format!("Expected an array, got {}", json));
+                    return Value::Null;
+                };
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        self.read_field(&list.element, list.element_required, item, &format!("{}[{}]", path, i))
+                    })
+                    .collect();
+                Value::List(items)
+            }
+            Type::Map(map) => {
+                // Maps are objects, so keys of other types than string are given as strings
+                let JsonValue::Object(entries) = json else {
+                    self.error(path, format!("Expected an object, got {}", json));
+                    return Value::Null;
+                };
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let path = format!("{}[{}]", path, key);
+                        let key = self.read_value(&map.key, &JsonValue::String(key.clone()), &path);
+                        let value = self.read_field(&map.value, map.value_required, value, &path);
+                        (key, value)
+                    })
+                    .collect();
+                Value::Map(entries)
+            }
+        }
+    }
+}
+
+/// The text of a JSON string, or of a number or boolean.
+fn scalar_text(json: &JsonValue) -> Option<String> {
+    match json {
+        JsonValue::String(s) => Some(s.trim().to_string()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_text<T: std::str::FromStr>(json: &JsonValue, expected: &str) -> anyhow::Result<T> {
+    scalar_text(json)
+        .and_then(|text| text.parse().ok())
+        .ok_or_else(|| anyhow!("Expected {}, got {}", expected, json))
+}
+
+pub(crate) fn read_literal(primitive: &PrimitiveType, json: &JsonValue) -> anyhow::Result<Literal> {
+    let literal = match primitive {
+        PrimitiveType::Boolean => Literal::Boolean(parse_text(json, "a boolean")?),
+        PrimitiveType::Int => Literal::Int(parse_text(json, "a 32-bit integer")?),
+        PrimitiveType::Long => Literal::Long(parse_text(json, "a 64-bit integer")?),
+        PrimitiveType::Float => Literal::Float(parse_text(json, "a float")?),
+        PrimitiveType::Double => Literal::Double(parse_text(json, "a double")?),
+        PrimitiveType::Decimal { precision, scale } => {
+            let text = scalar_text(json).ok_or_else(|| anyhow!("Expected a decimal, got {}", json))?;
+            Literal::Decimal(parse_decimal(&text, *precision, *scale)?)
+        }
+        PrimitiveType::Date => match json {
+            JsonValue::Number(_) => Literal::Date(parse_text(json, "days from 1970-01-01")?),
+            _ => {
+                let text = scalar_text(json).ok_or_else(|| anyhow!("Expected a date, got {}", json))?;
+                let date = NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+                    .with_context(|| format!("Expected a date like 2006-06-11, got {}", json))?;
+                Literal::Date((date - NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32)
+            }
+        },
+        PrimitiveType::Time => match json {
+            JsonValue::Number(_) => Literal::Time(parse_text(json, "microseconds from midnight")?),
+            _ => {
+                let text = scalar_text(json).ok_or_else(|| anyhow!("Expected a time, got {}", json))?;
+                let time = NaiveTime::parse_from_str(&text, "%H:%M:%S%.f")
+                    .with_context(|| format!("Expected a time like 22:31:08.123456, got {}", json))?;
+                Literal::Time(time.num_seconds_from_midnight() as i64 * 1_000_000 + time.nanosecond() as i64 / 1000)
+            }
+        },
+        PrimitiveType::Timestamp => match json {
+            JsonValue::Number(_) => Literal::Timestamp(parse_text(json, "microseconds from the epoch")?),
+            _ => {
+                let text = scalar_text(json).ok_or_else(|| anyhow!("Expected a timestamp, got {}", json))?;
+                Literal::Timestamp(parse_naive_timestamp(&text)?.timestamp_micros())
+            }
+        },
+        PrimitiveType::Timestamptz => match json {
+            JsonValue::Number(_) => Literal::TimestampTz(parse_text(json, "microseconds from the epoch")?),
+            _ => {
+                let text = scalar_text(json).ok_or_else(|| anyhow!("Expected a timestamp, got {}", json))?;
+                // Without an offset the timestamp is taken to be in UTC
+                let micros = match DateTime::parse_from_rfc3339(&text) {
+                    Ok(timestamp) => timestamp.timestamp_micros(),
+                    Err(_) => parse_naive_timestamp(&text)?.timestamp_micros(),
+                };
+                Literal::TimestampTz(micros)
+            }
+        },
+        PrimitiveType::String => match json {
+            JsonValue::String(s) => Literal::String(s.clone()),
+            other => bail!("Expected a string, got {}", other),
+        },
+        PrimitiveType::Uuid => {
+            let text = json.as_str().ok_or_else(|| anyhow!("Expected a UUID string, got {}", json))?;
+            Literal::Uuid(uuid::Uuid::parse_str(text).with_context(|| format!("Invalid UUID {}", text))?.as_u128())
+        }
+        PrimitiveType::Fixed(length) => {
+            let bytes = parse_hex(json)?;
+            if bytes.len() as u64 != *length {
+                bail!("Expected {} bytes, got {}", length, bytes.len());
+            }
+            Literal::Fixed(bytes)
+        }
+        PrimitiveType::Binary => Literal::Binary(parse_hex(json)?),
+    };
+    Ok(literal)
+}
+
+fn parse_naive_timestamp(text: &str) -> anyhow::Result<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f"))
+        .with_context(|| format!("Expected a timestamp like 2006-06-11T22:31:08.123456, got {}", text))
+}
+
+/// The unscaled value of a decimal such as `-12.34`, which may not have more than `scale`
+/// fractional digits or `precision` digits in total.
+fn parse_decimal(text: &str, precision: u32, scale: u32) -> anyhow::Result<i128> {
+    let invalid = || anyhow!("Expected a decimal({}, {}), got {}", precision, scale, text);
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        || fraction.len() > scale as usize
+    {
+        return Err(invalid());
+    }
+    let unscaled = format!("{}{:0<width$}", whole, fraction, width = scale as usize);
+    let unscaled = unscaled.trim_start_matches('0');
+    if unscaled.len() > precision as usize {
+        return Err(invalid());
+    }
+    let value: i128 = if unscaled.is_empty() { 0 } else { unscaled.parse().map_err(|_| invalid())? };
+    Ok(if negative { -value } else { value })
+}
+
+fn parse_hex(json: &JsonValue) -> anyhow::Result<Vec<u8>> {
+    let text = json.as_str().ok_or_else(|| anyhow!("Expected a hex string, got {}", json))?;
+    if text.len() % 2 != 0 {
+        bail!("Expected a hex string, got {}", json);
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| anyhow!("Expected a hex string, got {}", json)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::iceberg::schema::ListType;
+
+    #[test]
+    fn reads_values_of_the_table_types() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "review_date", Type::Primitive(PrimitiveType::Date)),
+                NestedField::optional(4, "reviewed_at", Type::Primitive(PrimitiveType::Timestamptz)),
+                NestedField::optional(5, "price", Type::Primitive(PrimitiveType::Decimal { precision: 9, scale: 2 })),
+                NestedField::optional(
+                    6,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 7,
+                        element_required: true,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+            ],
+        );
+
+        let body = json!([
+            {
+                "review_id": "R2RRIALQ1UBYO8",
+                "star_rating": "1",
+                "review_date": "2006-06-11",
+                "reviewed_at": "2006-06-11T01:00:00+01:00",
+                "price": 12.5,
+                "tags": ["history"]
+            },
+            {"review_id": "R1", "ignored": true}
+        ]);
+        let records = read_records(&schema, body.to_string().as_bytes(), &ReadOptions::default()).unwrap();
+        assert_eq!(
+            records[0],
+            vec![
+                Value::Primitive(Literal::String("R2RRIALQ1UBYO8".to_string())),
+                Value::Primitive(Literal::Int(1)),
+                Value::Primitive(Literal::Date(13310)),
+                Value::Primitive(Literal::TimestampTz(1_149_984_000_000_000)),
+                Value::Primitive(Literal::Decimal(1250)),
+                Value::List(vec![Value::Primitive(Literal::String("history".to_string()))]),
+            ]
+        );
+        assert_eq!(records[1][1..], [Value::Null, Value::Null, Value::Null, Value::Null, Value::Null]);
+    }
+
+    #[test]
+    fn reports_every_field_that_does_not_match() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(
+                    3,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 4,
+                        element_required: true,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+            ],
+        );
+        let body = br#"[
+            {"review_id": "R1", "star_rating": 5},
+            {"star_rating": "five", "tags": ["history", null]},
+            "R3",
+            {"review_id": "R4", "star_ratin": 1}
+        ]"#;
+
+        let error = read_records(&schema, body, &ReadOptions::default()).unwrap_err();
+        let error = error.downcast::<InvalidBody>().unwrap();
+        let fields = error.errors.iter().map(|e| (e.record, e.field.as_deref())).collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [(1, Some("review_id")), (1, Some("star_rating")), (1, Some("tags[1]")), (2, None)]
+        );
+        assert_eq!(error.errors[1].message, r#"Expected a 32-bit integer, got "five""#);
+
+        let reject = ReadOptions {
+            unknown_fields: UnknownFields::Reject,
+            ..Default::default()
+        };
+        let error = read_records(&schema, body, &reject).unwrap_err().downcast::<InvalidBody>().unwrap();
+        assert_eq!(error.errors.last().unwrap().field.as_deref(), Some("star_ratin"));
+        assert_eq!(error.errors.last().unwrap().message, "Unknown field");
+
+        let error = read_records(&schema, b"[{", &ReadOptions::default()).unwrap_err();
+        assert!(error.downcast::<InvalidBody>().unwrap().errors.is_empty());
+    }
+
+    #[test]
+    fn parses_decimals_exactly() {
+        assert_eq!(parse_decimal("-12.30", 4, 2).unwrap(), -1230);
+        assert_eq!(parse_decimal("0.05", 3, 2).unwrap(), 5);
+        assert_eq!(parse_decimal("7", 3, 2).unwrap(), 700);
+        assert!(parse_decimal("123.45", 4, 2).is_err());
+        assert!(parse_decimal("1e3", 9, 2).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::{env, fmt};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use arrow2::array::Array;
+use arrow2::chunk::Chunk;
+
+use crate::iceberg::arrow::records_to_chunk;
+use crate::iceberg::schema::Schema;
+
+// Readers for the request body formats, each producing records or columns of the table schema
+
+pub mod avro;
+pub mod csv;
+pub mod encoding;
+pub mod ipc;
+pub mod json;
+pub mod ndjson;
+
+use self::csv::CsvOptions;
+
+/// The formats request bodies can be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A JSON object or an array of objects.
+    Json,
+    /// A JSON object per line.
+    NdJson,
+    /// Comma or tab separated values, with or without a header row.
+    Csv(CsvOptions),
+    /// An Avro object container file.
+    Avro,
+    /// An Arrow IPC stream.
+    ArrowStream,
+}
+
+impl Format {
+    /// The format of a body sent with `content_type`, or `None` if it isn't one we can read.
+    /// Bodies without a specific content type are sniffed, so NDJSON sent as `application/json`
+    /// is read as NDJSON. CSV options come from the content type's parameters.
+    pub fn detect(content_type: Option<&str>, body: &[u8]) -> Option<Format> {
+        let mut parts = content_type.unwrap_or_default().split(';');
+        let media_type = content_type
+            .and(parts.next())
+            .map(|media_type| media_type.trim().to_ascii_lowercase());
+        let parameters = parts
+            .filter_map(|parameter| parameter.split_once('='))
+            .map(|(name, value)| (name.trim(), value.trim()));
+        match media_type.as_deref() {
+            Some("text/csv" | "application/csv") => CsvOptions::default().with_parameters(parameters).map(Format::Csv),
+            Some("text/tab-separated-values") => CsvOptions::tsv().with_parameters(parameters).map(Format::Csv),
+            Some("application/avro" | "avro/binary") => Some(Format::Avro),
+            Some("application/vnd.apache.arrow.stream") => Some(Format::ArrowStream),
+            Some("application/x-ndjson" | "application/ndjson" | "application/jsonl" | "application/x-jsonlines") => {
+                Some(Format::NdJson)
+            }
+            None | Some("" | "application/json" | "text/json" | "text/plain") => {
+                if ndjson::looks_like_ndjson(body) {
+                    Some(Format::NdJson)
+                } else {
+                    Some(Format::Json)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The records in `body` as arrow columns of the types of `schema`, one per top-level field.
+    pub fn read_chunk(
+        self,
+        schema: &Schema,
+        body: &[u8],
+        options: &ReadOptions,
+    ) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+        let records = match self {
+            Format::Json => json::read_records(schema, body, options)?,
+            Format::NdJson => ndjson::read_records(schema, body, options)?,
+            Format::Csv(csv_options) => csv::read_records(schema, body, &csv_options, options)?,
+            Format::Avro => avro::read_records(schema, body, options)?,
+            Format::ArrowStream => return ipc::read_chunk(schema, body, options),
+        };
+        records_to_chunk(schema, &records)
+    }
+}
+
+/// Bodies are checked in full so producers see every problem at once, but only this many are reported.
+pub const MAX_ERRORS: usize = 100;
+
+/// What to do with fields in a body that aren't in the table schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownFields {
+    /// Drop them, so producers can send more than the table holds.
+    #[default]
+    Ignore,
+    /// Report them as errors, to catch misspelled column names.
+    Reject,
+}
+
+/// Lambda can't be invoked with more than 6 MB, so there's no point accepting bigger bodies.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 6 * 1024 * 1024;
+
+/// Text compresses about ten times, so this allows for a full body of compressed JSON.
+pub const DEFAULT_MAX_DECOMPRESSED_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    pub unknown_fields: UnknownFields,
+    pub max_body_bytes: usize,
+    /// The most a compressed body may decompress to.
+    pub max_decompressed_bytes: usize,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            unknown_fields: UnknownFields::default(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_decompressed_bytes: DEFAULT_MAX_DECOMPRESSED_BYTES,
+        }
+    }
+}
+
+impl ReadOptions {
+    /// Options from `DOTSDB_UNKNOWN_FIELDS` (`ignore`, the default, or `reject`),
+    /// `DOTSDB_MAX_BODY_BYTES` and `DOTSDB_MAX_DECOMPRESSED_BYTES`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let unknown_fields = match env::var("DOTSDB_UNKNOWN_FIELDS").as_deref().unwrap_or("ignore") {
+            "ignore" => UnknownFields::Ignore,
+            "reject" => UnknownFields::Reject,
+            other => anyhow::bail!("Unknown DOTSDB_UNKNOWN_FIELDS policy {}", other),
+        };
+        let max_body_bytes = env_bytes("DOTSDB_MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES)?;
+        let max_decompressed_bytes = env_bytes("DOTSDB_MAX_DECOMPRESSED_BYTES", DEFAULT_MAX_DECOMPRESSED_BYTES)?;
+        Ok(ReadOptions {
+            unknown_fields,
+            max_body_bytes,
+            max_decompressed_bytes,
+        })
+    }
+}
+
+fn env_bytes(name: &str, default: usize) -> anyhow::Result<usize> {
+    match env::var(name) {
+        Ok(max) => max.parse().with_context(|| format!("Invalid {} {}", name, max)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// A value in a body that doesn't fit the table schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// The index of the record in the body.
+    pub record: usize,
+    /// The line of the body the record is on (from 1), for formats with a record per line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The path of the field within the record, e.g. `author.name` or `tags[2]`, or `None`
+    /// when the record as a whole is wrong.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+/// The body can't be read as records of the table. Either it isn't in the format at all,
+/// or some of its records don't match the schema and are listed in `errors`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvalidBody {
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl InvalidBody {
+    pub fn new(message: impl Into<String>) -> Self {
+        InvalidBody {
+            message: message.into(),
+            errors: vec![],
+        }
+    }
+
+    pub fn with_errors(errors: Vec<FieldError>) -> Self {
+        InvalidBody {
+            message: "Request body does not match the table schema".to_string(),
+            errors,
+        }
+    }
+}
+
+impl fmt::Display for InvalidBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for error in &self.errors {
+            match &error.field {
+                Some(field) => write!(f, "; record {}, {}: {}", error.record, field, error.message)?,
+                None => write!(f, "; record {}: {}", error.record, error.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidBody {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_by_content_type() {
+        let ndjson = b"{\"review_id\": \"R1\"}\n{\"review_id\": \"R2\"}\n";
+        assert_eq!(Format::detect(Some("application/x-ndjson"), ndjson), Some(Format::NdJson));
+        assert_eq!(Format::detect(Some("application/json"), ndjson), Some(Format::NdJson));
+        assert_eq!(Format::detect(None, b"[{\"review_id\": \"R1\"}]"), Some(Format::Json));
+        assert_eq!(
+            Format::detect(Some("text/tab-separated-values"), b"review_id\nR1"),
+            Some(Format::Csv(CsvOptions::tsv()))
+        );
+        assert_eq!(
+            Format::detect(Some("text/csv; header=absent"), b"R1"),
+            CsvOptions::default()
+                .with_parameters([("header", "absent")].into_iter())
+                .map(Format::Csv)
+        );
+        assert_eq!(
+            Format::detect(Some("application/vnd.apache.arrow.stream"), b""),
+            Some(Format::ArrowStream)
+        );
+        assert_eq!(Format::detect(Some("application/xml"), b"<review/>"), None);
+    }
+}
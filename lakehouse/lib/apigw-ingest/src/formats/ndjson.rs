@@ -0,0 +1,96 @@
+use serde::de::IgnoredAny;
+use serde_json::Value as JsonValue;
+
+use crate::formats::json::RecordReader;
+use crate::formats::{InvalidBody, ReadOptions, MAX_ERRORS};
+use crate::iceberg::schema::Schema;
+use crate::iceberg::values::Record;
+
+// Newline-delimited JSON (https://github.com/ndjson/ndjson-spec): one JSON object per line,
+// read like the objects of a JSON array body. Blank lines are skipped.
+
+/// The records on the lines of `body`, converted to the types of `schema`. Fails with
+/// [`InvalidBody`] listing every line that isn't JSON and every value that doesn't match the schema.
+pub fn read_records(schema: &Schema, body: &[u8], options: &ReadOptions) -> anyhow::Result<Vec<Record>> {
+    let mut errors = vec![];
+    let mut records = vec![];
+    let lines = body.split(|b| *b == b'\n').map(<[u8]>::trim_ascii).enumerate();
+    for (index, line) in lines.filter(|(_, line)| !line.is_empty()) {
+        let mut reader = RecordReader::new(records.len(), options, &mut errors).at_line(index + 1);
+        let record = match serde_json::from_slice::<JsonValue>(line) {
+            Ok(object) => reader.read_record(schema, &object),
+            Err(err) => {
+                reader.error("", format!("Line is not valid JSON: {}", err));
+                vec![]
+            }
+        };
+        records.push(record);
+    }
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        errors.truncate(MAX_ERRORS);
+        Err(InvalidBody::with_errors(errors).into())
+    }
+}
+
+/// Whether `body` holds more than one JSON value, each starting on a new line.
+pub fn looks_like_ndjson(body: &[u8]) -> bool {
+    let mut values = serde_json::Deserializer::from_slice(body).into_iter::<IgnoredAny>();
+    if !matches!(values.next(), Some(Ok(_))) {
+        return false;
+    }
+    let rest = &body[values.byte_offset()..];
+    let next = rest.iter().position(|b| !b.is_ascii_whitespace());
+    next.is_some_and(|next| rest[..next].contains(&b'\n'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::FieldError;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, Type};
+    use crate::iceberg::values::{Literal, Value};
+
+    #[test]
+    fn reads_a_record_per_line() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+            ],
+        );
+        let body = b"{\"review_id\": \"R1\", \"star_rating\": 5}\r\n\n{\"review_id\": \"R2\"}\n";
+        let records = read_records(&schema, body, &ReadOptions::default()).unwrap();
+        assert_eq!(
+            records,
+            [
+                vec![Value::Primitive(Literal::String("R1".to_string())), Value::Primitive(Literal::Int(5))],
+                vec![Value::Primitive(Literal::String("R2".to_string())), Value::Null],
+            ]
+        );
+
+        let body = b"{\"review_id\": \"R1\"}\n{\"review_id\": \n\n{\"star_rating\": \"five\"}";
+        let error = read_records(&schema, body, &ReadOptions::default()).unwrap_err();
+        let errors = error.downcast::<InvalidBody>().unwrap().errors;
+        let at = |e: &FieldError| (e.record, e.line, e.field.clone());
+        assert_eq!(
+            errors.iter().map(at).collect::<Vec<_>>(),
+            [
+                (1, Some(2), None),
+                (2, Some(4), Some("review_id".to_string())),
+                (2, Some(4), Some("star_rating".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn sniffs_values_on_separate_lines() {
+        assert!(looks_like_ndjson(b"{\"a\": 1}\n{\"a\": 2}"));
+        assert!(looks_like_ndjson(b"{\"a\": 1}\r\n{\"a\": "));
+        assert!(!looks_like_ndjson(b"{\"a\": 1}\n"));
+        assert!(!looks_like_ndjson(b"[{\"a\": 1},\n{\"a\": 2}]"));
+        assert!(!looks_like_ndjson(b"{\"a\": 1} {\"a\": 2}"));
+    }
+}
@@ -0,0 +1,440 @@
+use anyhow::{anyhow, bail};
+use arrow2::array::{
+    Array, BinaryArray, BooleanArray, FixedSizeBinaryArray, ListArray, PrimitiveArray, StructArray, Utf8Array,
+};
+use arrow2::bitmap::Bitmap;
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, Metadata, Schema as ArrowSchema, TimeUnit};
+use arrow2::types::NativeType;
+
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+use crate::iceberg::values::{Literal, Record, Value};
+
+// Converting Iceberg schemas and records to arrow, so they can be written as Parquet.
+// https://iceberg.apache.org/spec/#parquet for how each type is stored.
+
+/// The arrow field metadata key holding the Iceberg field id, as arrow-rs and pyarrow name it.
+pub const FIELD_ID_KEY: &str = "PARQUET:field_id";
+
+/// The arrow schema of `schema`, with one top-level field per table column.
+pub fn schema_to_arrow(schema: &Schema) -> ArrowSchema {
+    ArrowSchema::from(schema.fields.iter().map(field_to_arrow).collect::<Vec<_>>())
+}
+
+/// The Iceberg field id an arrow field was created from.
+pub fn field_id(field: &Field) -> Option<i32> {
+    field.metadata.get(FIELD_ID_KEY).and_then(|id| id.parse().ok())
+}
+
+fn field_to_arrow(field: &NestedField) -> Field {
+    arrow_field(field.id, &field.name, &field.field_type, !field.required)
+}
+
+pub(crate) fn arrow_field(id: i32, name: &str, field_type: &Type, nullable: bool) -> Field {
+    let metadata = Metadata::from([(FIELD_ID_KEY.to_string(), id.to_string())]);
+    Field::new(name, type_to_arrow(field_type, nullable), nullable).with_metadata(metadata)
+}
+
+fn type_to_arrow(field_type: &Type, nullable: bool) -> DataType {
+    match field_type {
+        Type::Primitive(primitive) => match primitive {
+            PrimitiveType::Boolean => DataType::Boolean,
+            PrimitiveType::Int => DataType::Int32,
+            PrimitiveType::Long => DataType::Int64,
+            PrimitiveType::Float => DataType::Float32,
+            PrimitiveType::Double => DataType::Float64,
+            PrimitiveType::Decimal { precision, scale } => DataType::Decimal(*precision as usize, *scale as usize),
+            PrimitiveType::Date => DataType::Date32,
+            PrimitiveType::Time => DataType::Time64(TimeUnit::Microsecond),
+            PrimitiveType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            PrimitiveType::Timestamptz => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".to_string())),
+            PrimitiveType::String => DataType::Utf8,
+            PrimitiveType::Uuid => DataType::FixedSizeBinary(16),
+            PrimitiveType::Fixed(length) => DataType::FixedSizeBinary(*length as usize),
+            PrimitiveType::Binary => DataType::Binary,
+        },
+        // The fields of a nullable struct are nullable too: arrow2 writes a value of a required
+        // column for every row, even where the struct is null, which makes the page invalid
+        Type::Struct(struct_type) => DataType::Struct(
+            struct_type
+                .fields
+                .iter()
+                .map(|field| arrow_field(field.id, &field.name, &field.field_type, nullable || !field.required))
+                .collect(),
+        ),
+        Type::List(list) => DataType::List(Box::new(arrow_field(
+            list.element_id,
+            "element",
+            &list.element,
+            !list.element_required,
+        ))),
+        // arrow2 can't write map arrays to Parquet, but a list of required key/value structs
+        // has the same columns and levels as a Parquet map
+        Type::Map(map) => {
+            let entries = DataType::Struct(vec![
+                arrow_field(map.key_id, "key", &map.key, false),
+                arrow_field(map.value_id, "value", &map.value, !map.value_required),
+            ]);
+            DataType::List(Box::new(Field::new("key_value", entries, false)))
+        }
+    }
+}
+
+/// Lays `records` out as arrow columns, one per top-level field of `schema`.
+pub fn records_to_chunk(schema: &Schema, records: &[Record]) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+    let arrow_schema = schema_to_arrow(schema);
+    let columns = schema
+        .fields
+        .iter()
+        .zip(arrow_schema.fields.iter())
+        .enumerate()
+        .map(|(i, (field, arrow_field))| {
+            let values = records
+                .iter()
+                .map(|record| record.get(i).unwrap_or(&Value::Null))
+                .collect();
+            to_array(&field.field_type, &arrow_field.data_type, values)
+                .map_err(|err| anyhow!("Column {}: {}", field.name, err))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Chunk::try_new(columns)?)
+}
+
+fn validity(values: &[&Value]) -> Option<Bitmap> {
+    if values.iter().any(|value| value.is_null()) {
+        Some(values.iter().map(|value| !value.is_null()).collect())
+    } else {
+        None
+    }
+}
+
+fn to_array(field_type: &Type, data_type: &DataType, values: Vec<&Value>) -> anyhow::Result<Box<dyn Array>> {
+    match field_type {
+        Type::Primitive(primitive) => primitive_array(primitive, data_type, &values),
+        Type::Struct(struct_type) => {
+            let DataType::Struct(arrow_fields) = data_type else {
+                bail!("{:?} is not a struct", data_type)
+            };
+            let children = struct_type
+                .fields
+                .iter()
+                .zip(arrow_fields)
+                .enumerate()
+                .map(|(i, (field, arrow_field))| {
+                    let children = values
+                        .iter()
+                        .map(|value| match value {
+                            Value::Struct(fields) => Ok(fields.get(i).unwrap_or(&Value::Null)),
+                            Value::Null => Ok(&Value::Null),
+                            other => Err(anyhow!("Expected a struct, got {:?}", other)),
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    to_array(&field.field_type, &arrow_field.data_type, children)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(StructArray::try_new(data_type.clone(), children, validity(&values))?.boxed())
+        }
+        Type::List(list) => {
+            let DataType::List(element_field) = data_type else {
+                bail!("{:?} is not a list", data_type)
+            };
+            let mut offsets = vec![0i32];
+            let mut elements = vec![];
+            for value in &values {
+                match value {
+                    Value::List(items) => elements.extend(items.iter()),
+                    Value::Null => {}
+                    other => bail!("Expected a list, got {:?}", other),
+                }
+                offsets.push(elements.len() as i32);
+            }
+            let elements = to_array(&list.element, &element_field.data_type, elements)?;
+            Ok(ListArray::<i32>::try_new(data_type.clone(), offsets.into(), elements, validity(&values))?.boxed())
+        }
+        Type::Map(map) => {
+            let DataType::List(entries_field) = data_type else {
+                bail!("{:?} is not a map", data_type)
+            };
+            let DataType::Struct(entry_fields) = &entries_field.data_type else {
+                bail!("{:?} is not a map", data_type)
+            };
+            let mut offsets = vec![0i32];
+            let mut keys = vec![];
+            let mut map_values = vec![];
+            for value in &values {
+                match value {
+                    Value::Map(entries) => {
+                        for (key, value) in entries {
+                            keys.push(key);
+                            map_values.push(value);
+                        }
+                    }
+                    Value::Null => {}
+                    other => bail!("Expected a map, got {:?}", other),
+                }
+                offsets.push(keys.len() as i32);
+            }
+            let entries = StructArray::try_new(
+                entries_field.data_type.clone(),
+                vec![
+                    to_array(&map.key, &entry_fields[0].data_type, keys)?,
+                    to_array(&map.value, &entry_fields[1].data_type, map_values)?,
+                ],
+                None,
+            )?;
+            Ok(
+                ListArray::<i32>::try_new(data_type.clone(), offsets.into(), entries.boxed(), validity(&values))?
+                    .boxed(),
+            )
+        }
+    }
+}
+
+/// The array of the field `id` among `fields` and their structs, with the rows where a struct
+/// holding it is null made null.
+pub(crate) fn field_array(fields: &[NestedField], arrays: &[Box<dyn Array>], id: i32) -> Option<Box<dyn Array>> {
+    fields.iter().zip(arrays).find_map(|(field, array)| match &field.field_type {
+        _ if field.id == id => Some(array.clone()),
+        Type::Struct(struct_type) => {
+            let array = array.as_any().downcast_ref::<StructArray>()?;
+            let child = field_array(&struct_type.fields, array.values(), id)?;
+            let validity = match (array.validity(), child.validity()) {
+                (Some(parent), Some(child)) => Some(parent & child),
+                (parent, child) => parent.or(child).cloned(),
+            };
+            Some(child.with_validity(validity))
+        }
+        _ => None,
+    })
+}
+
+/// The value at `index` of `array`, an array of `primitive` values, or `None` if it's null.
+pub fn literal_at(primitive: &PrimitiveType, array: &dyn Array, index: usize) -> Option<Literal> {
+    if array.is_null(index) {
+        return None;
+    }
+    let any = array.as_any();
+    let literal = match primitive {
+        PrimitiveType::Boolean => Literal::Boolean(any.downcast_ref::<BooleanArray>()?.value(index)),
+        PrimitiveType::Int | PrimitiveType::Date => {
+            let value = any.downcast_ref::<PrimitiveArray<i32>>()?.value(index);
+            match primitive {
+                PrimitiveType::Int => Literal::Int(value),
+                _ => Literal::Date(value),
+            }
+        }
+        PrimitiveType::Long | PrimitiveType::Time | PrimitiveType::Timestamp | PrimitiveType::Timestamptz => {
+            let value = any.downcast_ref::<PrimitiveArray<i64>>()?.value(index);
+            match primitive {
+                PrimitiveType::Long => Literal::Long(value),
+                PrimitiveType::Time => Literal::Time(value),
+                PrimitiveType::Timestamp => Literal::Timestamp(value),
+                _ => Literal::TimestampTz(value),
+            }
+        }
+        PrimitiveType::Float => Literal::Float(any.downcast_ref::<PrimitiveArray<f32>>()?.value(index)),
+        PrimitiveType::Double => Literal::Double(any.downcast_ref::<PrimitiveArray<f64>>()?.value(index)),
+        PrimitiveType::Decimal { .. } => Literal::Decimal(any.downcast_ref::<PrimitiveArray<i128>>()?.value(index)),
+        PrimitiveType::String => Literal::String(any.downcast_ref::<Utf8Array<i32>>()?.value(index).to_string()),
+        PrimitiveType::Binary => Literal::Binary(any.downcast_ref::<BinaryArray<i32>>()?.value(index).to_vec()),
+        PrimitiveType::Uuid => {
+            let value = any.downcast_ref::<FixedSizeBinaryArray>()?.value(index);
+            Literal::Uuid(u128::from_be_bytes(value.try_into().ok()?))
+        }
+        PrimitiveType::Fixed(_) => Literal::Fixed(any.downcast_ref::<FixedSizeBinaryArray>()?.value(index).to_vec()),
+    };
+    Some(literal)
+}
+
+/// Collects the literal of each value with `f`, which returns `None` for literals of the wrong type.
+fn literals<'a, T>(
+    values: &[&'a Value],
+    expected: &PrimitiveType,
+    f: impl Fn(&'a Literal) -> Option<T>,
+) -> anyhow::Result<Vec<Option<T>>> {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::Null => Ok(None),
+            Value::Primitive(literal) => f(literal)
+                .map(Some)
+                .ok_or_else(|| anyhow!("Expected a {:?} value, got {:?}", expected, literal)),
+            other => Err(anyhow!("Expected a {:?} value, got {:?}", expected, other)),
+        })
+        .collect()
+}
+
+fn native_array<T: NativeType>(data_type: &DataType, values: Vec<Option<T>>) -> Box<dyn Array> {
+    PrimitiveArray::<T>::from(values).to(data_type.clone()).boxed()
+}
+
+fn primitive_array(
+    primitive: &PrimitiveType,
+    data_type: &DataType,
+    values: &[&Value],
+) -> anyhow::Result<Box<dyn Array>> {
+    let array = match primitive {
+        PrimitiveType::Boolean => BooleanArray::from(literals(values, primitive, |l| match l {
+            Literal::Boolean(v) => Some(*v),
+            _ => None,
+        })?)
+        .boxed(),
+        PrimitiveType::Int | PrimitiveType::Date => native_array(
+            data_type,
+            literals(values, primitive, |l| match l {
+                Literal::Int(v) | Literal::Date(v) => Some(*v),
+                _ => None,
+            })?,
+        ),
+        PrimitiveType::Long | PrimitiveType::Time | PrimitiveType::Timestamp | PrimitiveType::Timestamptz => {
+            native_array(
+                data_type,
+                literals(values, primitive, |l| match l {
+                    Literal::Long(v) | Literal::Time(v) | Literal::Timestamp(v) | Literal::TimestampTz(v) => Some(*v),
+                    _ => None,
+                })?,
+            )
+        }
+        PrimitiveType::Float => native_array(
+            data_type,
+            literals(values, primitive, |l| match l {
+                Literal::Float(v) => Some(*v),
+                _ => None,
+            })?,
+        ),
+        PrimitiveType::Double => native_array(
+            data_type,
+            literals(values, primitive, |l| match l {
+                Literal::Double(v) => Some(*v),
+                _ => None,
+            })?,
+        ),
+        PrimitiveType::Decimal { .. } => native_array(
+            data_type,
+            literals(values, primitive, |l| match l {
+                Literal::Decimal(v) => Some(*v),
+                _ => None,
+            })?,
+        ),
+        PrimitiveType::String => {
+            let strings = literals(values, primitive, |l| match l {
+                Literal::String(v) => Some(v.as_str()),
+                _ => None,
+            })?;
+            Utf8Array::<i32>::from_iter(strings).boxed()
+        }
+        PrimitiveType::Binary => {
+            let bytes = literals(values, primitive, |l| match l {
+                Literal::Binary(v) | Literal::Fixed(v) => Some(v.as_slice()),
+                _ => None,
+            })?;
+            BinaryArray::<i32>::from_iter(bytes).boxed()
+        }
+        PrimitiveType::Uuid | PrimitiveType::Fixed(_) => {
+            let length = match primitive {
+                PrimitiveType::Fixed(length) => *length as usize,
+                _ => 16,
+            };
+            let bytes = literals(values, primitive, |l| match l {
+                Literal::Uuid(v) => Some(v.to_be_bytes().to_vec()),
+                Literal::Fixed(v) | Literal::Binary(v) => Some(v.clone()),
+                _ => None,
+            })?;
+            let mut buffer = Vec::with_capacity(bytes.len() * length);
+            for value in &bytes {
+                match value {
+                    Some(value) if value.len() != length => {
+                        bail!("Expected {} bytes, got {}", length, value.len())
+                    }
+                    Some(value) => buffer.extend_from_slice(value),
+                    None => buffer.resize(buffer.len() + length, 0),
+                }
+            }
+            FixedSizeBinaryArray::try_new(data_type.clone(), buffer.into(), validity(values))?.boxed()
+        }
+    };
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::schema::{ListType, MapType, StructType};
+
+    #[test]
+    fn converts_nested_records() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)),
+                NestedField::optional(2, "review_date", Type::Primitive(PrimitiveType::Date)),
+                NestedField::optional(
+                    3,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 6,
+                        element_required: true,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+                NestedField::optional(
+                    4,
+                    "votes",
+                    Type::Map(MapType {
+                        key_id: 7,
+                        key: Box::new(Type::Primitive(PrimitiveType::String)),
+                        value_id: 8,
+                        value_required: false,
+                        value: Box::new(Type::Primitive(PrimitiveType::Int)),
+                    }),
+                ),
+                NestedField::optional(
+                    5,
+                    "author",
+                    Type::Struct(StructType {
+                        fields: vec![NestedField::optional(9, "name", Type::Primitive(PrimitiveType::String))],
+                    }),
+                ),
+            ],
+        );
+
+        let arrow_schema = schema_to_arrow(&schema);
+        assert_eq!(
+            arrow_schema.fields.iter().map(field_id).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5].map(Some)
+        );
+        assert_eq!(arrow_schema.fields[1].data_type, DataType::Date32);
+        let DataType::List(element) = &arrow_schema.fields[2].data_type else {
+            panic!("tags is not a list")
+        };
+        assert_eq!(field_id(element), Some(6));
+
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let records = vec![
+            vec![
+                Value::Primitive(Literal::Long(1)),
+                Value::Primitive(Literal::Date(13310)),
+                Value::List(vec![string("history"), string("ireland")]),
+                Value::Map(vec![(string("helpful"), Value::Primitive(Literal::Int(153)))]),
+                Value::Struct(vec![string("Thomas Cahill")]),
+            ],
+            vec![
+                Value::Primitive(Literal::Long(2)),
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Null,
+            ],
+        ];
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+        assert_eq!(chunk.len(), 2);
+        assert_eq!(chunk.arrays().len(), 5);
+        let tags = chunk.arrays()[2].as_any().downcast_ref::<ListArray<i32>>().unwrap();
+        assert_eq!(tags.offsets().as_slice(), [0, 2, 2]);
+        assert_eq!(tags.null_count(), 1);
+        assert_eq!(chunk.arrays()[4].null_count(), 1);
+
+        let wrong_type = vec![vec![string("1"), Value::Null, Value::Null, Value::Null, Value::Null]];
+        assert!(records_to_chunk(&schema, &wrong_type).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use apache_avro::types::Value as AvroValue;
+use serde_json::{json, Value};
+
+use crate::iceberg::schema::PrimitiveType;
+use crate::iceberg::values::Literal;
+
+// Helpers for building the Avro schemas of manifests and manifest lists.
+// Iceberg readers resolve Avro fields by the "field-id" attribute, so every field carries one.
+
+pub(crate) fn required_field(name: &str, schema: Value, field_id: i32) -> Value {
+    json!({ "name": name, "type": schema, "field-id": field_id })
+}
+
+pub(crate) fn optional_field(name: &str, schema: Value, field_id: i32) -> Value {
+    json!({ "name": name, "type": ["null", schema], "default": null, "field-id": field_id })
+}
+
+pub(crate) fn record(name: &str, fields: Vec<Value>) -> Value {
+    json!({ "type": "record", "name": name, "fields": fields })
+}
+
+pub(crate) fn list(element: Value, element_id: i32) -> Value {
+    json!({ "type": "array", "items": element, "element-id": element_id })
+}
+
+/// Maps with non-string keys are stored as arrays of key/value records.
+pub(crate) fn int_map(value: Value, key_id: i32, value_id: i32) -> Value {
+    json!({
+        "type": "array",
+        "logicalType": "map",
+        "items": record(
+            &format!("k{}_v{}", key_id, value_id),
+            vec![required_field("key", json!("int"), key_id), required_field("value", value, value_id)],
+        ),
+    })
+}
+
+/// Number of bytes needed to hold an unscaled decimal of the given precision.
+pub(crate) fn decimal_required_bytes(precision: u32) -> usize {
+    (1..=16)
+        .find(|bytes| 2f64.powi(8 * *bytes as i32 - 1) >= 10f64.powi(precision as i32))
+        .unwrap_or(16)
+}
+
+pub(crate) fn primitive_schema(primitive: &PrimitiveType) -> Value {
+    match primitive {
+        PrimitiveType::Boolean => json!("boolean"),
+        PrimitiveType::Int => json!("int"),
+        PrimitiveType::Long => json!("long"),
+        PrimitiveType::Float => json!("float"),
+        PrimitiveType::Double => json!("double"),
+        PrimitiveType::Decimal { precision, scale } => json!({
+            "type": "fixed",
+            "name": format!("decimal_{}_{}", precision, scale),
+            "size": decimal_required_bytes(*precision),
+            "logicalType": "decimal",
+            "precision": precision,
+            "scale": scale,
+        }),
+        PrimitiveType::Date => json!({ "type": "int", "logicalType": "date" }),
+        PrimitiveType::Time => json!({ "type": "long", "logicalType": "time-micros" }),
+        PrimitiveType::Timestamp => json!({ "type": "long", "logicalType": "timestamp-micros", "adjust-to-utc": false }),
+        PrimitiveType::Timestamptz => json!({ "type": "long", "logicalType": "timestamp-micros", "adjust-to-utc": true }),
+        PrimitiveType::String => json!("string"),
+        PrimitiveType::Uuid => json!({ "type": "fixed", "name": "uuid_fixed", "size": 16 }),
+        PrimitiveType::Fixed(length) => json!({ "type": "fixed", "name": format!("fixed_{}", length), "size": length }),
+        PrimitiveType::Binary => json!("bytes"),
+    }
+}
+
+pub(crate) fn optional_value(value: Option<AvroValue>) -> AvroValue {
+    match value {
+        Some(value) => AvroValue::Union(1, Box::new(value)),
+        None => AvroValue::Union(0, Box::new(AvroValue::Null)),
+    }
+}
+
+pub(crate) fn literal_value(literal: &Literal, primitive: &PrimitiveType) -> AvroValue {
+    match literal {
+        Literal::Boolean(v) => AvroValue::Boolean(*v),
+        Literal::Int(v) => AvroValue::Int(*v),
+        Literal::Long(v) => AvroValue::Long(*v),
+        Literal::Float(v) => AvroValue::Float(*v),
+        Literal::Double(v) => AvroValue::Double(*v),
+        Literal::Date(v) => AvroValue::Date(*v),
+        Literal::Time(v) => AvroValue::TimeMicros(*v),
+        Literal::Timestamp(v) | Literal::TimestampTz(v) => AvroValue::TimestampMicros(*v),
+        Literal::String(v) => AvroValue::String(v.clone()),
+        Literal::Uuid(v) => AvroValue::Fixed(16, v.to_be_bytes().to_vec()),
+        Literal::Fixed(v) => AvroValue::Fixed(v.len(), v.clone()),
+        Literal::Binary(v) => AvroValue::Bytes(v.clone()),
+        Literal::Decimal(v) => {
+            let size = match primitive {
+                PrimitiveType::Decimal { precision, .. } => decimal_required_bytes(*precision),
+                _ => 16,
+            };
+            let bytes = v.to_be_bytes();
+            AvroValue::Decimal(bytes[16 - size..].to_vec().into())
+        }
+    }
+}
+
+/// The literal an Avro value read back from a manifest holds, the inverse of `literal_value`.
+pub(crate) fn value_literal(value: &AvroValue, primitive: &PrimitiveType) -> anyhow::Result<Literal> {
+    let literal = match (primitive, value) {
+        (PrimitiveType::Boolean, AvroValue::Boolean(v)) => Literal::Boolean(*v),
+        (PrimitiveType::Int, AvroValue::Int(v)) => Literal::Int(*v),
+        (PrimitiveType::Long, AvroValue::Long(v)) => Literal::Long(*v),
+        (PrimitiveType::Long, AvroValue::Int(v)) => Literal::Long(*v as i64),
+        (PrimitiveType::Float, AvroValue::Float(v)) => Literal::Float(*v),
+        (PrimitiveType::Double, AvroValue::Double(v)) => Literal::Double(*v),
+        (PrimitiveType::Date, AvroValue::Date(v) | AvroValue::Int(v)) => Literal::Date(*v),
+        (PrimitiveType::Time, AvroValue::TimeMicros(v) | AvroValue::Long(v)) => Literal::Time(*v),
+        (PrimitiveType::Timestamp, AvroValue::TimestampMicros(v) | AvroValue::Long(v)) => Literal::Timestamp(*v),
+        (PrimitiveType::Timestamptz, AvroValue::TimestampMicros(v) | AvroValue::Long(v)) => Literal::TimestampTz(*v),
+        (PrimitiveType::String, AvroValue::String(v)) => Literal::String(v.clone()),
+        (PrimitiveType::Uuid, AvroValue::Fixed(16, v)) => Literal::Uuid(u128::from_be_bytes(v[..].try_into()?)),
+        (PrimitiveType::Uuid, AvroValue::Uuid(v)) => Literal::Uuid(v.as_u128()),
+        (PrimitiveType::Fixed(_), AvroValue::Fixed(_, v)) => Literal::Fixed(v.clone()),
+        (PrimitiveType::Binary, AvroValue::Bytes(v)) => Literal::Binary(v.clone()),
+        (PrimitiveType::Decimal { .. }, AvroValue::Decimal(v)) => {
+            let bytes: Vec<u8> = v.try_into()?;
+            Literal::from_bytes(primitive, &bytes).ok_or_else(|| anyhow::anyhow!("Invalid decimal {:?}", bytes))?
+        }
+        (PrimitiveType::Decimal { .. }, AvroValue::Fixed(_, bytes) | AvroValue::Bytes(bytes)) => {
+            Literal::from_bytes(primitive, bytes).ok_or_else(|| anyhow::anyhow!("Invalid decimal {:?}", bytes))?
+        }
+        (primitive, value) => anyhow::bail!("Expected a {} value, found {:?}", primitive, value),
+    };
+    Ok(literal)
+}
+
+/// Field access for records read back from manifests and manifest lists.
+pub(crate) struct Record<'a>(&'a [(String, AvroValue)]);
+
+impl<'a> Record<'a> {
+    pub(crate) fn new(value: &'a AvroValue) -> anyhow::Result<Self> {
+        match value {
+            AvroValue::Record(fields) => Ok(Record(fields)),
+            _ => anyhow::bail!("Expected an Avro record, found {:?}", value),
+        }
+    }
+
+    /// The value of a field, with unions unwrapped and nulls treated as missing.
+    pub(crate) fn get(&self, name: &str) -> Option<&'a AvroValue> {
+        let value = self.0.iter().find(|(n, _)| n == name).map(|(_, v)| v)?;
+        match value {
+            AvroValue::Union(_, inner) => match inner.as_ref() {
+                AvroValue::Null => None,
+                inner => Some(inner),
+            },
+            AvroValue::Null => None,
+            value => Some(value),
+        }
+    }
+
+    pub(crate) fn int(&self, name: &str) -> anyhow::Result<Option<i32>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(AvroValue::Int(v)) => Ok(Some(*v)),
+            Some(v) => anyhow::bail!("Field {} is not an int: {:?}", name, v),
+        }
+    }
+
+    pub(crate) fn long(&self, name: &str) -> anyhow::Result<Option<i64>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(AvroValue::Long(v)) => Ok(Some(*v)),
+            Some(AvroValue::Int(v)) => Ok(Some(*v as i64)),
+            Some(v) => anyhow::bail!("Field {} is not a long: {:?}", name, v),
+        }
+    }
+
+    pub(crate) fn boolean(&self, name: &str) -> anyhow::Result<Option<bool>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(AvroValue::Boolean(v)) => Ok(Some(*v)),
+            Some(v) => anyhow::bail!("Field {} is not a boolean: {:?}", name, v),
+        }
+    }
+
+    pub(crate) fn string(&self, name: &str) -> anyhow::Result<Option<String>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(AvroValue::String(v)) => Ok(Some(v.clone())),
+            Some(v) => anyhow::bail!("Field {} is not a string: {:?}", name, v),
+        }
+    }
+
+    pub(crate) fn bytes(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(AvroValue::Bytes(v)) | Some(AvroValue::Fixed(_, v)) => Ok(Some(v.clone())),
+            Some(v) => anyhow::bail!("Field {} is not binary: {:?}", name, v),
+        }
+    }
+
+    pub(crate) fn array(&self, name: &str) -> anyhow::Result<Option<&'a [AvroValue]>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(AvroValue::Array(v)) => Ok(Some(v)),
+            Some(v) => anyhow::bail!("Field {} is not an array: {:?}", name, v),
+        }
+    }
+}
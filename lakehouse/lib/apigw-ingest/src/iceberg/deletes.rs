@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+use arrow2::array::{Array, BooleanArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::filter::filter_chunk;
+
+use crate::iceberg::arrow::{field_array, literal_at};
+use crate::iceberg::partition::PartitionSpec;
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+use crate::iceberg::transform::Transform;
+use crate::iceberg::values::Literal;
+
+// Delete files - https://iceberg.apache.org/spec/#delete-formats
+// An equality delete file holds values of some columns of the table, and deletes the rows of
+// the data files with lower sequence numbers, in the same partition, that have the same values.
+// A position delete file holds the paths of data files and positions of rows in them, sorted
+// by both, and deletes those rows from files with the same or lower sequence numbers.
+
+/// The field id of a position delete's data file path, reserved by the spec.
+pub const DELETE_FILE_PATH_ID: i32 = 2147483546;
+/// The field id of a position delete's row position, reserved by the spec.
+pub const DELETE_POS_ID: i32 = 2147483545;
+
+/// The schema of position delete files.
+pub fn position_delete_schema() -> Schema {
+    Schema::new(
+        0,
+        vec![
+            NestedField::required(DELETE_FILE_PATH_ID, "file_path", Type::Primitive(PrimitiveType::String)),
+            NestedField::required(DELETE_POS_ID, "pos", Type::Primitive(PrimitiveType::Long)),
+        ],
+    )
+}
+
+/// The columns rows are matched on by equality deletes, and the schema of the delete files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EqualityDeletes {
+    equality_ids: Vec<i32>,
+    schema: Schema,
+}
+
+impl EqualityDeletes {
+    /// Deletes matching on `equality_ids`, top-level primitive columns of `schema` that aren't
+    /// floating point, as the spec asks of identifier fields.
+    pub fn new(schema: &Schema, equality_ids: Vec<i32>) -> anyhow::Result<Self> {
+        let fields = equality_ids
+            .iter()
+            .map(|id| {
+                let field = schema
+                    .fields
+                    .iter()
+                    .find(|field| field.id == *id)
+                    .ok_or_else(|| anyhow!("Equality field {} is not a top-level column", id))?;
+                match &field.field_type {
+                    Type::Primitive(PrimitiveType::Float | PrimitiveType::Double) => {
+                        bail!("Equality field {} is a floating point column", field.name)
+                    }
+                    Type::Primitive(_) => Ok(field.clone()),
+                    _ => bail!("Equality field {} is not a primitive column", field.name),
+                }
+            })
+            .collect::<anyhow::Result<Vec<NestedField>>>()?;
+        if fields.is_empty() {
+            bail!("Equality deletes need at least one column");
+        }
+        Ok(EqualityDeletes {
+            equality_ids,
+            schema: Schema::new(schema.schema_id, fields),
+        })
+    }
+
+    /// Deletes matching on the identifier fields of `schema`.
+    pub fn identifier(schema: &Schema) -> anyhow::Result<Self> {
+        let ids = schema.identifier_field_ids.clone().unwrap_or_default();
+        if ids.is_empty() {
+            bail!("The table has no identifier fields");
+        }
+        EqualityDeletes::new(schema, ids)
+    }
+
+    pub fn equality_ids(&self) -> &[i32] {
+        &self.equality_ids
+    }
+
+    /// The schema of the delete files: the equality columns.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Fails unless every partition field of `spec` is derived from an equality column, so rows
+    /// with the same values are always in the same partition. Equality deletes only apply within
+    /// their partition, and would miss a row whose partition changed.
+    pub fn check_partitioning(&self, schema: &Schema, spec: &PartitionSpec) -> anyhow::Result<()> {
+        for field in &spec.fields {
+            if field.transform != Transform::Void && !self.equality_ids.contains(&field.source_id) {
+                let source = schema
+                    .field_by_id(field.source_id)
+                    .map_or_else(|| field.source_id.to_string(), |source| source.name.clone());
+                bail!("The table is partitioned by {}, which is not an identifier field", source);
+            }
+        }
+        Ok(())
+    }
+
+    /// The equality columns of `chunk`, whose columns are the fields of `schema`.
+    pub fn project(&self, schema: &Schema, chunk: &Chunk<Box<dyn Array>>) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+        let arrays = self
+            .equality_ids
+            .iter()
+            .map(|id| {
+                field_array(&schema.fields, chunk.arrays(), *id)
+                    .ok_or_else(|| anyhow!("Equality field {} is not in the chunk", id))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Chunk::new(arrays))
+    }
+
+    /// The values of the equality columns in every row of `keys`, a chunk with the columns of
+    /// the delete files, in a form rows can be matched on. Nulls match nulls.
+    pub fn row_keys(&self, keys: &Chunk<Box<dyn Array>>) -> Vec<Vec<Option<Vec<u8>>>> {
+        (0..keys.len())
+            .map(|row| {
+                self.schema
+                    .fields
+                    .iter()
+                    .zip(keys.arrays())
+                    .map(|(field, array)| {
+                        let primitive = field.field_type.as_primitive().unwrap();
+                        literal_at(primitive, array.as_ref(), row)
+                            .as_ref()
+                            .map(Literal::to_bytes)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// `chunk` with only the last of the rows that have the same values in the equality
+    /// columns. Deletes don't apply to rows committed with them, so a request can't hold two
+    /// versions of a row.
+    pub fn latest_rows(&self, schema: &Schema, chunk: Chunk<Box<dyn Array>>) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+        let keys = self.row_keys(&self.project(schema, &chunk)?);
+        let last_rows = keys
+            .iter()
+            .enumerate()
+            .map(|(row, key)| (key, row))
+            .collect::<HashMap<_, _>>();
+        if last_rows.len() == chunk.len() {
+            return Ok(chunk);
+        }
+        let latest = keys
+            .iter()
+            .enumerate()
+            .map(|(row, key)| last_rows[key] == row)
+            .collect::<Vec<_>>();
+        Ok(filter_chunk(&chunk, &BooleanArray::from_slice(latest))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::partition::PartitionField;
+    use crate::iceberg::values::Value;
+
+    #[test]
+    fn keeps_the_latest_row_of_every_key() {
+        let mut schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "score", Type::Primitive(PrimitiveType::Double)),
+            ],
+        );
+        assert!(EqualityDeletes::identifier(&schema).is_err());
+        assert!(EqualityDeletes::new(&schema, vec![3]).is_err());
+        schema.identifier_field_ids = Some(vec![1]);
+        let deletes = EqualityDeletes::identifier(&schema).unwrap();
+        assert_eq!(deletes.equality_ids(), [1]);
+        assert_eq!(deletes.schema().fields, schema.fields[..1]);
+
+        let record = |id: &str, rating: i32| {
+            vec![
+                Value::Primitive(Literal::String(id.to_string())),
+                Value::Primitive(Literal::Int(rating)),
+                Value::Null,
+            ]
+        };
+        let chunk = records_to_chunk(&schema, &[record("R1", 1), record("R2", 2), record("R1", 3)]).unwrap();
+        let latest = deletes.latest_rows(&schema, chunk).unwrap();
+        assert_eq!(
+            latest,
+            records_to_chunk(&schema, &[record("R2", 2), record("R1", 3)]).unwrap()
+        );
+        let keys = deletes.project(&schema, &latest).unwrap();
+        assert_eq!(keys.arrays(), &latest.arrays()[..1]);
+    }
+
+    #[test]
+    fn checks_the_partitioning_is_on_equality_columns() {
+        let mut schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "review_date", Type::Primitive(PrimitiveType::Date)),
+            ],
+        );
+        schema.identifier_field_ids = Some(vec![1]);
+        let deletes = EqualityDeletes::identifier(&schema).unwrap();
+        let spec = |source_id: i32, transform: Transform| PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id,
+                field_id: 1000,
+                name: "partition".to_string(),
+                transform,
+            }],
+        };
+        assert!(deletes.check_partitioning(&schema, &PartitionSpec::unpartitioned()).is_ok());
+        assert!(deletes.check_partitioning(&schema, &spec(1, Transform::Bucket(16))).is_ok());
+        assert!(deletes.check_partitioning(&schema, &spec(2, Transform::Void)).is_ok());
+        let err = deletes.check_partitioning(&schema, &spec(2, Transform::Day)).unwrap_err();
+        assert_eq!(err.to_string(), "The table is partitioned by review_date, which is not an identifier field");
+    }
+}
@@ -0,0 +1,507 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail};
+use arrow2::array::Array;
+use arrow2::chunk::Chunk;
+use serde_json::Value as JsonValue;
+
+use crate::formats::json::read_literal;
+use crate::iceberg::arrow::{field_array, literal_at};
+use crate::iceberg::manifest::DataFile;
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+use crate::iceberg::values::Literal;
+
+// Row filters such as `customer_id = '10822695' AND review_date < '2010-01-01'`, in the SQL
+// subset of Iceberg's expressions: comparisons, IN, IS NULL, AND, OR and parentheses.
+// Comparisons with null are unknown, and only rows the filter is true for match.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+/// A filter on the rows of a table, whose columns are referenced by field id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    IsNull(i32),
+    NotNull(i32),
+    Compare(i32, Operator, Literal),
+    In(i32, Vec<Literal>),
+    NotIn(i32, Vec<Literal>),
+}
+
+impl Predicate {
+    /// Parses `text`, whose columns are primitive columns of `schema` named by their (dotted)
+    /// path, and whose literals are SQL strings, numbers or booleans of the columns' types.
+    pub fn parse(schema: &Schema, text: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser {
+            schema,
+            tokens: tokenize(text)?,
+            next: 0,
+        };
+        let predicate = parser.or()?;
+        match parser.peek() {
+            None => Ok(predicate),
+            Some(token) => bail!("Unexpected {}", token),
+        }
+    }
+
+    /// Whether each row of `chunk`, whose columns are the fields of `schema`, matches.
+    pub fn matches(&self, schema: &Schema, chunk: &Chunk<Box<dyn Array>>) -> anyhow::Result<Vec<bool>> {
+        Ok(self
+            .evaluate(schema, chunk)?
+            .into_iter()
+            .map(|matches| matches == Some(true))
+            .collect())
+    }
+
+    /// Whether any row of `data_file` might match, judging by its column metrics. Files without
+    /// metrics for a column might always match.
+    pub fn might_match(&self, schema: &Schema, data_file: &DataFile) -> bool {
+        match self {
+            Predicate::And(left, right) => left.might_match(schema, data_file) && right.might_match(schema, data_file),
+            Predicate::Or(left, right) => left.might_match(schema, data_file) || right.might_match(schema, data_file),
+            Predicate::IsNull(id) => data_file.null_value_counts.get(id).is_none_or(|nulls| *nulls > 0),
+            Predicate::NotNull(id) => !all_null(data_file, *id),
+            Predicate::Compare(id, operator, literal) => {
+                let (lower, upper) = bounds(schema, data_file, *id);
+                let lower = lower.as_ref().and_then(|lower| lower.partial_cmp(literal));
+                let upper = upper.as_ref().and_then(|upper| upper.partial_cmp(literal));
+                !all_null(data_file, *id)
+                    && match operator {
+                        Operator::Eq => lower != Some(Ordering::Greater) && upper != Some(Ordering::Less),
+                        Operator::NotEq => true,
+                        Operator::Lt => lower.is_none_or(|lower| lower == Ordering::Less),
+                        Operator::LtEq => lower != Some(Ordering::Greater),
+                        Operator::Gt => upper.is_none_or(|upper| upper == Ordering::Greater),
+                        Operator::GtEq => upper != Some(Ordering::Less),
+                    }
+            }
+            Predicate::In(id, literals) => literals
+                .iter()
+                .any(|literal| Predicate::Compare(*id, Operator::Eq, literal.clone()).might_match(schema, data_file)),
+            Predicate::NotIn(id, _) => !all_null(data_file, *id),
+        }
+    }
+
+    /// Whether each row matches, or `None` where that's unknown because of nulls.
+    fn evaluate(&self, schema: &Schema, chunk: &Chunk<Box<dyn Array>>) -> anyhow::Result<Vec<Option<bool>>> {
+        let (id, test): (i32, RowTest) = match self {
+            Predicate::And(left, right) => {
+                let rows = left
+                    .evaluate(schema, chunk)?
+                    .into_iter()
+                    .zip(right.evaluate(schema, chunk)?);
+                return Ok(rows
+                    .map(|row| match row {
+                        (Some(false), _) | (_, Some(false)) => Some(false),
+                        (Some(true), Some(true)) => Some(true),
+                        _ => None,
+                    })
+                    .collect());
+            }
+            Predicate::Or(left, right) => {
+                let rows = left
+                    .evaluate(schema, chunk)?
+                    .into_iter()
+                    .zip(right.evaluate(schema, chunk)?);
+                return Ok(rows
+                    .map(|row| match row {
+                        (Some(true), _) | (_, Some(true)) => Some(true),
+                        (Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    })
+                    .collect());
+            }
+            Predicate::IsNull(id) => (*id, Box::new(|value| Some(value.is_none()))),
+            Predicate::NotNull(id) => (*id, Box::new(|value| Some(value.is_some()))),
+            Predicate::Compare(id, operator, literal) => (
+                *id,
+                Box::new(|value| {
+                    // NaN isn't ordered, so comparisons with it are false
+                    let ordering = value?.partial_cmp(literal);
+                    Some(ordering.is_some_and(|ordering| match operator {
+                        Operator::Eq => ordering == Ordering::Equal,
+                        Operator::NotEq => ordering != Ordering::Equal,
+                        Operator::Lt => ordering == Ordering::Less,
+                        Operator::LtEq => ordering != Ordering::Greater,
+                        Operator::Gt => ordering == Ordering::Greater,
+                        Operator::GtEq => ordering != Ordering::Less,
+                    }))
+                }),
+            ),
+            Predicate::In(id, literals) => (*id, Box::new(|value| Some(literals.contains(&value?)))),
+            Predicate::NotIn(id, literals) => (*id, Box::new(|value| Some(!literals.contains(&value?)))),
+        };
+        let primitive = schema
+            .field_by_id(id)
+            .and_then(|field| field.field_type.as_primitive())
+            .ok_or_else(|| anyhow!("Field {} is not a primitive column", id))?;
+        let array = field_array(&schema.fields, chunk.arrays(), id)
+            .ok_or_else(|| anyhow!("Field {} is not in the chunk", id))?;
+        Ok((0..chunk.len())
+            .map(|row| test(literal_at(primitive, array.as_ref(), row)))
+            .collect())
+    }
+}
+
+/// Whether a row's value of a column passes a comparison, or `None` if that's unknown.
+type RowTest<'a> = Box<dyn Fn(Option<Literal>) -> Option<bool> + 'a>;
+
+/// Whether the metrics of `data_file` say every value of the column is null.
+fn all_null(data_file: &DataFile, id: i32) -> bool {
+    match (data_file.value_counts.get(&id), data_file.null_value_counts.get(&id)) {
+        (Some(values), Some(nulls)) => values == nulls,
+        _ => false,
+    }
+}
+
+/// The lower and upper bounds of a column of `data_file`, where it has them.
+fn bounds(schema: &Schema, data_file: &DataFile, id: i32) -> (Option<Literal>, Option<Literal>) {
+    let Some(primitive) = schema.field_by_id(id).and_then(|field| field.field_type.as_primitive()) else {
+        return (None, None);
+    };
+    let bound = |bytes: Option<&Vec<u8>>| bytes.and_then(|bytes| Literal::from_bytes(primitive, bytes));
+    (
+        bound(data_file.lower_bounds.get(&id)),
+        bound(data_file.upper_bounds.get(&id)),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A column name or keyword
+    Word(String),
+    /// A column name in double quotes or backticks, never a keyword
+    Quoted(String),
+    String(String),
+    Number(String),
+    Operator(Operator),
+    Open,
+    Close,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) | Token::Quoted(word) => write!(f, "{}", word),
+            Token::String(string) => write!(f, "'{}'", string),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Operator(operator) => write!(f, "{:?}", operator),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '=' => Token::Operator(Operator::Eq),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::NotEq),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::LtEq),
+            '<' if chars.next_if_eq(&'>').is_some() => Token::Operator(Operator::NotEq),
+            '<' => Token::Operator(Operator::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::GtEq),
+            '>' => Token::Operator(Operator::Gt),
+            // Strings in single quotes, with '' for a quote; names in double quotes or backticks
+            '\'' | '"' | '`' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c && chars.next_if_eq(&c).is_some() => quoted.push(c),
+                        Some(next) if next == c => break,
+                        Some(next) => quoted.push(next),
+                        None => bail!("Unterminated {}{}", c, quoted),
+                    }
+                }
+                match c {
+                    '\'' => Token::String(quoted),
+                    _ => Token::Quoted(quoted),
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|next| next.is_ascii_alphanumeric() || matches!(next, '.' | '-' | '+'))
+                {
+                    number.push(next);
+                }
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.next_if(|next| next.is_alphanumeric() || matches!(next, '_' | '.')) {
+                    word.push(next);
+                }
+                Token::Word(word)
+            }
+            other => bail!("Unexpected {}", other),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser of
+/// ```text
+/// or      := and (OR and)*
+/// and     := term (AND term)*
+/// term    := '(' or ')' | column IS [NOT] NULL | column [NOT] IN '(' literal (',' literal)* ')'
+///          | column operator literal
+/// ```
+struct Parser<'a> {
+    schema: &'a Schema,
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> anyhow::Result<Token> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of predicate"))?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    /// Takes the next token if it's the keyword `keyword`.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.next += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> anyhow::Result<()> {
+        match self.advance()? {
+            token if token == expected => Ok(()),
+            token => bail!("Expected {}, got {}", expected, token),
+        }
+    }
+
+    fn or(&mut self) -> anyhow::Result<Predicate> {
+        let mut predicate = self.and()?;
+        while self.keyword("or") {
+            predicate = Predicate::Or(Box::new(predicate), Box::new(self.and()?));
+        }
+        Ok(predicate)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Predicate> {
+        let mut predicate = self.term()?;
+        while self.keyword("and") {
+            predicate = Predicate::And(Box::new(predicate), Box::new(self.term()?));
+        }
+        Ok(predicate)
+    }
+
+    fn term(&mut self) -> anyhow::Result<Predicate> {
+        if self.peek() == Some(&Token::Open) {
+            self.next += 1;
+            let predicate = self.or()?;
+            self.expect(Token::Close)?;
+            return Ok(predicate);
+        }
+        let field = match self.advance()? {
+            Token::Word(name) | Token::Quoted(name) => self.column(&name)?,
+            token => bail!("Expected a column, got {}", token),
+        };
+        let primitive = field.field_type.as_primitive().unwrap();
+
+        if self.keyword("is") {
+            let not = self.keyword("not");
+            if !self.keyword("null") {
+                bail!("Expected NULL after IS");
+            }
+            return Ok(match not {
+                true => Predicate::NotNull(field.id),
+                false => Predicate::IsNull(field.id),
+            });
+        }
+        let not = self.keyword("not");
+        if self.keyword("in") {
+            self.expect(Token::Open)?;
+            let mut literals = vec![self.literal(field, primitive)?];
+            while self.peek() == Some(&Token::Comma) {
+                self.next += 1;
+                literals.push(self.literal(field, primitive)?);
+            }
+            self.expect(Token::Close)?;
+            return Ok(match not {
+                true => Predicate::NotIn(field.id, literals),
+                false => Predicate::In(field.id, literals),
+            });
+        }
+        if not {
+            bail!("Expected IN after NOT");
+        }
+        match self.advance()? {
+            Token::Operator(operator) => Ok(Predicate::Compare(field.id, operator, self.literal(field, primitive)?)),
+            token => bail!("Expected a comparison after {}, got {}", field.name, token),
+        }
+    }
+
+    /// The primitive column at the dotted path `name`.
+    fn column(&self, name: &str) -> anyhow::Result<&'a NestedField> {
+        let mut fields = &self.schema.fields;
+        let mut parts = name.split('.').peekable();
+        while let Some(part) = parts.next() {
+            let field = fields
+                .iter()
+                .find(|field| field.name == part)
+                .ok_or_else(|| anyhow!("Unknown column {}", name))?;
+            match (&field.field_type, parts.peek()) {
+                (Type::Primitive(_), None) => return Ok(field),
+                (Type::Struct(struct_type), Some(_)) => fields = &struct_type.fields,
+                _ => bail!("{} is not a primitive column", name),
+            }
+        }
+        bail!("Unknown column {}", name)
+    }
+
+    fn literal(&mut self, field: &NestedField, primitive: &PrimitiveType) -> anyhow::Result<Literal> {
+        let json = match self.advance()? {
+            Token::String(string) => JsonValue::String(string),
+            Token::Number(number) => serde_json::from_str(&number).map_err(|_| anyhow!("Invalid number {}", number))?,
+            Token::Word(word) if word.eq_ignore_ascii_case("true") => JsonValue::Bool(true),
+            Token::Word(word) if word.eq_ignore_ascii_case("false") => JsonValue::Bool(false),
+            Token::Word(word) if word.eq_ignore_ascii_case("null") => {
+                bail!("Comparisons with NULL are never true, use {} IS NULL", field.name)
+            }
+            token => bail!("Expected a value for {}, got {}", field.name, token),
+        };
+        read_literal(primitive, &json).map_err(|err| anyhow!("Invalid value for {}: {}", field.name, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::manifest::{DataContentType, DataFileFormat};
+    use crate::iceberg::values::Value;
+
+    fn schema() -> Schema {
+        Schema::new(
+            0,
+            vec![
+                NestedField::optional(1, "customer_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "review_date", Type::Primitive(PrimitiveType::Date)),
+            ],
+        )
+    }
+
+    #[test]
+    fn parses_sql_filters() {
+        let schema = schema();
+        let customer = |id: &str| Predicate::Compare(1, Operator::Eq, Literal::String(id.to_string()));
+        assert_eq!(
+            Predicate::parse(&schema, "customer_id = '10822695'").unwrap(),
+            customer("10822695")
+        );
+        assert_eq!(
+            Predicate::parse(
+                &schema,
+                "(\"customer_id\" = 'O''Brien' or star_rating >= -1) AND review_date IS NOT NULL"
+            )
+            .unwrap(),
+            Predicate::And(
+                Box::new(Predicate::Or(
+                    Box::new(customer("O'Brien")),
+                    Box::new(Predicate::Compare(2, Operator::GtEq, Literal::Int(-1)))
+                )),
+                Box::new(Predicate::NotNull(3))
+            )
+        );
+        assert_eq!(
+            Predicate::parse(&schema, "review_date not in ('2006-06-11', 13311)").unwrap(),
+            Predicate::NotIn(3, vec![Literal::Date(13310), Literal::Date(13311)])
+        );
+
+        for invalid in [
+            "",
+            "customer = '1'",
+            "star_rating = 'five'",
+            "star_rating = NULL",
+            "customer_id = '1' AND",
+            "(star_rating < 3",
+        ] {
+            assert!(Predicate::parse(&schema, invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn matches_rows_and_prunes_files() {
+        let schema = schema();
+        let record = |rating: Option<i32>| {
+            vec![
+                Value::Primitive(Literal::String("10822695".to_string())),
+                rating.map_or(Value::Null, |rating| Value::Primitive(Literal::Int(rating))),
+                Value::Null,
+            ]
+        };
+        let chunk = records_to_chunk(&schema, &[record(Some(1)), record(None), record(Some(5))]).unwrap();
+        let matches = |text: &str| {
+            Predicate::parse(&schema, text)
+                .unwrap()
+                .matches(&schema, &chunk)
+                .unwrap()
+        };
+        assert_eq!(matches("star_rating <> 1"), [false, false, true]);
+        assert_eq!(matches("star_rating < 3 OR star_rating IS NULL"), [true, true, false]);
+        assert_eq!(
+            matches("customer_id = '10822695' AND star_rating NOT IN (5)"),
+            [true, false, false]
+        );
+
+        let mut data_file = DataFile::new(
+            DataContentType::Data,
+            "s3://bucket/books/data/a.parquet".to_string(),
+            DataFileFormat::Parquet,
+            vec![],
+            3,
+            1024,
+        );
+        data_file.lower_bounds.insert(2, Literal::Int(1).to_bytes());
+        data_file.upper_bounds.insert(2, Literal::Int(5).to_bytes());
+        data_file.value_counts.insert(3, 3);
+        data_file.null_value_counts.insert(3, 3);
+        let might_match = |text: &str| {
+            Predicate::parse(&schema, text)
+                .unwrap()
+                .might_match(&schema, &data_file)
+        };
+        assert!(might_match("star_rating = 3"));
+        assert!(!might_match("star_rating > 5"));
+        assert!(!might_match("star_rating in (0, 6)"));
+        assert!(might_match("star_rating <= 1 AND customer_id = 'any'"));
+        assert!(!might_match("review_date = '2006-06-11' OR star_rating < 1"));
+        assert!(might_match("review_date IS NULL"));
+    }
+}
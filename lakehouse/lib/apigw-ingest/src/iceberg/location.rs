@@ -0,0 +1,166 @@
+use crate::iceberg::metadata::TableMetadata;
+use crate::iceberg::transform::Transform;
+use crate::iceberg::values::Literal;
+
+// Where new data files go, like the Java LocationProviders
+// https://iceberg.apache.org/docs/latest/aws/#object-store-file-layout
+
+/// Picks the locations of new data files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocationProvider {
+    /// `<data dir>/<partition path>/<file>`
+    Default { data_dir: String },
+    /// `<data dir>/<hash>/[<database>/<table>/]<partition path>/<file>`, which spreads the files of
+    /// a table over many S3 prefixes so they don't share one prefix's request rate.
+    ObjectStorage {
+        data_dir: String,
+        /// The last two parts of the table's location, when the data isn't under it.
+        context: Option<String>,
+    },
+}
+
+impl LocationProvider {
+    /// The object storage layout if `write.object-storage.enabled` is true, the default one otherwise.
+    pub fn new(metadata: &TableMetadata) -> Self {
+        let properties = &metadata.properties;
+        if let Some(provider) = properties.get("write.location-provider.impl") {
+            log::warn!("Ignoring unsupported write.location-provider.impl {}", provider);
+        }
+        let object_storage = properties
+            .get("write.object-storage.enabled")
+            .is_some_and(|enabled| enabled.eq_ignore_ascii_case("true"));
+        if !object_storage {
+            return LocationProvider::Default {
+                data_dir: metadata.data_dir(),
+            };
+        }
+
+        let data_dir = match (
+            properties.get("write.data.path"),
+            properties.get("write.object-storage.path"),
+        ) {
+            (None, Some(path)) => path.trim_end_matches('/').to_string(),
+            _ => metadata.data_dir(),
+        };
+        let table_location = metadata.location.trim_end_matches('/');
+        let context = match data_dir.starts_with(&format!("{}/", table_location)) {
+            true => None,
+            false => Some(path_context(table_location)),
+        };
+        LocationProvider::ObjectStorage { data_dir, context }
+    }
+
+    /// The location of a new data file named `file_name` in the partition at `partition_path`,
+    /// which is empty for unpartitioned tables.
+    pub fn new_data_location(&self, partition_path: &str, file_name: &str) -> String {
+        let path = match partition_path {
+            "" => file_name.to_string(),
+            partition_path => format!("{}/{}", partition_path, file_name),
+        };
+        match self {
+            LocationProvider::Default { data_dir } => format!("{}/{}", data_dir, path),
+            LocationProvider::ObjectStorage { data_dir, context } => {
+                let hash = path_hash(&path);
+                match context {
+                    Some(context) => format!("{}/{:08x}/{}/{}", data_dir, hash, context, path),
+                    None => format!("{}/{:08x}/{}", data_dir, hash, path),
+                }
+            }
+        }
+    }
+}
+
+/// The hash Java puts in front of `path`: a string bucket transform with `Integer.MAX_VALUE` buckets.
+fn path_hash(path: &str) -> i32 {
+    match Transform::Bucket(i32::MAX as u32).apply(Some(&Literal::String(path.to_string()))) {
+        Ok(Some(Literal::Int(hash))) => hash,
+        _ => unreachable!("Strings always have a bucket"),
+    }
+}
+
+/// `<database>/<table>` for a table at `.../<database>/<table>`, or just the table's name if its
+/// location has no parent directory.
+fn path_context(table_location: &str) -> String {
+    let path = match table_location.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map_or("", |(_, path)| path),
+        None => table_location,
+    };
+    let parts = path.split('/').filter(|part| !part.is_empty()).collect::<Vec<_>>();
+    parts[parts.len().saturating_sub(2)..].join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::partition::PartitionSpec;
+    use crate::iceberg::schema::Schema;
+    use crate::iceberg::sort::SortOrder;
+
+    fn metadata(location: &str, properties: &[(&str, &str)]) -> TableMetadata {
+        TableMetadata::new(
+            location.to_string(),
+            Schema::new(0, vec![]),
+            PartitionSpec::unpartitioned(),
+            SortOrder::unsorted(),
+            properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn picks_data_file_locations_from_the_table_properties() {
+        let default = LocationProvider::new(&metadata("s3://bucket/db/books/", &[]));
+        assert_eq!(
+            default.new_data_location("review_date_day=2006-06-11", "a.parquet"),
+            "s3://bucket/db/books/data/review_date_day=2006-06-11/a.parquet"
+        );
+        assert_eq!(
+            default.new_data_location("", "a.parquet"),
+            "s3://bucket/db/books/data/a.parquet"
+        );
+
+        // "iceberg" hashes to 1210000089 in the spec's bucket examples
+        let in_table = LocationProvider::new(&metadata(
+            "s3://bucket/db/books",
+            &[("write.object-storage.enabled", "true")],
+        ));
+        assert_eq!(
+            in_table.new_data_location("", "iceberg"),
+            "s3://bucket/db/books/data/481f22d9/iceberg"
+        );
+
+        let elsewhere = LocationProvider::new(&metadata(
+            "s3://bucket/db/books",
+            &[
+                ("write.object-storage.enabled", "true"),
+                ("write.data.path", "s3://data-bucket/books/"),
+            ],
+        ));
+        let location = elsewhere.new_data_location("review_date_day=2006-06-11", "a.parquet");
+        let hash = format!("{:08x}", path_hash("review_date_day=2006-06-11/a.parquet"));
+        assert_eq!(
+            location,
+            format!(
+                "s3://data-bucket/books/{}/db/books/review_date_day=2006-06-11/a.parquet",
+                hash
+            )
+        );
+
+        // A sibling location that only shares the table location's prefix isn't under it
+        let sibling = LocationProvider::new(&metadata(
+            "s3://bucket/db/books/",
+            &[
+                ("write.object-storage.enabled", "true"),
+                ("write.object-storage.path", "s3://bucket/db/books-data"),
+            ],
+        ));
+        let location = sibling.new_data_location("", "a.parquet");
+        let hash = format!("{:08x}", path_hash("a.parquet"));
+        assert_eq!(location, format!("s3://bucket/db/books-data/{}/db/books/a.parquet", hash));
+
+        assert_eq!(path_context("s3://bucket/books"), "books");
+        assert_eq!(path_context("/tmp/warehouse/db/books"), "db/books");
+    }
+}
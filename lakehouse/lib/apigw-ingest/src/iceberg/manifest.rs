@@ -0,0 +1,571 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::{anyhow, bail};
+use apache_avro::types::Value as AvroValue;
+use apache_avro::{Codec, Reader, Writer};
+use serde_json::json;
+
+use crate::iceberg::avro::{
+    int_map, list, literal_value, optional_field, optional_value, primitive_schema, record, required_field, value_literal, Record,
+};
+use crate::iceberg::manifest_list::{FieldSummary, ManifestContent, ManifestFile};
+use crate::iceberg::metrics::Metrics;
+use crate::iceberg::partition::PartitionSpec;
+use crate::iceberg::schema::{Schema, StructType, Type};
+use crate::iceberg::values::Literal;
+
+// Manifest files - https://iceberg.apache.org/spec/#manifests
+// A manifest is an Avro file listing data files along with their partition tuple and metrics.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestStatus {
+    Existing = 0,
+    Added = 1,
+    Deleted = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataContentType {
+    Data = 0,
+    PositionDeletes = 1,
+    EqualityDeletes = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFileFormat {
+    Avro,
+    Orc,
+    Parquet,
+}
+
+impl fmt::Display for DataFileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataFileFormat::Avro => write!(f, "AVRO"),
+            DataFileFormat::Orc => write!(f, "ORC"),
+            DataFileFormat::Parquet => write!(f, "PARQUET"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFile {
+    pub content: DataContentType,
+    pub file_path: String,
+    pub file_format: DataFileFormat,
+    /// One value per field of the partition spec the file was written with
+    pub partition: Vec<Option<Literal>>,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    pub column_sizes: BTreeMap<i32, i64>,
+    pub value_counts: BTreeMap<i32, i64>,
+    pub null_value_counts: BTreeMap<i32, i64>,
+    pub nan_value_counts: BTreeMap<i32, i64>,
+    pub lower_bounds: BTreeMap<i32, Vec<u8>>,
+    pub upper_bounds: BTreeMap<i32, Vec<u8>>,
+    pub split_offsets: Option<Vec<i64>>,
+    pub equality_ids: Option<Vec<i32>>,
+    pub sort_order_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub status: ManifestStatus,
+    pub snapshot_id: Option<i64>,
+    /// Left empty for added files so they inherit the sequence number of the committing snapshot
+    pub sequence_number: Option<i64>,
+    pub file_sequence_number: Option<i64>,
+    pub data_file: DataFile,
+}
+
+impl DataFile {
+    pub fn new(
+        content: DataContentType,
+        file_path: String,
+        file_format: DataFileFormat,
+        partition: Vec<Option<Literal>>,
+        record_count: i64,
+        file_size_in_bytes: i64,
+    ) -> Self {
+        DataFile {
+            content,
+            file_path,
+            file_format,
+            partition,
+            record_count,
+            file_size_in_bytes,
+            column_sizes: BTreeMap::new(),
+            value_counts: BTreeMap::new(),
+            null_value_counts: BTreeMap::new(),
+            nan_value_counts: BTreeMap::new(),
+            lower_bounds: BTreeMap::new(),
+            upper_bounds: BTreeMap::new(),
+            split_offsets: None,
+            equality_ids: None,
+            sort_order_id: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.column_sizes = metrics.column_sizes;
+        self.value_counts = metrics.value_counts;
+        self.null_value_counts = metrics.null_value_counts;
+        self.nan_value_counts = metrics.nan_value_counts;
+        self.lower_bounds = metrics.lower_bounds;
+        self.upper_bounds = metrics.upper_bounds;
+        self
+    }
+}
+
+/// The Avro schema of `manifest_entry` records for the given partition type.
+fn manifest_entry_schema(spec: &PartitionSpec, schema: &Schema) -> anyhow::Result<serde_json::Value> {
+    let partition_fields = spec
+        .partition_type(schema)?
+        .fields
+        .iter()
+        .map(|f| match &f.field_type {
+            Type::Primitive(p) => Ok(optional_field(&f.name, primitive_schema(p), f.id)),
+            _ => Err(anyhow!("Partition field {} is not a primitive", f.name)),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let data_file = record(
+        "r2",
+        vec![
+            required_field("content", json!("int"), 134),
+            required_field("file_path", json!("string"), 100),
+            required_field("file_format", json!("string"), 101),
+            required_field("partition", record("r102", partition_fields), 102),
+            required_field("record_count", json!("long"), 103),
+            required_field("file_size_in_bytes", json!("long"), 104),
+            optional_field("column_sizes", int_map(json!("long"), 117, 118), 108),
+            optional_field("value_counts", int_map(json!("long"), 119, 120), 109),
+            optional_field("null_value_counts", int_map(json!("long"), 121, 122), 110),
+            optional_field("nan_value_counts", int_map(json!("long"), 138, 139), 137),
+            optional_field("lower_bounds", int_map(json!("bytes"), 126, 127), 125),
+            optional_field("upper_bounds", int_map(json!("bytes"), 129, 130), 128),
+            optional_field("key_metadata", json!("bytes"), 131),
+            optional_field("split_offsets", list(json!("long"), 133), 132),
+            optional_field("equality_ids", list(json!("int"), 136), 135),
+            optional_field("sort_order_id", json!("int"), 140),
+        ],
+    );
+
+    Ok(record(
+        "manifest_entry",
+        vec![
+            required_field("status", json!("int"), 0),
+            optional_field("snapshot_id", json!("long"), 1),
+            optional_field("sequence_number", json!("long"), 3),
+            optional_field("file_sequence_number", json!("long"), 4),
+            required_field("data_file", data_file, 2),
+        ],
+    ))
+}
+
+fn map_value<V>(map: &BTreeMap<i32, V>, value: impl Fn(&V) -> AvroValue) -> AvroValue {
+    if map.is_empty() {
+        return optional_value(None);
+    }
+    let entries = map
+        .iter()
+        .map(|(k, v)| AvroValue::Record(vec![("key".to_string(), AvroValue::Int(*k)), ("value".to_string(), value(v))]))
+        .collect();
+    optional_value(Some(AvroValue::Array(entries)))
+}
+
+fn entry_value(entry: &ManifestEntry, spec: &PartitionSpec, schema: &Schema) -> anyhow::Result<AvroValue> {
+    let file = &entry.data_file;
+    let partition_type = spec.partition_type(schema)?;
+    if file.partition.len() != partition_type.fields.len() {
+        anyhow::bail!(
+            "Data file {} has {} partition values but spec {} has {} fields",
+            file.file_path,
+            file.partition.len(),
+            spec.spec_id,
+            partition_type.fields.len()
+        );
+    }
+    let partition = partition_type
+        .fields
+        .iter()
+        .zip(file.partition.iter())
+        .map(|(field, value)| {
+            let primitive = field.field_type.as_primitive().unwrap();
+            (field.name.clone(), optional_value(value.as_ref().map(|v| literal_value(v, primitive))))
+        })
+        .collect();
+
+    let data_file = AvroValue::Record(vec![
+        ("content".to_string(), AvroValue::Int(file.content as i32)),
+        ("file_path".to_string(), AvroValue::String(file.file_path.clone())),
+        ("file_format".to_string(), AvroValue::String(file.file_format.to_string())),
+        ("partition".to_string(), AvroValue::Record(partition)),
+        ("record_count".to_string(), AvroValue::Long(file.record_count)),
+        ("file_size_in_bytes".to_string(), AvroValue::Long(file.file_size_in_bytes)),
+        ("column_sizes".to_string(), map_value(&file.column_sizes, |v| AvroValue::Long(*v))),
+        ("value_counts".to_string(), map_value(&file.value_counts, |v| AvroValue::Long(*v))),
+        ("null_value_counts".to_string(), map_value(&file.null_value_counts, |v| AvroValue::Long(*v))),
+        ("nan_value_counts".to_string(), map_value(&file.nan_value_counts, |v| AvroValue::Long(*v))),
+        ("lower_bounds".to_string(), map_value(&file.lower_bounds, |v| AvroValue::Bytes(v.clone()))),
+        ("upper_bounds".to_string(), map_value(&file.upper_bounds, |v| AvroValue::Bytes(v.clone()))),
+        ("key_metadata".to_string(), optional_value(None)),
+        (
+            "split_offsets".to_string(),
+            optional_value(file.split_offsets.as_ref().map(|o| AvroValue::Array(o.iter().map(|v| AvroValue::Long(*v)).collect()))),
+        ),
+        (
+            "equality_ids".to_string(),
+            optional_value(file.equality_ids.as_ref().map(|ids| AvroValue::Array(ids.iter().map(|v| AvroValue::Int(*v)).collect()))),
+        ),
+        ("sort_order_id".to_string(), optional_value(file.sort_order_id.map(AvroValue::Int))),
+    ]);
+
+    Ok(AvroValue::Record(vec![
+        ("status".to_string(), AvroValue::Int(entry.status as i32)),
+        ("snapshot_id".to_string(), optional_value(entry.snapshot_id.map(AvroValue::Long))),
+        ("sequence_number".to_string(), optional_value(entry.sequence_number.map(AvroValue::Long))),
+        ("file_sequence_number".to_string(), optional_value(entry.file_sequence_number.map(AvroValue::Long))),
+        ("data_file".to_string(), data_file),
+    ]))
+}
+
+/// The entries of a manifest, whose data files were written with `spec` of a table with `schema`.
+/// Entries that inherit their sequence numbers come back without them.
+pub fn read_manifest(bytes: &[u8], schema: &Schema, spec: &PartitionSpec) -> anyhow::Result<Vec<ManifestEntry>> {
+    let partition_type = spec.partition_type(schema)?;
+    Reader::new(bytes)?
+        .map(|value| entry_from_avro(&value?, &partition_type))
+        .collect()
+}
+
+fn entry_from_avro(value: &AvroValue, partition_type: &StructType) -> anyhow::Result<ManifestEntry> {
+    let entry = Record::new(value)?;
+    let status = match entry.int("status")? {
+        Some(0) => ManifestStatus::Existing,
+        Some(1) => ManifestStatus::Added,
+        Some(2) => ManifestStatus::Deleted,
+        other => bail!("Invalid manifest entry status {:?}", other),
+    };
+    let file = Record::new(entry.get("data_file").ok_or_else(|| anyhow!("Manifest entry without a data file"))?)?;
+    let content = match file.int("content")? {
+        None | Some(0) => DataContentType::Data,
+        Some(1) => DataContentType::PositionDeletes,
+        Some(2) => DataContentType::EqualityDeletes,
+        Some(other) => bail!("Invalid data file content {}", other),
+    };
+    let file_format = match file.string("file_format")?.unwrap_or_default().to_ascii_uppercase().as_str() {
+        "AVRO" => DataFileFormat::Avro,
+        "ORC" => DataFileFormat::Orc,
+        "PARQUET" => DataFileFormat::Parquet,
+        other => bail!("Unsupported file format {}", other),
+    };
+    let partition = match file.get("partition") {
+        Some(partition) => Some(Record::new(partition)?),
+        None => None,
+    };
+    let partition = partition_type
+        .fields
+        .iter()
+        .map(|field| {
+            let primitive = field.field_type.as_primitive().unwrap();
+            partition
+                .as_ref()
+                .and_then(|partition| partition.get(&field.name))
+                .map(|value| value_literal(value, primitive))
+                .transpose()
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let long = |value: &AvroValue| match value {
+        AvroValue::Long(v) => Some(*v),
+        AvroValue::Int(v) => Some(*v as i64),
+        _ => None,
+    };
+    let bytes = |value: &AvroValue| match value {
+        AvroValue::Bytes(v) | AvroValue::Fixed(_, v) => Some(v.clone()),
+        _ => None,
+    };
+    let int = |value: &AvroValue| match value {
+        AvroValue::Int(v) => Some(*v),
+        _ => None,
+    };
+
+    let data_file = DataFile {
+        content,
+        file_path: file.string("file_path")?.ok_or_else(|| anyhow!("Data file without a path"))?,
+        file_format,
+        partition,
+        record_count: file.long("record_count")?.unwrap_or_default(),
+        file_size_in_bytes: file.long("file_size_in_bytes")?.unwrap_or_default(),
+        column_sizes: read_map(&file, "column_sizes", long)?,
+        value_counts: read_map(&file, "value_counts", long)?,
+        null_value_counts: read_map(&file, "null_value_counts", long)?,
+        nan_value_counts: read_map(&file, "nan_value_counts", long)?,
+        lower_bounds: read_map(&file, "lower_bounds", bytes)?,
+        upper_bounds: read_map(&file, "upper_bounds", bytes)?,
+        split_offsets: file.array("split_offsets")?.map(|offsets| offsets.iter().filter_map(long).collect()),
+        equality_ids: file.array("equality_ids")?.map(|ids| ids.iter().filter_map(int).collect()),
+        sort_order_id: file.int("sort_order_id")?,
+    };
+    Ok(ManifestEntry {
+        status,
+        snapshot_id: entry.long("snapshot_id")?,
+        sequence_number: entry.long("sequence_number")?,
+        file_sequence_number: entry.long("file_sequence_number")?,
+        data_file,
+    })
+}
+
+/// A map written by `map_value`, with the values `value` can read.
+fn read_map<V>(
+    record: &Record,
+    name: &str,
+    value: impl Fn(&AvroValue) -> Option<V>,
+) -> anyhow::Result<BTreeMap<i32, V>> {
+    let Some(entries) = record.array(name)? else {
+        return Ok(BTreeMap::new());
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = Record::new(entry)?;
+            let key = entry.int("key")?.ok_or_else(|| anyhow!("{} entry without a key", name))?;
+            let value = entry
+                .get("value")
+                .and_then(&value)
+                .ok_or_else(|| anyhow!("{} entry {} has an invalid value", name, key))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Collects the data files added by a snapshot and serializes them as a v2 manifest.
+pub struct ManifestWriter<'a> {
+    snapshot_id: i64,
+    schema: &'a Schema,
+    spec: &'a PartitionSpec,
+    content: ManifestContent,
+    entries: Vec<ManifestEntry>,
+}
+
+impl<'a> ManifestWriter<'a> {
+    pub fn new(snapshot_id: i64, schema: &'a Schema, spec: &'a PartitionSpec) -> Self {
+        ManifestWriter { snapshot_id, schema, spec, content: ManifestContent::Data, entries: vec![] }
+    }
+
+    /// A writer for a delete manifest, which holds delete files rather than data files.
+    pub fn deletes(snapshot_id: i64, schema: &'a Schema, spec: &'a PartitionSpec) -> Self {
+        ManifestWriter { snapshot_id, schema, spec, content: ManifestContent::Deletes, entries: vec![] }
+    }
+
+    pub fn add(&mut self, data_file: DataFile) {
+        self.entries.push(ManifestEntry {
+            status: ManifestStatus::Added,
+            snapshot_id: Some(self.snapshot_id),
+            sequence_number: None,
+            file_sequence_number: None,
+            data_file,
+        });
+    }
+
+    /// Adds a file with an explicit data sequence number, such as a rewritten file that keeps
+    /// the sequence number of the files it replaces. The file sequence number is still inherited.
+    pub fn add_with_sequence_number(&mut self, data_file: DataFile, sequence_number: i64) {
+        self.entries.push(ManifestEntry {
+            status: ManifestStatus::Added,
+            snapshot_id: Some(self.snapshot_id),
+            sequence_number: Some(sequence_number),
+            file_sequence_number: None,
+            data_file,
+        });
+    }
+
+    /// Carries over a live entry of another manifest, which has to have its snapshot id and
+    /// sequence numbers filled in.
+    pub fn existing(&mut self, entry: ManifestEntry) {
+        self.entries.push(ManifestEntry { status: ManifestStatus::Existing, ..entry });
+    }
+
+    /// Marks the file of a live entry of another manifest as deleted by this snapshot.
+    pub fn delete(&mut self, entry: ManifestEntry) {
+        self.entries.push(ManifestEntry {
+            status: ManifestStatus::Deleted,
+            snapshot_id: Some(self.snapshot_id),
+            ..entry
+        });
+    }
+
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let avro_schema = apache_avro::Schema::parse(&manifest_entry_schema(self.spec, self.schema)?)?;
+        let mut writer = Writer::with_codec(&avro_schema, Vec::new(), Codec::Deflate);
+
+        writer.add_user_metadata("schema".to_string(), serde_json::to_string(self.schema)?)?;
+        writer.add_user_metadata("schema-id".to_string(), self.schema.schema_id.to_string())?;
+        writer.add_user_metadata("partition-spec".to_string(), serde_json::to_string(&self.spec.fields)?)?;
+        writer.add_user_metadata("partition-spec-id".to_string(), self.spec.spec_id.to_string())?;
+        writer.add_user_metadata("format-version".to_string(), "2")?;
+        let content = match self.content {
+            ManifestContent::Data => "data",
+            ManifestContent::Deletes => "deletes",
+        };
+        writer.add_user_metadata("content".to_string(), content)?;
+
+        for entry in &self.entries {
+            writer.append(entry_value(entry, self.spec, self.schema)?)?;
+        }
+        Ok(writer.into_inner()?)
+    }
+
+    /// The manifest list entry for this manifest once written to `manifest_path`.
+    /// Sequence numbers are left unassigned (-1) until the snapshot that adds it is committed,
+    /// apart from the minimum when live entries have explicit ones.
+    pub fn manifest_file(&self, manifest_path: String, manifest_length: i64) -> anyhow::Result<ManifestFile> {
+        let partition_type = self.spec.partition_type(self.schema)?;
+        let partitions = partition_type
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                let values: Vec<_> = self.entries.iter().map(|e| e.data_file.partition.get(i).cloned().flatten()).collect();
+                let non_null: Vec<&Literal> = values.iter().flatten().filter(|v| !v.is_nan()).collect();
+                FieldSummary {
+                    contains_null: values.iter().any(|v| v.is_none()),
+                    contains_nan: Some(values.iter().flatten().any(|v| v.is_nan())),
+                    lower_bound: non_null
+                        .iter()
+                        .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                        .map(|v| v.to_bytes()),
+                    upper_bound: non_null
+                        .iter()
+                        .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                        .map(|v| v.to_bytes()),
+                }
+            })
+            .collect();
+
+        let count = |status: ManifestStatus| self.entries.iter().filter(|e| e.status == status).count() as i32;
+        let rows = |status: ManifestStatus| {
+            self.entries.iter().filter(|e| e.status == status).map(|e| e.data_file.record_count).sum::<i64>()
+        };
+
+        let min_sequence_number = self
+            .entries
+            .iter()
+            .filter(|e| e.status != ManifestStatus::Deleted)
+            .filter_map(|e| e.sequence_number)
+            .min();
+
+        Ok(ManifestFile {
+            manifest_path,
+            manifest_length,
+            partition_spec_id: self.spec.spec_id,
+            content: self.content,
+            sequence_number: -1,
+            min_sequence_number: min_sequence_number.unwrap_or(-1),
+            added_snapshot_id: self.snapshot_id,
+            added_files_count: count(ManifestStatus::Added),
+            existing_files_count: count(ManifestStatus::Existing),
+            deleted_files_count: count(ManifestStatus::Deleted),
+            added_rows_count: rows(ManifestStatus::Added),
+            existing_rows_count: rows(ManifestStatus::Existing),
+            deleted_rows_count: rows(ManifestStatus::Deleted),
+            partitions: Some(partitions),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::partition::PartitionField;
+    use crate::iceberg::schema::{NestedField, PrimitiveType};
+    use crate::iceberg::transform::Transform;
+
+    #[test]
+    fn writes_readable_manifest_with_field_ids() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::optional(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "review_date", Type::Primitive(PrimitiveType::Date)),
+            ],
+        );
+        let spec = PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id: 2,
+                field_id: 1000,
+                name: "review_date_day".to_string(),
+                transform: Transform::Day,
+            }],
+        };
+
+        let mut writer = ManifestWriter::new(42, &schema, &spec);
+        writer.add(DataFile::new(
+            DataContentType::Data,
+            "s3://bucket/data/file.parquet".to_string(),
+            DataFileFormat::Parquet,
+            vec![Some(Literal::Date(13310))],
+            15,
+            1024,
+        ));
+        let bytes = writer.to_bytes().unwrap();
+
+        let reader = Reader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.user_metadata().get("format-version").unwrap(), b"2");
+        assert_eq!(reader.user_metadata().get("partition-spec-id").unwrap(), b"1");
+
+        let written_schema = serde_json::to_string(reader.writer_schema()).unwrap();
+        assert!(written_schema.contains(r#""field-id":102"#));
+        assert!(written_schema.contains(r#""element-id":133"#));
+
+        let records: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn reads_back_manifest_entries() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "price", Type::Primitive(PrimitiveType::Decimal { precision: 9, scale: 2 })),
+            ],
+        );
+        let spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![PartitionField {
+                source_id: 2,
+                field_id: 1000,
+                name: "price".to_string(),
+                transform: Transform::Identity,
+            }],
+        };
+        let mut data_file = DataFile::new(
+            DataContentType::EqualityDeletes,
+            "s3://bucket/data/price=-1.50/a-deletes.parquet".to_string(),
+            DataFileFormat::Parquet,
+            vec![Some(Literal::Decimal(-150))],
+            2,
+            512,
+        );
+        data_file.value_counts = BTreeMap::from([(1, 2)]);
+        data_file.lower_bounds = BTreeMap::from([(1, b"R1".to_vec())]);
+        data_file.equality_ids = Some(vec![1]);
+        let mut null_partition = data_file.clone();
+        null_partition.partition = vec![None];
+
+        let mut writer = ManifestWriter::deletes(42, &schema, &spec);
+        writer.add(data_file.clone());
+        writer.add(null_partition.clone());
+        let entries = read_manifest(&writer.to_bytes().unwrap(), &schema, &spec).unwrap();
+        assert_eq!(entries, writer.entries());
+        assert_eq!(entries[0].data_file, data_file);
+        assert_eq!(entries[1].data_file.partition, [None]);
+    }
+}
@@ -0,0 +1,214 @@
+use apache_avro::types::Value as AvroValue;
+use apache_avro::{Codec, Reader, Writer};
+use serde_json::json;
+
+use crate::iceberg::avro::{list, optional_field, optional_value, record, required_field, Record};
+
+// Manifest lists - https://iceberg.apache.org/spec/#manifest-lists
+// Each snapshot points at a manifest list, which holds one `manifest_file` record per manifest.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestContent {
+    Data = 0,
+    Deletes = 1,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSummary {
+    pub contains_null: bool,
+    pub contains_nan: Option<bool>,
+    pub lower_bound: Option<Vec<u8>>,
+    pub upper_bound: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestFile {
+    pub manifest_path: String,
+    pub manifest_length: i64,
+    pub partition_spec_id: i32,
+    pub content: ManifestContent,
+    pub sequence_number: i64,
+    pub min_sequence_number: i64,
+    pub added_snapshot_id: i64,
+    pub added_files_count: i32,
+    pub existing_files_count: i32,
+    pub deleted_files_count: i32,
+    pub added_rows_count: i64,
+    pub existing_rows_count: i64,
+    pub deleted_rows_count: i64,
+    pub partitions: Option<Vec<FieldSummary>>,
+}
+
+fn manifest_file_schema() -> serde_json::Value {
+    let field_summary = record(
+        "r508",
+        vec![
+            required_field("contains_null", json!("boolean"), 509),
+            optional_field("contains_nan", json!("boolean"), 518),
+            optional_field("lower_bound", json!("bytes"), 510),
+            optional_field("upper_bound", json!("bytes"), 511),
+        ],
+    );
+
+    record(
+        "manifest_file",
+        vec![
+            required_field("manifest_path", json!("string"), 500),
+            required_field("manifest_length", json!("long"), 501),
+            required_field("partition_spec_id", json!("int"), 502),
+            required_field("content", json!("int"), 517),
+            required_field("sequence_number", json!("long"), 515),
+            required_field("min_sequence_number", json!("long"), 516),
+            required_field("added_snapshot_id", json!("long"), 503),
+            required_field("added_files_count", json!("int"), 504),
+            required_field("existing_files_count", json!("int"), 505),
+            required_field("deleted_files_count", json!("int"), 506),
+            required_field("added_rows_count", json!("long"), 512),
+            required_field("existing_rows_count", json!("long"), 513),
+            required_field("deleted_rows_count", json!("long"), 514),
+            optional_field("partitions", list(field_summary, 508), 507),
+            optional_field("key_metadata", json!("bytes"), 519),
+        ],
+    )
+}
+
+impl ManifestFile {
+    fn to_avro(&self) -> AvroValue {
+        let partitions = self.partitions.as_ref().map(|summaries| {
+            AvroValue::Array(
+                summaries
+                    .iter()
+                    .map(|s| {
+                        AvroValue::Record(vec![
+                            ("contains_null".to_string(), AvroValue::Boolean(s.contains_null)),
+                            ("contains_nan".to_string(), optional_value(s.contains_nan.map(AvroValue::Boolean))),
+                            ("lower_bound".to_string(), optional_value(s.lower_bound.clone().map(AvroValue::Bytes))),
+                            ("upper_bound".to_string(), optional_value(s.upper_bound.clone().map(AvroValue::Bytes))),
+                        ])
+                    })
+                    .collect(),
+            )
+        });
+
+        AvroValue::Record(vec![
+            ("manifest_path".to_string(), AvroValue::String(self.manifest_path.clone())),
+            ("manifest_length".to_string(), AvroValue::Long(self.manifest_length)),
+            ("partition_spec_id".to_string(), AvroValue::Int(self.partition_spec_id)),
+            ("content".to_string(), AvroValue::Int(self.content as i32)),
+            ("sequence_number".to_string(), AvroValue::Long(self.sequence_number)),
+            ("min_sequence_number".to_string(), AvroValue::Long(self.min_sequence_number)),
+            ("added_snapshot_id".to_string(), AvroValue::Long(self.added_snapshot_id)),
+            ("added_files_count".to_string(), AvroValue::Int(self.added_files_count)),
+            ("existing_files_count".to_string(), AvroValue::Int(self.existing_files_count)),
+            ("deleted_files_count".to_string(), AvroValue::Int(self.deleted_files_count)),
+            ("added_rows_count".to_string(), AvroValue::Long(self.added_rows_count)),
+            ("existing_rows_count".to_string(), AvroValue::Long(self.existing_rows_count)),
+            ("deleted_rows_count".to_string(), AvroValue::Long(self.deleted_rows_count)),
+            ("partitions".to_string(), optional_value(partitions)),
+            ("key_metadata".to_string(), optional_value(None)),
+        ])
+    }
+
+    fn from_avro(value: &AvroValue) -> anyhow::Result<Self> {
+        let r = Record::new(value)?;
+        let partitions = r
+            .array("partitions")?
+            .map(|summaries| {
+                summaries
+                    .iter()
+                    .map(|s| {
+                        let s = Record::new(s)?;
+                        Ok(FieldSummary {
+                            contains_null: s.boolean("contains_null")?.unwrap_or(true),
+                            contains_nan: s.boolean("contains_nan")?,
+                            lower_bound: s.bytes("lower_bound")?,
+                            upper_bound: s.bytes("upper_bound")?,
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .transpose()?;
+
+        Ok(ManifestFile {
+            manifest_path: r.string("manifest_path")?.unwrap_or_default(),
+            manifest_length: r.long("manifest_length")?.unwrap_or_default(),
+            partition_spec_id: r.int("partition_spec_id")?.unwrap_or_default(),
+            content: match r.int("content")? {
+                Some(1) => ManifestContent::Deletes,
+                _ => ManifestContent::Data,
+            },
+            sequence_number: r.long("sequence_number")?.unwrap_or_default(),
+            min_sequence_number: r.long("min_sequence_number")?.unwrap_or_default(),
+            added_snapshot_id: r.long("added_snapshot_id")?.unwrap_or_default(),
+            added_files_count: r.int("added_files_count")?.unwrap_or_default(),
+            existing_files_count: r.int("existing_files_count")?.unwrap_or_default(),
+            deleted_files_count: r.int("deleted_files_count")?.unwrap_or_default(),
+            added_rows_count: r.long("added_rows_count")?.unwrap_or_default(),
+            existing_rows_count: r.long("existing_rows_count")?.unwrap_or_default(),
+            deleted_rows_count: r.long("deleted_rows_count")?.unwrap_or_default(),
+            partitions,
+        })
+    }
+}
+
+pub fn write_manifest_list(
+    snapshot_id: i64,
+    parent_snapshot_id: Option<i64>,
+    sequence_number: i64,
+    manifests: &[ManifestFile],
+) -> anyhow::Result<Vec<u8>> {
+    let avro_schema = apache_avro::Schema::parse(&manifest_file_schema())?;
+    let mut writer = Writer::with_codec(&avro_schema, Vec::new(), Codec::Deflate);
+
+    writer.add_user_metadata("snapshot-id".to_string(), snapshot_id.to_string())?;
+    writer.add_user_metadata(
+        "parent-snapshot-id".to_string(),
+        parent_snapshot_id.map(|id| id.to_string()).unwrap_or_else(|| "null".to_string()),
+    )?;
+    writer.add_user_metadata("sequence-number".to_string(), sequence_number.to_string())?;
+    writer.add_user_metadata("format-version".to_string(), "2")?;
+
+    for manifest in manifests {
+        writer.append(manifest.to_avro())?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+pub fn read_manifest_list(bytes: &[u8]) -> anyhow::Result<Vec<ManifestFile>> {
+    Reader::new(bytes)?
+        .map(|value| ManifestFile::from_avro(&value?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_manifest_files() {
+        let manifest = ManifestFile {
+            manifest_path: "s3://bucket/books/metadata/a-m0.avro".to_string(),
+            manifest_length: 4096,
+            partition_spec_id: 0,
+            content: ManifestContent::Data,
+            sequence_number: 3,
+            min_sequence_number: 3,
+            added_snapshot_id: 42,
+            added_files_count: 1,
+            existing_files_count: 0,
+            deleted_files_count: 0,
+            added_rows_count: 15,
+            existing_rows_count: 0,
+            deleted_rows_count: 0,
+            partitions: Some(vec![FieldSummary {
+                contains_null: false,
+                contains_nan: None,
+                lower_bound: Some(vec![1, 0, 0, 0]),
+                upper_bound: Some(vec![9, 0, 0, 0]),
+            }]),
+        };
+
+        let bytes = write_manifest_list(42, Some(41), 3, std::slice::from_ref(&manifest)).unwrap();
+        assert_eq!(read_manifest_list(&bytes).unwrap(), vec![manifest]);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::iceberg::location::LocationProvider;
+use crate::iceberg::partition::PartitionSpec;
+use crate::iceberg::schema::Schema;
+use crate::iceberg::sort::SortOrder;
+use crate::iceberg::update::TableUpdate;
+
+// Table metadata (metadata.json) - https://iceberg.apache.org/spec/#table-metadata-fields
+// Only format version 2 is supported, which is what Handler.kt creates the tables with.
+// Fields this crate doesn't model are kept in `other` so rewriting the metadata never drops them.
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TableMetadata {
+    pub format_version: i32,
+    pub table_uuid: String,
+    pub location: String,
+    pub last_sequence_number: i64,
+    pub last_updated_ms: i64,
+    pub last_column_id: i32,
+    pub current_schema_id: i32,
+    pub schemas: Vec<Schema>,
+    pub default_spec_id: i32,
+    pub partition_specs: Vec<PartitionSpec>,
+    pub last_partition_id: i32,
+    #[serde(default)]
+    pub default_sort_order_id: i32,
+    #[serde(default)]
+    pub sort_orders: Vec<SortOrder>,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    #[serde(default, serialize_with = "serialize_snapshot_id", deserialize_with = "deserialize_snapshot_id")]
+    pub current_snapshot_id: Option<i64>,
+    #[serde(default)]
+    pub refs: HashMap<String, SnapshotReference>,
+    #[serde(default)]
+    pub snapshots: Vec<Snapshot>,
+    #[serde(default)]
+    pub snapshot_log: Vec<SnapshotLogEntry>,
+    #[serde(default)]
+    pub metadata_log: Vec<MetadataLogEntry>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Snapshot {
+    #[serde(default)]
+    pub sequence_number: i64,
+    pub snapshot_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_snapshot_id: Option<i64>,
+    pub timestamp_ms: i64,
+    pub summary: Summary,
+    pub manifest_list: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub operation: Operation,
+    #[serde(flatten)]
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Append,
+    Replace,
+    Overwrite,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotReference {
+    pub snapshot_id: i64,
+    #[serde(rename = "type")]
+    pub ref_type: String,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotLogEntry {
+    pub timestamp_ms: i64,
+    pub snapshot_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetadataLogEntry {
+    pub timestamp_ms: i64,
+    pub metadata_file: String,
+}
+
+// Java writes -1 rather than null when a table has no snapshots yet
+fn serialize_snapshot_id<S: Serializer>(id: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(id.unwrap_or(-1))
+}
+
+fn deserialize_snapshot_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    let id = Option::<i64>::deserialize(deserializer)?;
+    Ok(id.filter(|id| *id != -1))
+}
+
+impl TableMetadata {
+    /// Metadata for a new, empty table at `location`.
+    pub fn new(
+        location: String,
+        schema: Schema,
+        spec: PartitionSpec,
+        sort_order: SortOrder,
+        properties: HashMap<String, String>,
+    ) -> Self {
+        TableMetadata {
+            format_version: 2,
+            table_uuid: Uuid::new_v4().to_string(),
+            location: location.trim_end_matches('/').to_string(),
+            last_sequence_number: 0,
+            last_updated_ms: now_ms(),
+            last_column_id: schema.highest_field_id(),
+            current_schema_id: schema.schema_id,
+            schemas: vec![schema],
+            default_spec_id: spec.spec_id,
+            // Partition field ids start at 1000, so 999 means "none assigned yet"
+            last_partition_id: spec.fields.iter().map(|f| f.field_id).max().unwrap_or(999),
+            partition_specs: vec![spec],
+            default_sort_order_id: sort_order.order_id,
+            sort_orders: vec![sort_order],
+            properties,
+            current_snapshot_id: None,
+            refs: HashMap::new(),
+            snapshots: vec![],
+            snapshot_log: vec![],
+            metadata_log: vec![],
+            other: Default::default(),
+        }
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> anyhow::Result<Self> {
+        let metadata: TableMetadata = serde_json::from_slice(bytes)?;
+        if metadata.format_version != 2 {
+            anyhow::bail!("Unsupported table format version {}", metadata.format_version);
+        }
+        Ok(metadata)
+    }
+
+    pub fn current_schema(&self) -> anyhow::Result<&Schema> {
+        self.schemas
+            .iter()
+            .find(|s| s.schema_id == self.current_schema_id)
+            .ok_or_else(|| anyhow!("Current schema {} not found in table metadata", self.current_schema_id))
+    }
+
+    pub fn default_spec(&self) -> anyhow::Result<&PartitionSpec> {
+        self.partition_specs
+            .iter()
+            .find(|s| s.spec_id == self.default_spec_id)
+            .ok_or_else(|| anyhow!("Default partition spec {} not found in table metadata", self.default_spec_id))
+    }
+
+    pub fn default_sort_order(&self) -> anyhow::Result<&SortOrder> {
+        self.sort_orders
+            .iter()
+            .find(|s| s.order_id == self.default_sort_order_id)
+            .ok_or_else(|| anyhow!("Default sort order {} not found in table metadata", self.default_sort_order_id))
+    }
+
+    pub fn current_snapshot(&self) -> Option<&Snapshot> {
+        self.current_snapshot_id
+            .and_then(|id| self.snapshots.iter().find(|s| s.snapshot_id == id))
+    }
+
+    pub fn next_sequence_number(&self) -> i64 {
+        self.last_sequence_number + 1
+    }
+
+    /// Where manifests, manifest lists and metadata files are written, honouring `write.metadata.path`.
+    pub fn metadata_dir(&self) -> String {
+        match self.properties.get("write.metadata.path") {
+            Some(path) => path.trim_end_matches('/').to_string(),
+            None => format!("{}/metadata", self.location.trim_end_matches('/')),
+        }
+    }
+
+    /// Where data files are written, honouring `write.data.path` and the older
+    /// `write.folder-storage.path`.
+    pub fn data_dir(&self) -> String {
+        match self.properties.get("write.data.path").or_else(|| self.properties.get("write.folder-storage.path")) {
+            Some(path) => path.trim_end_matches('/').to_string(),
+            None => format!("{}/data", self.location.trim_end_matches('/')),
+        }
+    }
+
+    /// Picks the locations of new data files, as set by `write.object-storage.enabled`.
+    pub fn location_provider(&self) -> LocationProvider {
+        LocationProvider::new(self)
+    }
+
+    /// The location of the metadata file that follows `current_location`, named
+    /// `<version>-<uuid>.metadata.json` like the Java metastore catalogs do.
+    pub fn next_metadata_location(&self, current_location: &str) -> String {
+        let file_name = current_location.rsplit('/').next().unwrap_or_default();
+        let version = file_name
+            .split_once('-')
+            .and_then(|(version, _)| version.parse::<i32>().ok())
+            .unwrap_or(-1);
+        format!("{}/{:05}-{}.metadata.json", self.metadata_dir(), version + 1, Uuid::new_v4())
+    }
+
+    /// The metadata that results from applying `updates` to this metadata, which was read
+    /// from `metadata_location`.
+    pub fn update(&self, updates: &[TableUpdate], metadata_location: &str) -> anyhow::Result<TableMetadata> {
+        let mut metadata = self.clone();
+        for update in updates {
+            match update {
+                TableUpdate::AddSnapshot { snapshot } => {
+                    if metadata.snapshots.iter().any(|s| s.snapshot_id == snapshot.snapshot_id) {
+                        anyhow::bail!("Snapshot {} already exists", snapshot.snapshot_id);
+                    }
+                    if snapshot.sequence_number <= metadata.last_sequence_number {
+                        anyhow::bail!(
+                            "Snapshot sequence number {} is not newer than the table's {}",
+                            snapshot.sequence_number,
+                            metadata.last_sequence_number
+                        );
+                    }
+                    metadata.last_sequence_number = snapshot.sequence_number;
+                    metadata.last_updated_ms = snapshot.timestamp_ms;
+                    metadata.snapshots.push(snapshot.clone());
+                }
+                TableUpdate::SetSnapshotRef { ref_name, snapshot_id, ref_type } => {
+                    let snapshot = metadata
+                        .snapshots
+                        .iter()
+                        .find(|s| s.snapshot_id == *snapshot_id)
+                        .ok_or_else(|| anyhow!("Cannot point {} at unknown snapshot {}", ref_name, snapshot_id))?;
+                    if ref_name == "main" {
+                        metadata.current_snapshot_id = Some(*snapshot_id);
+                        metadata.snapshot_log.push(SnapshotLogEntry {
+                            timestamp_ms: snapshot.timestamp_ms,
+                            snapshot_id: *snapshot_id,
+                        });
+                    }
+                    metadata.refs.insert(
+                        ref_name.clone(),
+                        SnapshotReference {
+                            snapshot_id: *snapshot_id,
+                            ref_type: ref_type.clone(),
+                            other: Default::default(),
+                        },
+                    );
+                }
+                TableUpdate::RemoveSnapshots { snapshot_ids } => {
+                    let referenced = metadata.refs.iter().find(|(_, r)| snapshot_ids.contains(&r.snapshot_id));
+                    if let Some((name, reference)) = referenced {
+                        anyhow::bail!("Cannot remove snapshot {}, {} points at it", reference.snapshot_id, name);
+                    }
+                    metadata.snapshots.retain(|s| !snapshot_ids.contains(&s.snapshot_id));
+                    metadata.snapshot_log.retain(|entry| !snapshot_ids.contains(&entry.snapshot_id));
+                }
+            }
+        }
+        metadata.metadata_log.push(MetadataLogEntry {
+            timestamp_ms: self.last_updated_ms,
+            metadata_file: metadata_location.to_string(),
+        });
+        // Only the last write.metadata.previous-versions-max metadata files are logged, like Java does
+        let max_versions = metadata
+            .properties
+            .get("write.metadata.previous-versions-max")
+            .and_then(|max| max.parse::<usize>().ok())
+            .unwrap_or(100)
+            .max(1);
+        let excess = metadata.metadata_log.len().saturating_sub(max_versions);
+        metadata.metadata_log.drain(..excess);
+        Ok(metadata)
+    }
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// A random positive snapshot id, derived from a v4 UUID like the Java implementation does.
+pub fn generate_snapshot_id() -> i64 {
+    let (high, low) = Uuid::new_v4().as_u64_pair();
+    ((high ^ low) & i64::MAX as u64) as i64
+}
@@ -0,0 +1,450 @@
+use std::collections::{BTreeMap, HashMap};
+
+use arrow2::array::{
+    Array, BinaryArray, BooleanArray, FixedSizeBinaryArray, ListArray, PrimitiveArray, StructArray, Utf8Array,
+};
+use arrow2::chunk::Chunk;
+
+use crate::iceberg::schema::{PrimitiveType, Schema, Type};
+use crate::iceberg::values::Literal;
+
+// Column metrics of data files - https://iceberg.apache.org/spec/#manifests
+// They're kept in manifest entries so engines can skip files without opening them. How much of
+// them is kept for each column is set with the same `write.metadata.metrics.*` table properties
+// as the Java implementation.
+
+/// How much of the metrics of a column are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsMode {
+    None,
+    /// Column sizes, value counts, null counts and NaN counts, without bounds
+    Counts,
+    /// Counts, and bounds with strings and binary cut to this many characters or bytes
+    Truncate(usize),
+    Full,
+}
+
+impl MetricsMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        let mode = mode.trim().to_ascii_lowercase();
+        let mode = match mode.as_str() {
+            "none" => MetricsMode::None,
+            "counts" => MetricsMode::Counts,
+            "full" => MetricsMode::Full,
+            _ => {
+                let length = mode.strip_prefix("truncate(")?.strip_suffix(')')?.parse().ok()?;
+                if length == 0 {
+                    return None;
+                }
+                MetricsMode::Truncate(length)
+            }
+        };
+        Some(mode)
+    }
+}
+
+/// The metrics modes of a table's columns: `write.metadata.metrics.default`, which is
+/// `truncate(16)` unless set, and `write.metadata.metrics.column.<name>` for columns, nested ones
+/// named by their path such as `author.name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsConfig {
+    pub default: MetricsMode,
+    pub columns: HashMap<String, MetricsMode>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            default: MetricsMode::Truncate(16),
+            columns: HashMap::new(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Modes that are missing or invalid are left to the default, invalid ones with a warning.
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let mode = |key: &str, value: &str| {
+            let mode = MetricsMode::parse(value);
+            if mode.is_none() {
+                log::warn!("Ignoring invalid table property {}={}", key, value);
+            }
+            mode
+        };
+        let default = properties
+            .get("write.metadata.metrics.default")
+            .and_then(|value| mode("write.metadata.metrics.default", value))
+            .unwrap_or(MetricsConfig::default().default);
+        let columns = properties
+            .iter()
+            .filter_map(|(key, value)| {
+                let column = key.strip_prefix("write.metadata.metrics.column.")?;
+                Some((column.to_string(), mode(key, value)?))
+            })
+            .collect();
+        MetricsConfig { default, columns }
+    }
+
+    pub fn mode(&self, column: &str) -> MetricsMode {
+        self.columns.get(column).copied().unwrap_or(self.default)
+    }
+}
+
+/// The metrics of a data file, by field id of the leaf columns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    pub column_sizes: BTreeMap<i32, i64>,
+    pub value_counts: BTreeMap<i32, i64>,
+    pub null_value_counts: BTreeMap<i32, i64>,
+    pub nan_value_counts: BTreeMap<i32, i64>,
+    pub lower_bounds: BTreeMap<i32, Vec<u8>>,
+    pub upper_bounds: BTreeMap<i32, Vec<u8>>,
+}
+
+impl Metrics {
+    /// The metrics of `chunk`, whose columns are the fields of `schema`, given the sizes its
+    /// columns were written with. Values in lists and maps are counted but have no bounds,
+    /// since a bound on them can't tell whether a file has rows that match.
+    pub fn collect(
+        schema: &Schema,
+        chunk: &Chunk<Box<dyn Array>>,
+        column_sizes: &BTreeMap<i32, i64>,
+        config: &MetricsConfig,
+    ) -> Self {
+        let mut collector = Collector {
+            metrics: Metrics::default(),
+            column_sizes,
+            config,
+        };
+        for (field, array) in schema.fields.iter().zip(chunk.arrays()) {
+            collector.column(field.id, &field.name, &field.field_type, array.as_ref(), false);
+        }
+        collector.metrics
+    }
+}
+
+struct Collector<'a> {
+    metrics: Metrics,
+    column_sizes: &'a BTreeMap<i32, i64>,
+    config: &'a MetricsConfig,
+}
+
+impl<'a> Collector<'a> {
+    fn column(&mut self, id: i32, path: &str, field_type: &Type, array: &dyn Array, repeated: bool) {
+        match field_type {
+            Type::Primitive(primitive) => self.leaf(id, path, primitive, array, repeated),
+            Type::Struct(struct_type) => {
+                let array = array.as_any().downcast_ref::<StructArray>().unwrap();
+                for (field, values) in struct_type.fields.iter().zip(array.values()) {
+                    // A field of a null struct is null, whatever its array holds
+                    let validity = match (array.validity(), values.validity()) {
+                        (Some(parent), Some(child)) => Some(parent & child),
+                        (parent, child) => parent.or(child).cloned(),
+                    };
+                    let values = values.with_validity(validity);
+                    let path = format!("{}.{}", path, field.name);
+                    self.column(field.id, &path, &field.field_type, values.as_ref(), repeated);
+                }
+            }
+            Type::List(list) => {
+                let array = array.as_any().downcast_ref::<ListArray<i32>>().unwrap();
+                let path = format!("{}.element", path);
+                self.column(list.element_id, &path, &list.element, array.values().as_ref(), true);
+            }
+            Type::Map(map) => {
+                let array = array.as_any().downcast_ref::<ListArray<i32>>().unwrap();
+                let entries = array.values().as_any().downcast_ref::<StructArray>().unwrap();
+                let [keys, values] = entries.values() else {
+                    unreachable!("Map entries have a key and a value");
+                };
+                self.column(map.key_id, &format!("{}.key", path), &map.key, keys.as_ref(), true);
+                self.column(
+                    map.value_id,
+                    &format!("{}.value", path),
+                    &map.value,
+                    values.as_ref(),
+                    true,
+                );
+            }
+        }
+    }
+
+    fn leaf(&mut self, id: i32, path: &str, primitive: &PrimitiveType, array: &dyn Array, repeated: bool) {
+        let length = match self.config.mode(path) {
+            MetricsMode::None => return,
+            MetricsMode::Counts => None,
+            MetricsMode::Truncate(length) => Some(Some(length)),
+            MetricsMode::Full => Some(None),
+        };
+        let metrics = &mut self.metrics;
+        if let Some(size) = self.column_sizes.get(&id) {
+            metrics.column_sizes.insert(id, *size);
+        }
+        metrics.value_counts.insert(id, array.len() as i64);
+        metrics.null_value_counts.insert(id, array.null_count() as i64);
+        if let Some(nans) = nan_count(array) {
+            metrics.nan_value_counts.insert(id, nans as i64);
+        }
+        let Some(length) = length.filter(|_| !repeated) else {
+            return;
+        };
+        if let Some((lower, upper)) = bounds(primitive, array) {
+            let (lower, upper) = match length {
+                Some(length) => (truncate_lower(lower, length), truncate_upper(upper, length)),
+                None => (lower, Some(upper)),
+            };
+            metrics.lower_bounds.insert(id, lower.to_bytes());
+            if let Some(upper) = upper {
+                metrics.upper_bounds.insert(id, upper.to_bytes());
+            }
+        }
+    }
+}
+
+fn nan_count(array: &dyn Array) -> Option<usize> {
+    let any = array.as_any();
+    if let Some(array) = any.downcast_ref::<PrimitiveArray<f32>>() {
+        Some(array.iter().flatten().filter(|value| value.is_nan()).count())
+    } else {
+        let array = any.downcast_ref::<PrimitiveArray<f64>>()?;
+        Some(array.iter().flatten().filter(|value| value.is_nan()).count())
+    }
+}
+
+/// The smallest and largest of `values`. NaNs aren't ordered, so they're left out.
+fn min_max<T: PartialOrd + Copy>(values: impl Iterator<Item = T>) -> Option<(T, T)> {
+    values
+        .filter(|value| value.partial_cmp(value).is_some())
+        .fold(None, |bounds, value| match bounds {
+            None => Some((value, value)),
+            Some((min, max)) => Some((
+                if value < min { value } else { min },
+                if value > max { value } else { max },
+            )),
+        })
+}
+
+/// The lower and upper bounds of the values in `array`, an array of `primitive` values, or
+/// `None` if it only has nulls and NaNs.
+fn bounds(primitive: &PrimitiveType, array: &dyn Array) -> Option<(Literal, Literal)> {
+    fn both<T>((min, max): (T, T), literal: impl Fn(T) -> Literal) -> (Literal, Literal) {
+        (literal(min), literal(max))
+    }
+    let any = array.as_any();
+    let bounds = match primitive {
+        PrimitiveType::Boolean => {
+            let array = any.downcast_ref::<BooleanArray>()?;
+            both(min_max(array.iter().flatten())?, Literal::Boolean)
+        }
+        PrimitiveType::Int | PrimitiveType::Date => {
+            let array = any.downcast_ref::<PrimitiveArray<i32>>()?;
+            let literal = match primitive {
+                PrimitiveType::Int => Literal::Int,
+                _ => Literal::Date,
+            };
+            both(min_max(array.iter().flatten().copied())?, literal)
+        }
+        PrimitiveType::Long | PrimitiveType::Time | PrimitiveType::Timestamp | PrimitiveType::Timestamptz => {
+            let array = any.downcast_ref::<PrimitiveArray<i64>>()?;
+            let literal = match primitive {
+                PrimitiveType::Long => Literal::Long,
+                PrimitiveType::Time => Literal::Time,
+                PrimitiveType::Timestamp => Literal::Timestamp,
+                _ => Literal::TimestampTz,
+            };
+            both(min_max(array.iter().flatten().copied())?, literal)
+        }
+        PrimitiveType::Float => {
+            let array = any.downcast_ref::<PrimitiveArray<f32>>()?;
+            both(min_max(array.iter().flatten().copied())?, Literal::Float)
+        }
+        PrimitiveType::Double => {
+            let array = any.downcast_ref::<PrimitiveArray<f64>>()?;
+            both(min_max(array.iter().flatten().copied())?, Literal::Double)
+        }
+        PrimitiveType::Decimal { .. } => {
+            let array = any.downcast_ref::<PrimitiveArray<i128>>()?;
+            both(min_max(array.iter().flatten().copied())?, Literal::Decimal)
+        }
+        PrimitiveType::String => {
+            let array = any.downcast_ref::<Utf8Array<i32>>()?;
+            both(min_max(array.iter().flatten())?, |value| {
+                Literal::String(value.to_string())
+            })
+        }
+        PrimitiveType::Binary => {
+            let array = any.downcast_ref::<BinaryArray<i32>>()?;
+            both(min_max(array.iter().flatten())?, |value| {
+                Literal::Binary(value.to_vec())
+            })
+        }
+        PrimitiveType::Uuid => {
+            let array = any.downcast_ref::<FixedSizeBinaryArray>()?;
+            both(min_max(array.iter().flatten())?, |value| {
+                Literal::Uuid(u128::from_be_bytes(value.try_into().unwrap()))
+            })
+        }
+        PrimitiveType::Fixed(_) => {
+            let array = any.downcast_ref::<FixedSizeBinaryArray>()?;
+            both(min_max(array.iter().flatten())?, |value| Literal::Fixed(value.to_vec()))
+        }
+    };
+    Some(bounds)
+}
+
+/// `lower` cut to `length` characters or bytes, which is still no bigger than any value.
+fn truncate_lower(lower: Literal, length: usize) -> Literal {
+    match lower {
+        Literal::String(value) => Literal::String(value.chars().take(length).collect()),
+        Literal::Binary(mut value) => {
+            value.truncate(length);
+            Literal::Binary(value)
+        }
+        lower => lower,
+    }
+}
+
+/// `upper` cut to `length` characters or bytes, with the last one that can be incremented
+/// incremented so it's still no smaller than any value, or `None` if none can.
+fn truncate_upper(upper: Literal, length: usize) -> Option<Literal> {
+    match upper {
+        Literal::String(value) if value.chars().count() > length => {
+            let mut chars = value.chars().take(length).collect::<Vec<_>>();
+            while let Some(last) = chars.pop() {
+                // The next character, skipping the surrogates that aren't characters
+                let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+                if let Some(next) = next {
+                    chars.push(next);
+                    return Some(Literal::String(chars.into_iter().collect()));
+                }
+            }
+            None
+        }
+        Literal::Binary(mut value) if value.len() > length => {
+            value.truncate(length);
+            while let Some(last) = value.pop() {
+                if last < u8::MAX {
+                    value.push(last + 1);
+                    return Some(Literal::Binary(value));
+                }
+            }
+            None
+        }
+        upper => Some(upper),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::schema::{ListType, NestedField, StructType};
+    use crate::iceberg::values::Value;
+
+    #[test]
+    fn collects_metrics_by_mode() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "score", Type::Primitive(PrimitiveType::Double)),
+                NestedField::optional(
+                    4,
+                    "author",
+                    Type::Struct(StructType {
+                        fields: vec![NestedField::optional(5, "name", Type::Primitive(PrimitiveType::String))],
+                    }),
+                ),
+                NestedField::optional(
+                    6,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 7,
+                        element_required: true,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+            ],
+        );
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let records = vec![
+            vec![
+                string("R2RRIALQ1UBYO8-how-the-irish"),
+                Value::Primitive(Literal::Int(1)),
+                Value::Primitive(Literal::Double(f64::NAN)),
+                Value::Struct(vec![string("Thomas Cahill")]),
+                Value::List(vec![string("history"), string("ireland")]),
+            ],
+            vec![
+                string("R1"),
+                Value::Primitive(Literal::Int(5)),
+                Value::Primitive(Literal::Double(0.5)),
+                Value::Null,
+                Value::Null,
+            ],
+            vec![
+                string("R2"),
+                Value::Null,
+                Value::Null,
+                Value::Struct(vec![Value::Null]),
+                Value::List(vec![]),
+            ],
+        ];
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+        let config = MetricsConfig::from_properties(&HashMap::from([
+            ("write.metadata.metrics.default".to_string(), "truncate(4)".to_string()),
+            (
+                "write.metadata.metrics.column.star_rating".to_string(),
+                "counts".to_string(),
+            ),
+            (
+                "write.metadata.metrics.column.author.name".to_string(),
+                "none".to_string(),
+            ),
+            (
+                "write.metadata.metrics.column.score".to_string(),
+                "truncate(0)".to_string(),
+            ),
+        ]));
+        let sizes = BTreeMap::from([(1, 100), (2, 10), (5, 20)]);
+        let metrics = Metrics::collect(&schema, &chunk, &sizes, &config);
+
+        assert_eq!(metrics.column_sizes, BTreeMap::from([(1, 100), (2, 10)]));
+        assert_eq!(metrics.value_counts, BTreeMap::from([(1, 3), (2, 3), (3, 3), (7, 2)]));
+        assert_eq!(
+            metrics.null_value_counts,
+            BTreeMap::from([(1, 0), (2, 1), (3, 1), (7, 0)])
+        );
+        assert_eq!(metrics.nan_value_counts, BTreeMap::from([(3, 1)]));
+        assert_eq!(
+            metrics.lower_bounds,
+            BTreeMap::from([(1, b"R1".to_vec()), (3, 0.5f64.to_le_bytes().to_vec())])
+        );
+        assert_eq!(
+            metrics.upper_bounds,
+            BTreeMap::from([(1, b"R2RS".to_vec()), (3, 0.5f64.to_le_bytes().to_vec())])
+        );
+
+        let metrics = Metrics::collect(&schema, &chunk, &sizes, &MetricsConfig::default());
+        assert_eq!(metrics.null_value_counts[&5], 2);
+        assert_eq!(metrics.upper_bounds[&5], b"Thomas Cahill".to_vec());
+    }
+
+    #[test]
+    fn truncates_upper_bounds_to_a_larger_value() {
+        assert_eq!(
+            truncate_upper(Literal::String("ab\u{10ffff}\u{10ffff}z".to_string()), 4),
+            Some(Literal::String("ac".to_string()))
+        );
+        assert_eq!(
+            truncate_upper(Literal::String("a\u{d7ff}b".to_string()), 2),
+            Some(Literal::String("a\u{e000}".to_string()))
+        );
+        assert_eq!(truncate_upper(Literal::Binary(vec![0xff, 0xff, 1]), 2), None);
+        assert_eq!(
+            truncate_lower(Literal::String("héllo".to_string()), 2),
+            Literal::String("hé".to_string())
+        );
+    }
+}
@@ -0,0 +1,21 @@
+// Just enough of the Iceberg table spec to append and delete data from the ingest lambda
+// https://iceberg.apache.org/spec/
+
+pub mod arrow;
+pub(crate) mod avro;
+pub mod deletes;
+pub mod expression;
+pub mod location;
+pub mod manifest;
+pub mod manifest_list;
+pub mod metadata;
+pub mod metrics;
+pub mod parquet;
+pub mod partition;
+pub mod scan;
+pub mod schema;
+pub mod snapshot;
+pub mod sort;
+pub mod transform;
+pub mod update;
+pub mod values;
@@ -0,0 +1,1044 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::io::Cursor;
+use std::iter::Peekable;
+use std::vec;
+
+use arrow2::array::{new_empty_array, new_null_array, Array, BinaryArray, DictionaryArray, PrimitiveArray, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::compute::aggregate::estimated_bytes_size;
+use arrow2::compute::cast::{cast, CastOptions};
+use arrow2::compute::concatenate::concatenate;
+use arrow2::datatypes::{DataType, Field, IntegerType, Schema as ArrowSchema};
+use arrow2::io::parquet::read::{read_metadata, FileReader};
+use arrow2::io::parquet::write::{
+    array_to_columns, to_parquet_type as arrow_to_parquet_type, transverse, BrotliLevel, CompressionOptions, Encoding,
+    GzipLevel, Version, WriteOptions, ZstdLevel,
+};
+use futures::AsyncWriteExt;
+use parquet2::error::Error as ParquetError;
+use parquet2::metadata::SchemaDescriptor;
+use parquet2::page::{CompressedDictPage, CompressedPage, DataPage, DataPageHeader, EncodedPage};
+use parquet2::schema::types::{FieldInfo, GroupConvertedType, GroupLogicalType, ParquetType, PrimitiveLogicalType};
+use parquet2::schema::Repetition;
+use parquet2::write::{self, DynIter, DynStreamingIterator, FileStreamer, RowGroupIter, WriteOptions as FileWriteOptions};
+use parquet2::FallibleStreamingIterator;
+
+use crate::iceberg::arrow::{arrow_field, schema_to_arrow};
+use crate::iceberg::metrics::{Metrics, MetricsConfig};
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+use crate::storage::ObjectStore;
+
+// Writing data files as Parquet - https://iceberg.apache.org/spec/#parquet
+// Every column carries the id of the Iceberg field it holds, so readers resolve columns by id
+// and keep working after columns are renamed or reordered.
+
+/// The Parquet schema of data files for `schema`, with field ids on every column and group.
+pub fn to_parquet_schema(schema: &Schema) -> anyhow::Result<SchemaDescriptor> {
+    let fields = schema
+        .fields
+        .iter()
+        .map(nested_field_to_parquet)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(SchemaDescriptor::new("table".to_string(), fields))
+}
+
+fn repetition(required: bool) -> Repetition {
+    if required {
+        Repetition::Required
+    } else {
+        Repetition::Optional
+    }
+}
+
+fn nested_field_to_parquet(field: &NestedField) -> anyhow::Result<ParquetType> {
+    to_parquet_type(field.id, &field.name, &field.field_type, field.required)
+}
+
+fn to_parquet_type(id: i32, name: &str, field_type: &Type, required: bool) -> anyhow::Result<ParquetType> {
+    let parquet_type = match field_type {
+        Type::Primitive(primitive) => {
+            // Physical and logical types are the ones arrow2 writes the values with
+            let mut parquet_type = arrow_to_parquet_type(&arrow_field(id, name, field_type, !required))?;
+            if let ParquetType::PrimitiveType(primitive_type) = &mut parquet_type {
+                primitive_type.field_info.id = Some(id);
+                if *primitive == PrimitiveType::Uuid {
+                    primitive_type.logical_type = Some(PrimitiveLogicalType::Uuid);
+                }
+            }
+            parquet_type
+        }
+        Type::Struct(struct_type) => ParquetType::from_group(
+            name.to_string(),
+            repetition(required),
+            None,
+            None,
+            // Like the arrow fields, the fields of an optional struct are optional too
+            struct_type
+                .fields
+                .iter()
+                .map(|field| to_parquet_type(field.id, &field.name, &field.field_type, required && field.required))
+                .collect::<anyhow::Result<_>>()?,
+            Some(id),
+        ),
+        Type::List(list) => {
+            let element = to_parquet_type(list.element_id, "element", &list.element, list.element_required)?;
+            let repeated = ParquetType::from_group(
+                "list".to_string(),
+                Repetition::Repeated,
+                None,
+                None,
+                vec![element],
+                None,
+            );
+            ParquetType::from_group(
+                name.to_string(),
+                repetition(required),
+                Some(GroupConvertedType::List),
+                Some(GroupLogicalType::List),
+                vec![repeated],
+                Some(id),
+            )
+        }
+        Type::Map(map) => {
+            let key = to_parquet_type(map.key_id, "key", &map.key, true)?;
+            let value = to_parquet_type(map.value_id, "value", &map.value, map.value_required)?;
+            let key_value = ParquetType::from_group(
+                "key_value".to_string(),
+                Repetition::Repeated,
+                None,
+                None,
+                vec![key, value],
+                None,
+            );
+            ParquetType::from_group(
+                name.to_string(),
+                repetition(required),
+                Some(GroupConvertedType::Map),
+                Some(GroupLogicalType::Map),
+                vec![key_value],
+                Some(id),
+            )
+        }
+    };
+    Ok(parquet_type)
+}
+
+/// The field id of a Parquet column or group.
+pub fn parquet_field_id(parquet_type: &ParquetType) -> Option<i32> {
+    let FieldInfo { id, .. } = parquet_type.get_field_info();
+    *id
+}
+
+/// How a column is encoded. Dictionary encoded columns are written as a dictionary page and
+/// RLE encoded indices into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnEncoding {
+    /// Dictionary encoded if a sample of its values has few enough distinct ones.
+    Auto,
+    Dictionary,
+    Plain,
+}
+
+/// Rows sampled to estimate how many distinct values a column has.
+const DICTIONARY_SAMPLE_ROWS: usize = 1000;
+
+/// How data files are written, configured with the same `write.parquet.*` table properties as
+/// the Java implementation, plus `write.parquet.dict-enabled` to turn dictionary encoding off
+/// and `write.parquet.dict-enabled.column.<name>` to turn it on or off for a column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteProperties {
+    pub compression: CompressionOptions,
+    pub write_statistics: bool,
+    pub dictionary_enabled: bool,
+    /// Top-level columns whose encoding isn't chosen automatically.
+    pub column_encodings: HashMap<String, ColumnEncoding>,
+    /// Which metrics of the columns are kept in manifests, from `write.metadata.metrics.*`.
+    pub metrics: MetricsConfig,
+    /// Columns whose dictionary would be bigger than this in a row group are written plain.
+    pub dict_size_bytes: usize,
+    pub page_size_bytes: usize,
+    pub row_group_size_bytes: usize,
+    /// Data files are rolled over to a new file once about this big.
+    pub target_file_size_bytes: u64,
+}
+
+impl Default for WriteProperties {
+    fn default() -> Self {
+        WriteProperties {
+            compression: CompressionOptions::Zstd(None),
+            write_statistics: true,
+            dictionary_enabled: true,
+            column_encodings: HashMap::new(),
+            metrics: MetricsConfig::default(),
+            dict_size_bytes: 2 * 1024 * 1024,
+            page_size_bytes: 1024 * 1024,
+            row_group_size_bytes: 128 * 1024 * 1024,
+            target_file_size_bytes: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl WriteProperties {
+    /// Properties that are missing or invalid keep their defaults, invalid ones with a warning.
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let default = WriteProperties::default();
+        let property = |key: &str| {
+            let value = properties.get(key)?;
+            let parsed = value.parse::<usize>().ok();
+            if parsed.is_none() {
+                log::warn!("Ignoring invalid table property {}={}", key, value);
+            }
+            parsed
+        };
+        let level = property("write.parquet.compression-level");
+        let compression = match properties.get("write.parquet.compression-codec") {
+            Some(codec) => compression(codec, level).unwrap_or_else(|| {
+                log::warn!("Ignoring unsupported write.parquet.compression-codec {} {:?}", codec, level);
+                default.compression
+            }),
+            None => compression("zstd", level).unwrap_or(default.compression),
+        };
+        WriteProperties {
+            compression,
+            write_statistics: default.write_statistics,
+            dictionary_enabled: properties
+                .get("write.parquet.dict-enabled")
+                .map_or(default.dictionary_enabled, |enabled| enabled != "false"),
+            column_encodings: properties
+                .iter()
+                .filter_map(|(key, value)| {
+                    let column = key.strip_prefix("write.parquet.dict-enabled.column.")?;
+                    let encoding = match value.as_str() {
+                        "true" => ColumnEncoding::Dictionary,
+                        "false" => ColumnEncoding::Plain,
+                        _ => {
+                            log::warn!("Ignoring invalid table property {}={}", key, value);
+                            return None;
+                        }
+                    };
+                    Some((column.to_string(), encoding))
+                })
+                .collect(),
+            metrics: MetricsConfig::from_properties(properties),
+            dict_size_bytes: property("write.parquet.dict-size-bytes").unwrap_or(default.dict_size_bytes),
+            page_size_bytes: property("write.parquet.page-size-bytes").unwrap_or(default.page_size_bytes),
+            row_group_size_bytes: property("write.parquet.row-group-size-bytes").unwrap_or(default.row_group_size_bytes),
+            target_file_size_bytes: property("write.target-file-size-bytes")
+                .map_or(default.target_file_size_bytes, |size| size as u64),
+        }
+    }
+
+    /// The encoding of the top-level column `name`.
+    pub fn column_encoding(&self, name: &str) -> ColumnEncoding {
+        match self.column_encodings.get(name) {
+            Some(encoding) => *encoding,
+            None if self.dictionary_enabled => ColumnEncoding::Auto,
+            None => ColumnEncoding::Plain,
+        }
+    }
+}
+
+/// The codec named by `write.parquet.compression-codec`, at `level` for the codecs that have
+/// levels, or `None` if either isn't supported.
+fn compression(codec: &str, level: Option<usize>) -> Option<CompressionOptions> {
+    let compression = match codec.to_ascii_lowercase().as_str() {
+        "uncompressed" | "none" => CompressionOptions::Uncompressed,
+        "snappy" => CompressionOptions::Snappy,
+        "lz4" => CompressionOptions::Lz4Raw,
+        "gzip" => match level {
+            Some(level) => CompressionOptions::Gzip(Some(GzipLevel::try_new(level.try_into().ok()?).ok()?)),
+            None => CompressionOptions::Gzip(None),
+        },
+        "zstd" => match level {
+            Some(level) => CompressionOptions::Zstd(Some(ZstdLevel::try_new(level.try_into().ok()?).ok()?)),
+            None => CompressionOptions::Zstd(None),
+        },
+        "brotli" => match level {
+            Some(level) => CompressionOptions::Brotli(Some(BrotliLevel::try_new(level.try_into().ok()?).ok()?)),
+            None => CompressionOptions::Brotli(None),
+        },
+        _ => return None,
+    };
+    Some(compression)
+}
+
+/// A data file that was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrittenFile {
+    pub location: String,
+    pub record_count: u64,
+    pub file_size_in_bytes: u64,
+    pub metrics: Metrics,
+}
+
+/// Streams `chunk`, whose columns are the fields of `schema`, as a Parquet file to `location`
+/// and returns the file's size and metrics. Nothing is left behind at `location` if writing
+/// fails part-way.
+pub async fn write_chunk(
+    storage: &dyn ObjectStore,
+    location: &str,
+    schema: &Schema,
+    properties: &WriteProperties,
+    chunk: Chunk<Box<dyn Array>>,
+) -> anyhow::Result<WrittenFile> {
+    let writer = ChunkWriter::new(schema, properties)?;
+    let mut row_groups = split(chunk.arrays(), properties.row_group_size_bytes).into_iter().peekable();
+    let (record_count, file_size_in_bytes, column_sizes) =
+        writer.write_file(storage, location, &mut row_groups, u64::MAX).await?;
+    Ok(WrittenFile {
+        location: location.to_string(),
+        record_count: record_count as u64,
+        file_size_in_bytes,
+        metrics: Metrics::collect(schema, &chunk, &column_sizes, &properties.metrics),
+    })
+}
+
+/// Like [`write_chunk`], but rolls over to a new file at `next_location()` once the row groups
+/// written to a file add up to `write.target-file-size-bytes`. Files are cut between row groups,
+/// so each holds at least one. The files already written are deleted again if a later one fails.
+pub async fn write_chunk_files(
+    storage: &dyn ObjectStore,
+    mut next_location: impl FnMut() -> String,
+    schema: &Schema,
+    properties: &WriteProperties,
+    chunk: Chunk<Box<dyn Array>>,
+) -> anyhow::Result<Vec<WrittenFile>> {
+    let writer = ChunkWriter::new(schema, properties)?;
+    let mut row_groups = split(chunk.arrays(), properties.row_group_size_bytes).into_iter().peekable();
+    let mut files: Vec<WrittenFile> = vec![];
+    let mut offset = 0;
+    while row_groups.peek().is_some() {
+        let location = next_location();
+        let written = writer
+            .write_file(storage, &location, &mut row_groups, properties.target_file_size_bytes)
+            .await;
+        let (record_count, file_size_in_bytes, column_sizes) = match written {
+            Ok(written) => written,
+            Err(err) => {
+                for file in &files {
+                    if let Err(delete_err) = storage.delete(&file.location).await {
+                        log::warn!("Failed to delete {}: {}", file.location, delete_err);
+                    }
+                }
+                return Err(err);
+            }
+        };
+        let rows = if offset == 0 && record_count == chunk.len() {
+            chunk.clone()
+        } else {
+            Chunk::new(chunk.arrays().iter().map(|array| array.slice(offset, record_count)).collect())
+        };
+        offset += record_count;
+        files.push(WrittenFile {
+            location,
+            record_count: record_count as u64,
+            file_size_in_bytes,
+            metrics: Metrics::collect(schema, &rows, &column_sizes, &properties.metrics),
+        });
+    }
+    Ok(files)
+}
+
+/// What's needed to write row groups of a schema's columns.
+struct ChunkWriter<'a> {
+    properties: &'a WriteProperties,
+    options: WriteOptions,
+    page_types: Vec<ParquetType>,
+    parquet_schema: SchemaDescriptor,
+    leaf_ids: Vec<Option<i32>>,
+}
+
+impl<'a> ChunkWriter<'a> {
+    fn new(schema: &Schema, properties: &'a WriteProperties) -> anyhow::Result<Self> {
+        let options = WriteOptions {
+            write_statistics: properties.write_statistics,
+            compression: properties.compression,
+            version: Version::V2,
+        };
+        let arrow_schema = schema_to_arrow(schema);
+        // The pages are encoded with arrow2's own Parquet types, which lay maps out as lists of
+        // key/value structs. Those have the same columns and levels as the map groups in the file's
+        // schema, which is what ends up in the footer.
+        let page_types = arrow_schema
+            .fields
+            .iter()
+            .map(arrow_to_parquet_type)
+            .collect::<Result<Vec<_>, _>>()?;
+        let parquet_schema = to_parquet_schema(schema)?;
+        let leaf_ids = parquet_schema
+            .columns()
+            .iter()
+            .map(|column| column.descriptor.primitive_type.field_info.id)
+            .collect();
+        Ok(ChunkWriter {
+            properties,
+            options,
+            page_types,
+            parquet_schema,
+            leaf_ids,
+        })
+    }
+
+    /// Writes row groups to a file at `location` until they add up to `target_size_bytes` or run
+    /// out, and returns the number of rows, the file's size and the size of every column.
+    async fn write_file(
+        &self,
+        storage: &dyn ObjectStore,
+        location: &str,
+        row_groups: &mut Peekable<vec::IntoIter<Vec<Box<dyn Array>>>>,
+        target_size_bytes: u64,
+    ) -> anyhow::Result<(usize, u64, BTreeMap<i32, i64>)> {
+        let mut rows = 0;
+        let mut column_sizes = BTreeMap::new();
+        let mut file = storage.writer(location)?;
+
+        let written = async {
+            let file_options = FileWriteOptions {
+                write_statistics: self.options.write_statistics,
+                version: self.options.version,
+            };
+            let mut writer = FileStreamer::new(&mut file, self.parquet_schema.clone(), file_options, Some(created_by()));
+            let mut size_bytes = 0;
+            while size_bytes < target_size_bytes {
+                let Some(arrays) = row_groups.next() else {
+                    break;
+                };
+                rows += arrays.first().map_or(0, |array| array.len());
+                let (row_group, sizes) = row_group(arrays, &self.page_types, self.properties, self.options)?;
+                for (id, size) in self.leaf_ids.iter().zip(sizes) {
+                    size_bytes += size as u64;
+                    if let Some(id) = id {
+                        *column_sizes.entry(*id).or_default() += size;
+                    }
+                }
+                writer.write(row_group).await?;
+            }
+            writer.end(None).await?;
+            file.close().await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+
+        match written {
+            Ok(()) => Ok((rows, file.bytes_written(), column_sizes)),
+            Err(err) => {
+                if let Err(abort_err) = file.abort().await {
+                    log::warn!("Failed to abort writing {}: {}", location, abort_err);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Reads the Parquet file at `location` as a chunk whose columns are the fields of `schema`,
+/// matched by field id so that renamed columns are still found. Fields the file doesn't have,
+/// such as columns added after it was written, are all null.
+pub async fn read_chunk(
+    storage: &dyn ObjectStore,
+    location: &str,
+    schema: &Schema,
+) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+    let mut reader = Cursor::new(storage.get(location).await?);
+    let metadata = read_metadata(&mut reader)?;
+    // The names the file's columns were written under, by field id
+    let file_names = metadata
+        .schema()
+        .fields()
+        .iter()
+        .filter_map(|field| Some((parquet_field_id(field)?, field.name().to_string())))
+        .collect::<HashMap<_, _>>();
+    let arrow_schema = schema_to_arrow(schema);
+    let present = schema
+        .fields
+        .iter()
+        .zip(&arrow_schema.fields)
+        .filter_map(|(field, arrow_field)| {
+            let name = file_names.get(&field.id)?;
+            Some((field.id, Field::new(name, arrow_field.data_type.clone(), arrow_field.is_nullable)))
+        })
+        .collect::<Vec<_>>();
+    let rows = metadata.num_rows;
+    let file_schema = ArrowSchema::from(present.iter().map(|(_, field)| field.clone()).collect::<Vec<_>>());
+    let chunks =
+        FileReader::new(reader, metadata.row_groups, file_schema, None, None, None).collect::<Result<Vec<_>, _>>()?;
+
+    let mut columns = vec![];
+    for (i, (id, field)) in present.iter().enumerate() {
+        let column = match chunks.as_slice() {
+            [] => new_empty_array(field.data_type.clone()),
+            [chunk] => chunk.arrays()[i].clone(),
+            chunks => concatenate(&chunks.iter().map(|chunk| chunk.arrays()[i].as_ref()).collect::<Vec<_>>())?,
+        };
+        columns.push((*id, column));
+    }
+    let mut columns = columns.into_iter().peekable();
+    let arrays = schema
+        .fields
+        .iter()
+        .zip(arrow_schema.fields)
+        .map(|(field, arrow_field)| match columns.next_if(|(id, _)| *id == field.id) {
+            Some((_, column)) => column,
+            None => new_null_array(arrow_field.data_type, rows),
+        })
+        .collect();
+    Ok(Chunk::new(arrays))
+}
+
+/// `arrays` cut into runs of rows of about `size_bytes` each, or just `arrays` if they're smaller.
+/// Empty arrays make no runs, so an empty chunk is written without row groups.
+fn split(arrays: &[Box<dyn Array>], size_bytes: usize) -> Vec<Vec<Box<dyn Array>>> {
+    let length = arrays.first().map_or(0, |array| array.len());
+    let bytes: usize = arrays.iter().map(|array| estimated_bytes_size(array.as_ref())).sum();
+    let rows = (length as u128 * size_bytes as u128 / bytes.max(1) as u128).clamp(1, length.max(1) as u128) as usize;
+    (0..length)
+        .step_by(rows)
+        .map(|offset| {
+            let rows = rows.min(length - offset);
+            arrays.iter().map(|array| array.slice(offset, rows)).collect()
+        })
+        .collect()
+}
+
+/// One row group of columns, and the compressed size of each leaf column. Each column chunk is
+/// dictionary encoded if its encoding calls for it and the dictionary is small enough, and split
+/// into pages otherwise: a column chunk only has one dictionary page, and arrow2 writes the
+/// dictionary with every data page.
+fn row_group(
+    arrays: Vec<Box<dyn Array>>,
+    page_types: &[ParquetType],
+    properties: &WriteProperties,
+    options: WriteOptions,
+) -> anyhow::Result<(RowGroupIter<'static, ParquetError>, Vec<i64>)> {
+    let mut columns: Vec<Vec<EncodedPage>> = vec![];
+    for (array, page_type) in arrays.into_iter().zip(page_types) {
+        let column_encoding = properties.column_encoding(&page_type.get_field_info().name);
+        let (pages, encoding) = match dictionary(array.as_ref(), column_encoding, properties) {
+            Some(dictionary) => (vec![dictionary], Encoding::RleDictionary),
+            None => (split(&[array], properties.page_size_bytes).concat(), Encoding::Plain),
+        };
+        let mut leaves: Vec<Vec<EncodedPage>> = vec![];
+        for page in pages {
+            let encodings = transverse(page.data_type(), |_| encoding);
+            for (i, leaf) in array_to_columns(page, page_type.clone(), options, &encodings)?.into_iter().enumerate() {
+                let mut leaf = leaf.collect::<Result<Vec<_>, _>>()?;
+                if encoding == Encoding::RleDictionary {
+                    leaf = leaf.into_iter().map(with_null_count).collect();
+                }
+                match leaves.get_mut(i) {
+                    Some(pages) => pages.extend(leaf),
+                    None => leaves.push(leaf),
+                }
+            }
+        }
+        columns.extend(leaves);
+    }
+    // Compressed up front, to know the size of every column chunk
+    let columns = columns
+        .into_iter()
+        .map(|pages| {
+            pages
+                .into_iter()
+                .map(|page| compress(page, options.compression))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let sizes = columns
+        .iter()
+        .map(|pages| pages.iter().map(|(_, size)| *size as i64).sum())
+        .collect();
+    let row_group = DynIter::new(columns.into_iter().map(|pages| {
+        let pages = pages.into_iter().map(|(page, _)| page).collect::<Vec<_>>().into_iter();
+        Ok(DynStreamingIterator::new(CompressedPages { pages, current: None }))
+    }));
+    Ok((row_group, sizes))
+}
+
+/// `page` compressed, and the size of its compressed data.
+fn compress(page: EncodedPage, compression: CompressionOptions) -> Result<(CompressedPage, usize), ParquetError> {
+    match page {
+        EncodedPage::Data(page) => {
+            let page = write::compress(EncodedPage::Data(page), vec![], compression)?;
+            let CompressedPage::Data(data) = &page else {
+                unreachable!("A data page compresses to a data page");
+            };
+            let size = data.compressed_size();
+            Ok((page, size))
+        }
+        // Compressed here as the size of a compressed dictionary page isn't public
+        EncodedPage::Dict(page) => {
+            let buffer = match compression {
+                CompressionOptions::Uncompressed => page.buffer.clone(),
+                _ => {
+                    let mut buffer = vec![];
+                    parquet2::compression::compress(compression, &page.buffer, &mut buffer)?;
+                    buffer
+                }
+            };
+            let size = buffer.len();
+            let page = CompressedDictPage::new(
+                buffer,
+                compression.into(),
+                page.buffer.len(),
+                page.num_values,
+                page.is_sorted,
+            );
+            Ok((CompressedPage::Dict(page), size))
+        }
+    }
+}
+
+/// The compressed pages of a column chunk, as the streaming iterator parquet2 writes.
+struct CompressedPages {
+    pages: vec::IntoIter<CompressedPage>,
+    current: Option<CompressedPage>,
+}
+
+impl FallibleStreamingIterator for CompressedPages {
+    type Item = CompressedPage;
+    type Error = ParquetError;
+
+    fn advance(&mut self) -> Result<(), ParquetError> {
+        self.current = self.pages.next();
+        Ok(())
+    }
+
+    fn get(&self) -> Option<&CompressedPage> {
+        self.current.as_ref()
+    }
+}
+
+/// `page` with the null count of its statistics set from its header. arrow2 takes the
+/// statistics of dictionary encoded pages from the dictionary, which has no nulls.
+fn with_null_count(page: EncodedPage) -> EncodedPage {
+    match page {
+        EncodedPage::Data(page) => {
+            let DataPageHeader::V2(mut header) = page.header().clone() else {
+                return EncodedPage::Data(page);
+            };
+            if let Some(statistics) = &mut header.statistics {
+                statistics.null_count = Some(header.num_nulls as i64);
+            }
+            let rows = header.num_rows as usize;
+            EncodedPage::Data(DataPage::new(
+                DataPageHeader::V2(header),
+                page.buffer().to_vec(),
+                page.descriptor,
+                Some(rows),
+            ))
+        }
+        page => page,
+    }
+}
+
+/// `array` as a dictionary array, if it's a top-level column of a type arrow2 can write
+/// dictionaries of, `encoding` calls for a dictionary and it's no bigger than `dict_size_bytes`.
+fn dictionary(array: &dyn Array, encoding: ColumnEncoding, properties: &WriteProperties) -> Option<Box<dyn Array>> {
+    let dictionary = match encoding {
+        ColumnEncoding::Plain => false,
+        ColumnEncoding::Dictionary => true,
+        ColumnEncoding::Auto => {
+            let (sampled, distinct) = sample_cardinality(array)?;
+            distinct <= sampled / 2
+        }
+    };
+    if !dictionary
+        || !matches!(
+            array.data_type(),
+            DataType::Utf8 | DataType::Binary | DataType::Int32 | DataType::Int64
+        )
+    {
+        return None;
+    }
+    let data_type = DataType::Dictionary(IntegerType::Int32, Box::new(array.data_type().clone()), false);
+    let dictionary = cast(array, &data_type, CastOptions::default()).ok()?;
+    let values = dictionary.as_any().downcast_ref::<DictionaryArray<i32>>()?.values();
+    (estimated_bytes_size(values.as_ref()) <= properties.dict_size_bytes).then_some(dictionary)
+}
+
+/// How many of up to [`DICTIONARY_SAMPLE_ROWS`] rows spread over `array` have values, and how
+/// many of those values are distinct, or `None` if arrow2 can't write `array` as a dictionary.
+fn sample_cardinality(array: &dyn Array) -> Option<(usize, usize)> {
+    fn count<T: Hash + Eq>(values: impl Iterator<Item = T>) -> (usize, usize) {
+        let values = values.collect::<Vec<_>>();
+        let distinct = values.iter().collect::<HashSet<_>>().len();
+        (values.len(), distinct)
+    }
+    let rows = (0..array.len())
+        .step_by((array.len() / DICTIONARY_SAMPLE_ROWS).max(1))
+        .filter(|&i| array.is_valid(i));
+    let any = array.as_any();
+    let counts = match array.data_type() {
+        DataType::Utf8 => {
+            let array = any.downcast_ref::<Utf8Array<i32>>()?;
+            count(rows.map(|i| array.value(i)))
+        }
+        DataType::Binary => {
+            let array = any.downcast_ref::<BinaryArray<i32>>()?;
+            count(rows.map(|i| array.value(i)))
+        }
+        DataType::Int32 => {
+            let array = any.downcast_ref::<PrimitiveArray<i32>>()?;
+            count(rows.map(|i| array.value(i)))
+        }
+        DataType::Int64 => {
+            let array = any.downcast_ref::<PrimitiveArray<i64>>()?;
+            count(rows.map(|i| array.value(i)))
+        }
+        _ => return None,
+    };
+    Some(counts)
+}
+
+fn created_by() -> String {
+    format!("{} version {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arrow2::io::parquet::read::{read_metadata, FileReader};
+
+    use super::*;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::schema::{ListType, MapType, StructType};
+    use crate::iceberg::values::{Literal, Value};
+    use crate::storage::memory::MemoryStore;
+
+    fn nested_schema() -> Schema {
+        Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Uuid)),
+                NestedField::optional(
+                    2,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 5,
+                        element_required: false,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+                NestedField::optional(
+                    3,
+                    "votes",
+                    Type::Map(MapType {
+                        key_id: 6,
+                        key: Box::new(Type::Primitive(PrimitiveType::String)),
+                        value_id: 7,
+                        value_required: true,
+                        value: Box::new(Type::Primitive(PrimitiveType::Long)),
+                    }),
+                ),
+                NestedField::optional(
+                    4,
+                    "author",
+                    Type::Struct(StructType {
+                        fields: vec![NestedField::required(8, "name", Type::Primitive(PrimitiveType::String))],
+                    }),
+                ),
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn writes_field_ids_for_nested_columns() {
+        let schema = nested_schema();
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let records = vec![
+            vec![
+                Value::Primitive(Literal::Uuid(1)),
+                Value::List(vec![string("history"), Value::Null]),
+                Value::Map(vec![(string("helpful"), Value::Primitive(Literal::Long(153)))]),
+                Value::Struct(vec![string("Thomas Cahill")]),
+            ],
+            vec![
+                Value::Primitive(Literal::Uuid(2)),
+                Value::Null,
+                Value::Map(vec![]),
+                Value::Null,
+            ],
+        ];
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+
+        let store = MemoryStore::new();
+        let written = write_chunk(&store, "memory://data/a.parquet", &schema, &WriteProperties::default(), chunk.clone())
+            .await
+            .unwrap();
+        let bytes = store.get("memory://data/a.parquet").await.unwrap();
+        assert_eq!(written.file_size_in_bytes, bytes.len() as u64);
+        assert_eq!(
+            written.metrics.column_sizes.keys().copied().collect::<Vec<_>>(),
+            [1, 5, 6, 7, 8]
+        );
+        assert_eq!(written.metrics.value_counts[&1], 2);
+
+        let metadata = read_metadata(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(metadata.num_rows, 2);
+        let columns = metadata
+            .schema()
+            .columns()
+            .iter()
+            .map(|column| {
+                let ids = column.base_type.get_field_info().id;
+                (
+                    column.path_in_schema.join("."),
+                    ids,
+                    column.descriptor.primitive_type.field_info.id,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            [
+                ("id".to_string(), Some(1), Some(1)),
+                ("tags.list.element".to_string(), Some(2), Some(5)),
+                ("votes.key_value.key".to_string(), Some(3), Some(6)),
+                ("votes.key_value.value".to_string(), Some(3), Some(7)),
+                ("author.name".to_string(), Some(4), Some(8)),
+            ]
+        );
+        let votes = &metadata.schema().fields()[2];
+        assert!(matches!(
+            votes,
+            ParquetType::GroupType {
+                logical_type: Some(GroupLogicalType::Map),
+                ..
+            }
+        ));
+        assert_eq!(parquet_field_id(votes), Some(3));
+
+        // arrow2 reads Parquet maps wrongly, but it can read them as the lists they're written from
+        let bytes = store.get("memory://data/a.parquet").await.unwrap();
+        let reader = FileReader::new(
+            Cursor::new(bytes),
+            metadata.row_groups,
+            schema_to_arrow(&schema),
+            None,
+            None,
+            None,
+        );
+        let chunks = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(chunks, [chunk]);
+    }
+
+    #[tokio::test]
+    async fn writes_with_the_table_write_properties() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "marketplace", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(3, "helpful_votes", Type::Primitive(PrimitiveType::Long)),
+            ],
+        );
+        let records = (0..1000)
+            .map(|i| {
+                vec![
+                    Value::Primitive(Literal::String(format!("R{}", i))),
+                    match i % 3 {
+                        0 => Value::Null,
+                        _ => Value::Primitive(Literal::String("US".to_string())),
+                    },
+                    Value::Null,
+                ]
+            })
+            .collect::<Vec<_>>();
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+        let properties = WriteProperties::from_properties(&HashMap::from([
+            ("write.parquet.compression-codec".to_string(), "snappy".to_string()),
+            ("write.parquet.row-group-size-bytes".to_string(), "4096".to_string()),
+            ("write.parquet.page-size-bytes".to_string(), "1024".to_string()),
+            ("write.parquet.dict-size-bytes".to_string(), "bogus".to_string()),
+        ]));
+        assert_eq!(properties.compression, CompressionOptions::Snappy);
+        assert_eq!(properties.dict_size_bytes, WriteProperties::default().dict_size_bytes);
+
+        let store = MemoryStore::new();
+        write_chunk(&store, "memory://data/a.parquet", &schema, &properties, chunk.clone())
+            .await
+            .unwrap();
+        let bytes = store.get("memory://data/a.parquet").await.unwrap();
+        let metadata = read_metadata(&mut Cursor::new(bytes.clone())).unwrap();
+        assert!(metadata.row_groups.len() > 1);
+        for row_group in &metadata.row_groups {
+            let [review_id, marketplace, helpful_votes] = row_group.columns() else {
+                panic!("Expected 3 columns");
+            };
+            assert_eq!(review_id.compression(), parquet2::compression::Compression::Snappy);
+            assert!(marketplace
+                .column_encoding()
+                .contains(&Encoding::RleDictionary.into()));
+            let statistics = marketplace.statistics().unwrap().unwrap();
+            assert!(statistics.null_count().unwrap() > 0);
+            let statistics = helpful_votes.statistics().unwrap().unwrap();
+            assert_eq!(statistics.null_count(), Some(row_group.num_rows() as i64));
+        }
+
+        let reader = FileReader::new(
+            Cursor::new(bytes),
+            metadata.row_groups,
+            schema_to_arrow(&schema),
+            None,
+            None,
+            None,
+        );
+        let chunks = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        let rows: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+        assert_eq!(rows, chunk.len());
+        assert_eq!(chunks[0].arrays()[1].null_count(), chunks[0].len().div_ceil(3));
+    }
+
+    #[tokio::test]
+    async fn chooses_column_encodings_by_cardinality() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::optional(1, "marketplace", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "star_rating", Type::Primitive(PrimitiveType::Int)),
+                NestedField::optional(3, "vine", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(4, "review_body", Type::Primitive(PrimitiveType::String)),
+            ],
+        );
+        let string = |s: String| Value::Primitive(Literal::String(s));
+        let records = (0..500)
+            .map(|i| {
+                vec![
+                    string(["US", "UK", "DE"][i % 3].to_string()),
+                    Value::Primitive(Literal::Int(i as i32 % 5 + 1)),
+                    string(if i % 10 == 0 { "Y" } else { "N" }.to_string()),
+                    string(format!("Review number {}", i)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+        let dictionary_encoded = |properties: &[(&str, &str)]| {
+            let store = MemoryStore::new();
+            let schema = schema.clone();
+            let chunk = chunk.clone();
+            let properties = properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            async move {
+                let properties = WriteProperties::from_properties(&properties);
+                write_chunk(&store, "memory://data/a.parquet", &schema, &properties, chunk)
+                    .await
+                    .unwrap();
+                let bytes = store.get("memory://data/a.parquet").await.unwrap();
+                let metadata = read_metadata(&mut Cursor::new(bytes)).unwrap();
+                metadata.row_groups[0]
+                    .columns()
+                    .iter()
+                    .map(|column| column.column_encoding().contains(&Encoding::RleDictionary.into()))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(dictionary_encoded(&[]).await, [true, true, true, false]);
+        assert_eq!(
+            dictionary_encoded(&[
+                ("write.parquet.dict-enabled.column.vine", "false"),
+                ("write.parquet.dict-enabled.column.review_body", "true"),
+            ])
+            .await,
+            [true, true, false, true]
+        );
+        assert_eq!(
+            dictionary_encoded(&[
+                ("write.parquet.dict-enabled", "false"),
+                ("write.parquet.dict-enabled.column.star_rating", "true"),
+            ])
+            .await,
+            [false, true, false, false]
+        );
+    }
+
+    #[tokio::test]
+    async fn rolls_over_to_new_files_at_the_target_size() {
+        let schema = Schema::new(
+            0,
+            vec![NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String))],
+        );
+        let records = (0..1000)
+            .map(|i| vec![Value::Primitive(Literal::String(format!("R{:04}", i)))])
+            .collect::<Vec<_>>();
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+        let properties = WriteProperties::from_properties(
+            &[
+                ("write.parquet.compression-codec", "uncompressed"),
+                ("write.parquet.dict-enabled", "false"),
+                ("write.parquet.row-group-size-bytes", "2000"),
+                ("write.target-file-size-bytes", "5000"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        );
+
+        let store = MemoryStore::new();
+        let mut files = 0;
+        let next_location = || {
+            files += 1;
+            format!("memory://data/{}.parquet", files)
+        };
+        let written = write_chunk_files(&store, next_location, &schema, &properties, chunk)
+            .await
+            .unwrap();
+        assert!(written.len() > 1);
+        assert_eq!(written.iter().map(|file| file.record_count).sum::<u64>(), 1000);
+
+        let mut first_row = 0;
+        for file in written {
+            let bytes = store.get(&file.location).await.unwrap();
+            assert_eq!(file.file_size_in_bytes, bytes.len() as u64);
+            let metadata = read_metadata(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(metadata.num_rows as u64, file.record_count);
+            assert!(metadata.row_groups.len() > 1);
+            assert_eq!(file.metrics.value_counts[&1], file.record_count as i64);
+            let lower_bound = format!("R{:04}", first_row);
+            assert_eq!(file.metrics.lower_bounds[&1], lower_bound.as_bytes());
+            first_row += file.record_count;
+        }
+
+        // An empty chunk has no row groups to write, so it makes no files
+        let empty = records_to_chunk(&schema, &[]).unwrap();
+        let written = write_chunk_files(&store, || unreachable!(), &schema, &properties, empty).await.unwrap();
+        assert!(written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reads_columns_by_field_id_after_renames() {
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let written_schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "customer_id", Type::Primitive(PrimitiveType::String)),
+            ],
+        );
+        let records = vec![vec![string("R1"), string("C1")], vec![string("R2"), string("C2")]];
+        let chunk = records_to_chunk(&written_schema, &records).unwrap();
+        let store = MemoryStore::new();
+        let location = "memory://data/a.parquet";
+        write_chunk(&store, location, &written_schema, &WriteProperties::default(), chunk.clone())
+            .await
+            .unwrap();
+
+        // customer_id was renamed and moved to the front, and a column was added since
+        let schema = Schema::new(
+            1,
+            vec![
+                NestedField::optional(2, "customer", Type::Primitive(PrimitiveType::String)),
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(3, "customer_id", Type::Primitive(PrimitiveType::String)),
+            ],
+        );
+        let read = read_chunk(&store, location, &schema).await.unwrap();
+        assert_eq!(read.arrays()[0], chunk.arrays()[1]);
+        assert_eq!(read.arrays()[1], chunk.arrays()[0]);
+        assert_eq!(read.arrays()[2].null_count(), 2);
+    }
+}
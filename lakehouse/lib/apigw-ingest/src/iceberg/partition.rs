@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use arrow2::array::{Array, BooleanArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::filter::filter_chunk;
+use serde::{Deserialize, Serialize};
+
+use crate::iceberg::arrow::{field_array, literal_at};
+use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, StructType};
+use crate::iceberg::transform::Transform;
+use crate::iceberg::values::Literal;
+
+// Partition specs - https://iceberg.apache.org/spec/#partition-specs
+
+/// The rows of a chunk in one partition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    /// One value per field of the partition spec
+    pub values: Vec<Option<Literal>>,
+    pub chunk: Chunk<Box<dyn Array>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionSpec {
+    pub spec_id: i32,
+    pub fields: Vec<PartitionField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionField {
+    pub source_id: i32,
+    pub field_id: i32,
+    pub name: String,
+    pub transform: Transform,
+}
+
+impl PartitionSpec {
+    pub fn unpartitioned() -> Self {
+        PartitionSpec { spec_id: 0, fields: vec![] }
+    }
+
+    pub fn is_unpartitioned(&self) -> bool {
+        self.fields.iter().all(|f| f.transform == Transform::Void)
+    }
+
+    /// The struct type of the partition tuple stored with each data file written under this spec.
+    pub fn partition_type(&self, schema: &Schema) -> anyhow::Result<StructType> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| {
+                let source = schema
+                    .field_by_id(f.source_id)
+                    .ok_or_else(|| anyhow!("Partition source field {} not found in schema", f.source_id))?;
+                let result_type = f.transform.result_type(&source.field_type)?;
+                Ok(NestedField::optional(f.field_id, &f.name, result_type))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(StructType { fields })
+    }
+
+    /// Where the data files of the partition with `values` go under the table's data
+    /// directory, such as `review_date_day=2006-06-11`, or an empty path if unpartitioned.
+    pub fn partition_path(&self, schema: &Schema, values: &[Option<Literal>]) -> anyhow::Result<String> {
+        let segments = self
+            .fields
+            .iter()
+            .zip(values)
+            .map(|(field, value)| {
+                let source = source_type(schema, field.source_id)?;
+                let value = field.transform.to_human_string(source, value.as_ref());
+                Ok(format!("{}={}", url_encode(&field.name), url_encode(&value)))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(segments.join("/"))
+    }
+
+    /// The rows of `chunk`, whose columns are the fields of `schema`, split by partition, in the
+    /// order their first rows come in. An empty chunk has no partitions.
+    pub fn split_chunk(
+        &self,
+        schema: &Schema,
+        chunk: Chunk<Box<dyn Array>>,
+    ) -> anyhow::Result<Vec<Partition>> {
+        let sources = self
+            .fields
+            .iter()
+            .map(|field| {
+                let source = source_type(schema, field.source_id)?;
+                let array = field_array(&schema.fields, chunk.arrays(), field.source_id)
+                    .ok_or_else(|| anyhow!("Partition source field {} is not in the chunk", field.source_id))?;
+                Ok((field.transform, source, array))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // The partition values and rows of each partition, and each partition by its values' bytes
+        let mut partitions: Vec<(Vec<Option<Literal>>, Vec<bool>)> = vec![];
+        let mut by_key: HashMap<Vec<Option<Vec<u8>>>, usize> = HashMap::new();
+        for row in 0..chunk.len() {
+            let values = sources
+                .iter()
+                .map(|(transform, source, array)| transform.apply(literal_at(source, array.as_ref(), row).as_ref()))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let key = values.iter().map(|value| value.as_ref().map(Literal::to_bytes)).collect();
+            let index = *by_key.entry(key).or_insert_with(|| {
+                partitions.push((values, vec![false; chunk.len()]));
+                partitions.len() - 1
+            });
+            partitions[index].1[row] = true;
+        }
+
+        match partitions.len() {
+            0 => Ok(vec![]),
+            1 => Ok(vec![Partition {
+                values: partitions.pop().unwrap().0,
+                chunk,
+            }]),
+            _ => partitions
+                .into_iter()
+                .map(|(values, rows)| {
+                    let chunk = filter_chunk(&chunk, &BooleanArray::from_slice(rows))?;
+                    Ok(Partition { values, chunk })
+                })
+                .collect(),
+        }
+    }
+}
+
+fn source_type(schema: &Schema, source_id: i32) -> anyhow::Result<&PrimitiveType> {
+    schema
+        .field_by_id(source_id)
+        .and_then(|field| field.field_type.as_primitive())
+        .ok_or_else(|| anyhow!("Partition source field {} is not a primitive field of the schema", source_id))
+}
+
+/// `value` encoded like Java's `URLEncoder` encodes the partition paths of the Java implementation.
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'*' | b'_' => encoded.push(byte as char),
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::schema::Type;
+    use crate::iceberg::values::Value;
+
+    #[test]
+    fn splits_chunks_by_partition() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(
+                    2,
+                    "product",
+                    Type::Struct(StructType {
+                        fields: vec![NestedField::optional(3, "category", Type::Primitive(PrimitiveType::String))],
+                    }),
+                ),
+                NestedField::optional(4, "review_date", Type::Primitive(PrimitiveType::Date)),
+            ],
+        );
+        let spec = PartitionSpec {
+            spec_id: 1,
+            fields: vec![
+                PartitionField {
+                    source_id: 3,
+                    field_id: 1000,
+                    name: "category".to_string(),
+                    transform: Transform::Identity,
+                },
+                PartitionField {
+                    source_id: 4,
+                    field_id: 1001,
+                    name: "review_month".to_string(),
+                    transform: Transform::Month,
+                },
+            ],
+        };
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let record = |id: &str, product: Value, date: i32| vec![string(id), product, Value::Primitive(Literal::Date(date))];
+        let records = vec![
+            record("R1", Value::Struct(vec![string("Books & Maps")]), 13310),
+            record("R2", Value::Null, 13310),
+            record("R3", Value::Struct(vec![string("Books & Maps")]), 13300),
+            record("R4", Value::Struct(vec![Value::Null]), 13340),
+        ];
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+
+        let partitions = spec.split_chunk(&schema, chunk).unwrap();
+        let rows = partitions
+            .iter()
+            .map(|partition| (partition.values.clone(), partition.chunk.len()))
+            .collect::<Vec<_>>();
+        let books = Some(Literal::String("Books & Maps".to_string()));
+        assert_eq!(
+            rows,
+            [
+                (vec![books.clone(), Some(Literal::Int(437))], 2),
+                (vec![None, Some(Literal::Int(437))], 1),
+                (vec![None, Some(Literal::Int(438))], 1),
+            ]
+        );
+        let review_ids = partitions[0].chunk.arrays()[0].as_any().downcast_ref::<arrow2::array::Utf8Array<i32>>();
+        assert_eq!(review_ids.unwrap().values_iter().collect::<Vec<_>>(), ["R1", "R3"]);
+
+        assert_eq!(
+            spec.partition_path(&schema, &partitions[0].values).unwrap(),
+            "category=Books+%26+Maps/review_month=2006-06"
+        );
+        assert_eq!(
+            spec.partition_path(&schema, &partitions[2].values).unwrap(),
+            "category=null/review_month=2006-07"
+        );
+        assert_eq!(PartitionSpec::unpartitioned().partition_path(&schema, &[]).unwrap(), "");
+
+        let empty = records_to_chunk(&schema, &[]).unwrap();
+        assert!(spec.split_chunk(&schema, empty).unwrap().is_empty());
+    }
+}
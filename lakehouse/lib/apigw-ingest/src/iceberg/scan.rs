@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use arrow2::chunk::Chunk;
+
+use crate::iceberg::deletes::{position_delete_schema, EqualityDeletes, DELETE_FILE_PATH_ID};
+use crate::iceberg::manifest::{read_manifest, DataContentType, DataFile, ManifestEntry, ManifestStatus};
+use crate::iceberg::manifest_list::{read_manifest_list, ManifestFile};
+use crate::iceberg::metadata::{Snapshot, TableMetadata};
+use crate::iceberg::parquet;
+use crate::iceberg::schema::Schema;
+use crate::storage::ObjectStore;
+
+// Scan planning - https://iceberg.apache.org/spec/#scan-planning
+// The live files of a snapshot are the entries of its manifests that aren't deleted. A delete
+// file applies to the data files of its partition: position deletes to those with the same or
+// a lower sequence number, equality deletes to those with a lower one. Equality deletes written
+// with an unpartitioned spec apply to every partition.
+
+/// A live data file of a snapshot, with the delete files that apply to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileScanTask {
+    /// The partition spec the file was written with
+    pub spec_id: i32,
+    /// The data sequence number of the file
+    pub sequence_number: i64,
+    pub data_file: DataFile,
+    pub deletes: Vec<DataFile>,
+}
+
+/// The entries of `manifest`, with the snapshot ids and sequence numbers that added entries
+/// inherit from the manifest list filled in.
+pub async fn read_manifest_entries(
+    storage: &dyn ObjectStore,
+    metadata: &TableMetadata,
+    manifest: &ManifestFile,
+) -> anyhow::Result<Vec<ManifestEntry>> {
+    let spec = metadata
+        .partition_specs
+        .iter()
+        .find(|spec| spec.spec_id == manifest.partition_spec_id)
+        .ok_or_else(|| anyhow!("Partition spec {} not found", manifest.partition_spec_id))?;
+    let bytes = storage.get(&manifest.manifest_path).await?;
+    let mut entries = read_manifest(&bytes, metadata.current_schema()?, spec)?;
+    for entry in &mut entries {
+        entry.snapshot_id.get_or_insert(manifest.added_snapshot_id);
+        if entry.status == ManifestStatus::Added {
+            entry.sequence_number.get_or_insert(manifest.sequence_number);
+            entry.file_sequence_number.get_or_insert(manifest.sequence_number);
+        }
+    }
+    Ok(entries)
+}
+
+/// The live data files of `snapshot`, each with the delete files that apply to it.
+pub async fn plan_files(
+    storage: &dyn ObjectStore,
+    metadata: &TableMetadata,
+    snapshot: &Snapshot,
+) -> anyhow::Result<Vec<FileScanTask>> {
+    let manifests = read_manifest_list(&storage.get(&snapshot.manifest_list).await?)?;
+    let mut data_files = vec![];
+    let mut delete_files = vec![];
+    for manifest in &manifests {
+        for entry in read_manifest_entries(storage, metadata, manifest).await? {
+            if entry.status == ManifestStatus::Deleted {
+                continue;
+            }
+            let sequence_number = entry
+                .sequence_number
+                .ok_or_else(|| anyhow!("{} has no sequence number", entry.data_file.file_path))?;
+            let file = (manifest.partition_spec_id, sequence_number, entry.data_file);
+            match file.2.content {
+                DataContentType::Data => data_files.push(file),
+                DataContentType::PositionDeletes | DataContentType::EqualityDeletes => delete_files.push(file),
+            }
+        }
+    }
+
+    let unpartitioned = metadata
+        .partition_specs
+        .iter()
+        .filter(|spec| spec.is_unpartitioned())
+        .map(|spec| spec.spec_id)
+        .collect::<HashSet<_>>();
+    let tasks = data_files
+        .into_iter()
+        .map(|(spec_id, sequence_number, data_file)| {
+            let same_partition = |delete_spec_id: i32, delete_file: &DataFile| {
+                delete_spec_id == spec_id && delete_file.partition == data_file.partition
+            };
+            let deletes = delete_files
+                .iter()
+                .filter(
+                    |(delete_spec_id, delete_sequence_number, delete_file)| match delete_file.content {
+                        DataContentType::PositionDeletes => {
+                            sequence_number <= *delete_sequence_number
+                                && same_partition(*delete_spec_id, delete_file)
+                                && may_reference(delete_file, &data_file.file_path)
+                        }
+                        DataContentType::EqualityDeletes => {
+                            sequence_number < *delete_sequence_number
+                                && (unpartitioned.contains(delete_spec_id)
+                                    || same_partition(*delete_spec_id, delete_file))
+                        }
+                        DataContentType::Data => false,
+                    },
+                )
+                .map(|(_, _, delete_file)| delete_file.clone())
+                .collect();
+            FileScanTask {
+                spec_id,
+                sequence_number,
+                data_file,
+                deletes,
+            }
+        })
+        .collect();
+    Ok(tasks)
+}
+
+/// Whether the file path bounds of a position delete file leave room for `path`.
+fn may_reference(delete_file: &DataFile, path: &str) -> bool {
+    let path = path.as_bytes();
+    let above_lower = match delete_file.lower_bounds.get(&DELETE_FILE_PATH_ID) {
+        Some(lower) => lower.as_slice() <= path,
+        None => true,
+    };
+    let below_upper = match delete_file.upper_bounds.get(&DELETE_FILE_PATH_ID) {
+        Some(upper) => upper.as_slice() >= path,
+        None => true,
+    };
+    above_lower && below_upper
+}
+
+impl FileScanTask {
+    /// Reads the data file as a chunk whose columns are the fields of `schema`, along with
+    /// whether each of its rows is live, i.e. not deleted by the task's delete files.
+    pub async fn read(
+        &self,
+        storage: &dyn ObjectStore,
+        schema: &Schema,
+    ) -> anyhow::Result<(Chunk<Box<dyn Array>>, Vec<bool>)> {
+        let chunk = parquet::read_chunk(storage, &self.data_file.file_path, schema).await?;
+        let mut live = vec![true; chunk.len()];
+        for delete_file in &self.deletes {
+            match delete_file.content {
+                DataContentType::PositionDeletes => {
+                    let deletes =
+                        parquet::read_chunk(storage, &delete_file.file_path, &position_delete_schema()).await?;
+                    let paths = deletes.arrays()[0].as_any().downcast_ref::<Utf8Array<i32>>();
+                    let positions = deletes.arrays()[1].as_any().downcast_ref::<Int64Array>();
+                    let (paths, positions) = paths
+                        .zip(positions)
+                        .ok_or_else(|| anyhow!("{} is not a position delete file", delete_file.file_path))?;
+                    for (path, position) in paths.iter().zip(positions.iter()) {
+                        if let (Some(path), Some(position)) = (path, position) {
+                            if path == self.data_file.file_path {
+                                if let Some(row) = live.get_mut(*position as usize) {
+                                    *row = false;
+                                }
+                            }
+                        }
+                    }
+                }
+                DataContentType::EqualityDeletes => {
+                    let equality_ids = delete_file.equality_ids.clone().unwrap_or_default();
+                    let equality = EqualityDeletes::new(schema, equality_ids)?;
+                    let deletes = parquet::read_chunk(storage, &delete_file.file_path, equality.schema()).await?;
+                    let deleted = equality.row_keys(&deletes).into_iter().collect::<HashSet<_>>();
+                    let keys = equality.row_keys(&equality.project(schema, &chunk)?);
+                    for (row, key) in keys.iter().enumerate() {
+                        if deleted.contains(key) {
+                            live[row] = false;
+                        }
+                    }
+                }
+                DataContentType::Data => {}
+            }
+        }
+        Ok((chunk, live))
+    }
+}
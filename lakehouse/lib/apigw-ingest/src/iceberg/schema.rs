@@ -0,0 +1,294 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+
+// Iceberg schemas as they appear in table metadata - https://iceberg.apache.org/spec/#schemas
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "type", rename = "struct")]
+pub struct Schema {
+    #[serde(default)]
+    pub schema_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier_field_ids: Option<Vec<i32>>,
+    pub fields: Vec<NestedField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestedField {
+    pub id: i32,
+    pub name: String,
+    pub required: bool,
+    #[serde(rename = "type")]
+    pub field_type: Type,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PrimitiveType {
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Decimal { precision: u32, scale: u32 },
+    Date,
+    Time,
+    Timestamp,
+    Timestamptz,
+    String,
+    Uuid,
+    Fixed(u64),
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructType {
+    pub fields: Vec<NestedField>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListType {
+    pub element_id: i32,
+    pub element_required: bool,
+    pub element: Box<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapType {
+    pub key_id: i32,
+    pub key: Box<Type>,
+    pub value_id: i32,
+    pub value_required: bool,
+    pub value: Box<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Primitive(PrimitiveType),
+    Struct(StructType),
+    List(ListType),
+    Map(MapType),
+}
+
+impl Schema {
+    pub fn new(schema_id: i32, fields: Vec<NestedField>) -> Self {
+        Schema { schema_id, identifier_field_ids: None, fields }
+    }
+
+    /// Finds a field by id anywhere in the schema, including nested struct, list and map fields.
+    pub fn field_by_id(&self, id: i32) -> Option<&NestedField> {
+        fn search(fields: &[NestedField], id: i32) -> Option<&NestedField> {
+            fields.iter().find_map(|f| {
+                if f.id == id {
+                    return Some(f);
+                }
+                match &f.field_type {
+                    Type::Struct(s) => search(&s.fields, id),
+                    _ => None,
+                }
+            })
+        }
+        search(&self.fields, id)
+    }
+
+    pub fn field_by_name(&self, name: &str) -> Option<&NestedField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// The highest field id used by this schema.
+    pub fn highest_field_id(&self) -> i32 {
+        fn highest(field_type: &Type) -> i32 {
+            match field_type {
+                Type::Primitive(_) => 0,
+                Type::Struct(s) => s.fields.iter().map(|f| f.id.max(highest(&f.field_type))).max().unwrap_or(0),
+                Type::List(l) => l.element_id.max(highest(&l.element)),
+                Type::Map(m) => m.key_id.max(m.value_id).max(highest(&m.key)).max(highest(&m.value)),
+            }
+        }
+        highest(&Type::Struct(StructType { fields: self.fields.clone() }))
+    }
+}
+
+impl NestedField {
+    pub fn optional(id: i32, name: &str, field_type: Type) -> Self {
+        NestedField { id, name: name.to_string(), required: false, field_type, doc: None }
+    }
+
+    pub fn required(id: i32, name: &str, field_type: Type) -> Self {
+        NestedField { id, name: name.to_string(), required: true, field_type, doc: None }
+    }
+}
+
+impl Type {
+    pub fn as_primitive(&self) -> Option<&PrimitiveType> {
+        match self {
+            Type::Primitive(p) => Some(p),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrimitiveType::Boolean => write!(f, "boolean"),
+            PrimitiveType::Int => write!(f, "int"),
+            PrimitiveType::Long => write!(f, "long"),
+            PrimitiveType::Float => write!(f, "float"),
+            PrimitiveType::Double => write!(f, "double"),
+            PrimitiveType::Decimal { precision, scale } => write!(f, "decimal({}, {})", precision, scale),
+            PrimitiveType::Date => write!(f, "date"),
+            PrimitiveType::Time => write!(f, "time"),
+            PrimitiveType::Timestamp => write!(f, "timestamp"),
+            PrimitiveType::Timestamptz => write!(f, "timestamptz"),
+            PrimitiveType::String => write!(f, "string"),
+            PrimitiveType::Uuid => write!(f, "uuid"),
+            PrimitiveType::Fixed(length) => write!(f, "fixed[{}]", length),
+            PrimitiveType::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl FromStr for PrimitiveType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let primitive = match s {
+            "boolean" => PrimitiveType::Boolean,
+            "int" => PrimitiveType::Int,
+            "long" => PrimitiveType::Long,
+            "float" => PrimitiveType::Float,
+            "double" => PrimitiveType::Double,
+            "date" => PrimitiveType::Date,
+            "time" => PrimitiveType::Time,
+            "timestamp" => PrimitiveType::Timestamp,
+            "timestamptz" => PrimitiveType::Timestamptz,
+            "string" => PrimitiveType::String,
+            "uuid" => PrimitiveType::Uuid,
+            "binary" => PrimitiveType::Binary,
+            _ if s.starts_with("fixed[") && s.ends_with(']') => {
+                PrimitiveType::Fixed(s[6..s.len() - 1].trim().parse()?)
+            }
+            _ if s.starts_with("decimal(") && s.ends_with(')') => {
+                let (precision, scale) = s[8..s.len() - 1]
+                    .split_once(',')
+                    .ok_or_else(|| anyhow!("Invalid decimal type: {}", s))?;
+                PrimitiveType::Decimal { precision: precision.trim().parse()?, scale: scale.trim().parse()? }
+            }
+            _ => bail!("Unknown Iceberg type: {}", s),
+        };
+        Ok(primitive)
+    }
+}
+
+impl Type {
+    fn to_json(&self) -> Value {
+        match self {
+            Type::Primitive(p) => Value::String(p.to_string()),
+            Type::Struct(s) => json!({ "type": "struct", "fields": s.fields }),
+            Type::List(l) => json!({
+                "type": "list",
+                "element-id": l.element_id,
+                "element-required": l.element_required,
+                "element": l.element.to_json(),
+            }),
+            Type::Map(m) => json!({
+                "type": "map",
+                "key-id": m.key_id,
+                "key": m.key.to_json(),
+                "value-id": m.value_id,
+                "value-required": m.value_required,
+                "value": m.value.to_json(),
+            }),
+        }
+    }
+
+    fn from_json(value: &Value) -> anyhow::Result<Self> {
+        fn get<'a>(value: &'a Value, key: &str) -> anyhow::Result<&'a Value> {
+            value.get(key).ok_or_else(|| anyhow!("Missing '{}' in type {}", key, value))
+        }
+        fn get_i32(value: &Value, key: &str) -> anyhow::Result<i32> {
+            get(value, key)?.as_i64().map(|v| v as i32).ok_or_else(|| anyhow!("'{}' must be an integer", key))
+        }
+        fn get_bool(value: &Value, key: &str) -> anyhow::Result<bool> {
+            get(value, key)?.as_bool().ok_or_else(|| anyhow!("'{}' must be a boolean", key))
+        }
+
+        match value {
+            Value::String(s) => Ok(Type::Primitive(s.parse()?)),
+            Value::Object(_) => match get(value, "type")?.as_str() {
+                Some("struct") => Ok(Type::Struct(StructType {
+                    fields: serde_json::from_value(get(value, "fields")?.clone())?,
+                })),
+                Some("list") => Ok(Type::List(ListType {
+                    element_id: get_i32(value, "element-id")?,
+                    element_required: get_bool(value, "element-required")?,
+                    element: Box::new(Type::from_json(get(value, "element")?)?),
+                })),
+                Some("map") => Ok(Type::Map(MapType {
+                    key_id: get_i32(value, "key-id")?,
+                    key: Box::new(Type::from_json(get(value, "key")?)?),
+                    value_id: get_i32(value, "value-id")?,
+                    value_required: get_bool(value, "value-required")?,
+                    value: Box::new(Type::from_json(get(value, "value")?)?),
+                })),
+                _ => bail!("Unknown Iceberg type: {}", value),
+            },
+            _ => bail!("Unknown Iceberg type: {}", value),
+        }
+    }
+}
+
+impl Serialize for Type {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Type {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Type::from_json(&value).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_nested_types() {
+        let json = r#"{
+            "type": "struct",
+            "schema-id": 1,
+            "fields": [
+                {"id": 1, "name": "review_id", "required": true, "type": "string"},
+                {"id": 2, "name": "price", "required": false, "type": "decimal(9, 2)"},
+                {"id": 3, "name": "tags", "required": false, "type": {
+                    "type": "list", "element-id": 5, "element-required": false, "element": "string"
+                }},
+                {"id": 4, "name": "attrs", "required": false, "type": {
+                    "type": "map", "key-id": 6, "key": "string", "value-id": 7, "value-required": true, "value": "fixed[16]"
+                }}
+            ]
+        }"#;
+        let schema: Schema = serde_json::from_str(json).unwrap();
+
+        assert_eq!(schema.schema_id, 1);
+        assert_eq!(schema.highest_field_id(), 7);
+        assert_eq!(
+            schema.field_by_id(2).unwrap().field_type,
+            Type::Primitive(PrimitiveType::Decimal { precision: 9, scale: 2 })
+        );
+
+        let reparsed: Schema = serde_json::from_str(&serde_json::to_string(&schema).unwrap()).unwrap();
+        assert_eq!(schema, reparsed);
+    }
+}
@@ -0,0 +1,188 @@
+use std::cmp::Ordering;
+
+use anyhow::anyhow;
+use arrow2::array::{Array, PrimitiveArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::take::take;
+use serde::{Deserialize, Serialize};
+
+use crate::iceberg::arrow::{field_array, literal_at};
+use crate::iceberg::schema::Schema;
+use crate::iceberg::transform::Transform;
+use crate::iceberg::values::Literal;
+
+// Sort orders - https://iceberg.apache.org/spec/#sort-orders
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SortOrder {
+    pub order_id: i32,
+    pub fields: Vec<SortField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SortField {
+    pub transform: Transform,
+    pub source_id: i32,
+    pub direction: SortDirection,
+    pub null_order: NullOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NullOrder {
+    NullsFirst,
+    NullsLast,
+}
+
+impl SortOrder {
+    pub fn unsorted() -> Self {
+        SortOrder { order_id: 0, fields: vec![] }
+    }
+
+    pub fn is_unsorted(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The rows of `chunk`, whose columns are the fields of `schema`, in this order. Rows that
+    /// compare equal keep the order they came in.
+    pub fn sort_chunk(&self, schema: &Schema, chunk: Chunk<Box<dyn Array>>) -> anyhow::Result<Chunk<Box<dyn Array>>> {
+        if self.is_unsorted() || chunk.len() < 2 {
+            return Ok(chunk);
+        }
+        // The transformed value of each sort field, for every row
+        let keys = self
+            .fields
+            .iter()
+            .map(|field| {
+                let source = schema
+                    .field_by_id(field.source_id)
+                    .and_then(|source| source.field_type.as_primitive())
+                    .ok_or_else(|| anyhow!("Sort source field {} is not a primitive field of the schema", field.source_id))?;
+                let array = field_array(&schema.fields, chunk.arrays(), field.source_id)
+                    .ok_or_else(|| anyhow!("Sort source field {} is not in the chunk", field.source_id))?;
+                (0..chunk.len())
+                    .map(|row| field.transform.apply(literal_at(source, array.as_ref(), row).as_ref()))
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut rows = (0..chunk.len()).collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            self.fields
+                .iter()
+                .zip(&keys)
+                .map(|(field, keys)| field.compare(keys[*a].as_ref(), keys[*b].as_ref()))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+        if rows.iter().enumerate().all(|(i, row)| i == *row) {
+            return Ok(chunk);
+        }
+        let indices = PrimitiveArray::from_vec(rows.into_iter().map(|row| row as u32).collect());
+        let arrays = chunk
+            .arrays()
+            .iter()
+            .map(|array| take(array.as_ref(), &indices))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Chunk::try_new(arrays)?)
+    }
+}
+
+impl SortField {
+    fn compare(&self, a: Option<&Literal>, b: Option<&Literal>) -> Ordering {
+        match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if self.null_order == NullOrder::NullsFirst => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => self.compare(b, a).reverse(),
+            (Some(a), Some(b)) => {
+                let ordering = match (a, b) {
+                    // In their total order, which puts NaNs after every other number
+                    (Literal::Float(a), Literal::Float(b)) => a.total_cmp(b),
+                    (Literal::Double(a), Literal::Double(b)) => a.total_cmp(b),
+                    (a, b) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+                };
+                match self.direction {
+                    SortDirection::Asc => ordering,
+                    SortDirection::Desc => ordering.reverse(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow2::array::Utf8Array;
+
+    use super::*;
+    use crate::iceberg::arrow::records_to_chunk;
+    use crate::iceberg::schema::{ListType, NestedField, PrimitiveType, Type};
+    use crate::iceberg::values::Value;
+
+    #[test]
+    fn sorts_chunks_by_the_sort_order() {
+        let schema = Schema::new(
+            0,
+            vec![
+                NestedField::required(1, "review_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(2, "product_id", Type::Primitive(PrimitiveType::String)),
+                NestedField::optional(3, "review_date", Type::Primitive(PrimitiveType::Date)),
+                NestedField::optional(
+                    4,
+                    "tags",
+                    Type::List(ListType {
+                        element_id: 5,
+                        element_required: true,
+                        element: Box::new(Type::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+            ],
+        );
+        let order = SortOrder {
+            order_id: 1,
+            fields: vec![
+                SortField {
+                    transform: Transform::Identity,
+                    source_id: 2,
+                    direction: SortDirection::Asc,
+                    null_order: NullOrder::NullsFirst,
+                },
+                SortField {
+                    transform: Transform::Identity,
+                    source_id: 3,
+                    direction: SortDirection::Desc,
+                    null_order: NullOrder::NullsLast,
+                },
+            ],
+        };
+        let string = |s: &str| Value::Primitive(Literal::String(s.to_string()));
+        let date = |days: i32| Value::Primitive(Literal::Date(days));
+        let records = vec![
+            vec![string("R1"), string("B"), date(1), Value::List(vec![string("one")])],
+            vec![string("R2"), string("A"), Value::Null, Value::Null],
+            vec![string("R3"), string("A"), date(2), Value::List(vec![])],
+            vec![string("R4"), Value::Null, date(1), Value::List(vec![string("four")])],
+            vec![string("R5"), string("A"), date(3), Value::Null],
+        ];
+        let chunk = records_to_chunk(&schema, &records).unwrap();
+
+        let sorted = order.sort_chunk(&schema, chunk.clone()).unwrap();
+        let review_ids = sorted.arrays()[0].as_any().downcast_ref::<Utf8Array<i32>>().unwrap();
+        assert_eq!(review_ids.values_iter().collect::<Vec<_>>(), ["R4", "R5", "R3", "R2", "R1"]);
+        let order = [3, 4, 2, 1, 0];
+        let expected = records_to_chunk(&schema, &order.map(|i| records[i].clone())).unwrap();
+        assert_eq!(sorted, expected);
+
+        assert_eq!(SortOrder::unsorted().sort_chunk(&schema, chunk.clone()).unwrap(), chunk);
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::bail;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::formats::InvalidBody;
+use crate::iceberg::schema::{PrimitiveType, Type};
+use crate::iceberg::values::Literal;
+
+// Partition and sort transforms - https://iceberg.apache.org/spec/#partition-transforms
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    Identity,
+    Bucket(u32),
+    Truncate(u32),
+    Year,
+    Month,
+    Day,
+    Hour,
+    Void,
+}
+
+impl Transform {
+    /// The type of the partition value produced when applying this transform to `source`.
+    pub fn result_type(&self, source: &Type) -> anyhow::Result<Type> {
+        let primitive = match source {
+            Type::Primitive(p) => p,
+            _ => bail!("Cannot partition by nested type {:?}", source),
+        };
+        let result = match self {
+            Transform::Identity | Transform::Truncate(_) | Transform::Void => primitive.clone(),
+            Transform::Bucket(_) | Transform::Year | Transform::Month | Transform::Hour => PrimitiveType::Int,
+            Transform::Day => PrimitiveType::Date,
+        };
+        Ok(Type::Primitive(result))
+    }
+
+    /// The partition value of `value`, a value of the transform's source type. Dates and times
+    /// outside of what the transform can represent are an [`InvalidBody`].
+    /// https://iceberg.apache.org/spec/#partition-transforms
+    pub fn apply(&self, value: Option<&Literal>) -> anyhow::Result<Option<Literal>> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let result = match (self, value) {
+            (Transform::Void, _) => return Ok(None),
+            (Transform::Identity, value) => value.clone(),
+            (Transform::Bucket(n), value) => {
+                let hash = murmur3_32(&bucket_bytes(value)?);
+                Literal::Int(((hash & i32::MAX as u32) % n) as i32)
+            }
+            (Transform::Truncate(width), value) => {
+                let width = *width as usize;
+                match value {
+                    Literal::Int(v) => Literal::Int(v - v.rem_euclid(width as i32)),
+                    Literal::Long(v) => Literal::Long(v - v.rem_euclid(width as i64)),
+                    Literal::Decimal(v) => Literal::Decimal(v - v.rem_euclid(width as i128)),
+                    Literal::String(v) => Literal::String(v.chars().take(width).collect()),
+                    Literal::Binary(v) => Literal::Binary(v.iter().take(width).copied().collect()),
+                    _ => bail!("Cannot truncate {:?}", value),
+                }
+            }
+            (Transform::Year | Transform::Month | Transform::Day, Literal::Date(days)) => {
+                let date = UNIX_EPOCH_DAYS_FROM_CE
+                    .checked_add(*days)
+                    .and_then(NaiveDate::from_num_days_from_ce_opt)
+                    .ok_or_else(|| InvalidBody::new(format!("Date {} is out of range", days)))?;
+                match self {
+                    Transform::Year => Literal::Int(date.year() - 1970),
+                    Transform::Month => Literal::Int((date.year() - 1970) * 12 + date.month0() as i32),
+                    _ => Literal::Date(*days),
+                }
+            }
+            (
+                Transform::Year | Transform::Month | Transform::Day | Transform::Hour,
+                Literal::Timestamp(micros) | Literal::TimestampTz(micros),
+            ) => {
+                let days = micros.div_euclid(MICROS_PER_DAY) as i32;
+                match self {
+                    Transform::Day => Literal::Date(days),
+                    Transform::Hour => Literal::Int(
+                        i32::try_from(micros.div_euclid(MICROS_PER_HOUR))
+                            .map_err(|_| InvalidBody::new(format!("Timestamp {} is out of range", micros)))?,
+                    ),
+                    _ => return Transform::apply(self, Some(&Literal::Date(days))),
+                }
+            }
+            _ => bail!("Cannot apply {} to {:?}", self, value),
+        };
+        Ok(Some(result))
+    }
+
+    /// A partition value as it appears in data file paths, such as `2006-06-11` for a day.
+    /// `source` is the type of the values the transform applies to.
+    pub fn to_human_string(&self, source: &PrimitiveType, value: Option<&Literal>) -> String {
+        let Some(value) = value else {
+            return "null".to_string();
+        };
+        match (self, value) {
+            (Transform::Year, Literal::Int(years)) => format!("{:04}", 1970 + years),
+            (Transform::Month, Literal::Int(months)) => {
+                format!("{:04}-{:02}", 1970 + months.div_euclid(12), months.rem_euclid(12) + 1)
+            }
+            (Transform::Hour, Literal::Int(hours)) => {
+                let time = timestamp(*hours as i64 * MICROS_PER_HOUR);
+                time.map_or_else(|| hours.to_string(), |time| time.format("%Y-%m-%d-%H").to_string())
+            }
+            (Transform::Bucket(_), Literal::Int(bucket)) => bucket.to_string(),
+            (_, value) => literal_to_human_string(source, value),
+        }
+    }
+}
+
+/// Days from 0001-01-01 to 1970-01-01.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+fn timestamp(micros: i64) -> Option<NaiveDateTime> {
+    NaiveDateTime::from_timestamp_opt(micros.div_euclid(1_000_000), (micros.rem_euclid(1_000_000) * 1000) as u32)
+}
+
+fn literal_to_human_string(source: &PrimitiveType, value: &Literal) -> String {
+    let date = |days: i32| UNIX_EPOCH_DAYS_FROM_CE.checked_add(days).and_then(NaiveDate::from_num_days_from_ce_opt);
+    match value {
+        Literal::Date(days) => date(*days).map_or_else(|| days.to_string(), |date| date.to_string()),
+        Literal::Time(micros) => timestamp(*micros).map_or_else(
+            || micros.to_string(),
+            |time| time.format("%H:%M:%S%.f").to_string(),
+        ),
+        Literal::Timestamp(micros) => timestamp(*micros).map_or_else(
+            || micros.to_string(),
+            |time| time.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+        ),
+        Literal::TimestampTz(micros) => timestamp(*micros).map_or_else(
+            || micros.to_string(),
+            |time| time.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+        ),
+        Literal::Decimal(unscaled) => match source {
+            PrimitiveType::Decimal { scale, .. } if *scale > 0 => {
+                let scale = *scale as usize;
+                let digits = format!("{:0width$}", unscaled.unsigned_abs(), width = scale + 1);
+                let (integer, fraction) = digits.split_at(digits.len() - scale);
+                let sign = if *unscaled < 0 { "-" } else { "" };
+                format!("{}{}.{}", sign, integer, fraction)
+            }
+            _ => unscaled.to_string(),
+        },
+        Literal::Uuid(v) => Uuid::from_u128(*v).to_string(),
+        Literal::Fixed(v) | Literal::Binary(v) => BASE64.encode(v),
+        Literal::Boolean(v) => v.to_string(),
+        Literal::Int(v) => v.to_string(),
+        Literal::Long(v) => v.to_string(),
+        Literal::Float(v) => v.to_string(),
+        Literal::Double(v) => v.to_string(),
+        Literal::String(v) => v.clone(),
+    }
+}
+
+/// The bytes the bucket hash of a value is computed from: ints and longs hash alike, as do
+/// dates and times and their underlying numbers.
+fn bucket_bytes(value: &Literal) -> anyhow::Result<Vec<u8>> {
+    let bytes = match value {
+        Literal::Int(v) | Literal::Date(v) => (*v as i64).to_le_bytes().to_vec(),
+        Literal::Long(v) | Literal::Time(v) | Literal::Timestamp(v) | Literal::TimestampTz(v) => {
+            v.to_le_bytes().to_vec()
+        }
+        Literal::Decimal(_) | Literal::String(_) | Literal::Uuid(_) | Literal::Fixed(_) | Literal::Binary(_) => {
+            value.to_bytes()
+        }
+        Literal::Boolean(_) | Literal::Float(_) | Literal::Double(_) => bail!("Cannot bucket {:?}", value),
+    };
+    Ok(bytes)
+}
+
+/// 32-bit x86 Murmur3 with a seed of 0, the hash of the bucket transform.
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut hash = 0u32;
+    let blocks = data.chunks_exact(4);
+    let tail = blocks.remainder();
+    for block in blocks {
+        hash ^= mix(u32::from_le_bytes(block.try_into().unwrap()));
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    if !tail.is_empty() {
+        let k = tail.iter().rev().fold(0u32, |k, byte| (k << 8) | *byte as u32);
+        hash ^= mix(k);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Identity => write!(f, "identity"),
+            Transform::Bucket(n) => write!(f, "bucket[{}]", n),
+            Transform::Truncate(w) => write!(f, "truncate[{}]", w),
+            Transform::Year => write!(f, "year"),
+            Transform::Month => write!(f, "month"),
+            Transform::Day => write!(f, "day"),
+            Transform::Hour => write!(f, "hour"),
+            Transform::Void => write!(f, "void"),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Bucket counts and truncate widths are divisors
+        let width = |n: &str| match n.parse()? {
+            0 => bail!("Transform {} needs a width greater than 0", s),
+            n => Ok(n),
+        };
+        let transform = match s {
+            "identity" => Transform::Identity,
+            "year" => Transform::Year,
+            "month" => Transform::Month,
+            "day" => Transform::Day,
+            "hour" => Transform::Hour,
+            "void" => Transform::Void,
+            _ if s.starts_with("bucket[") && s.ends_with(']') => Transform::Bucket(width(&s[7..s.len() - 1])?),
+            _ if s.starts_with("truncate[") && s.ends_with(']') => Transform::Truncate(width(&s[9..s.len() - 1])?),
+            _ => bail!("Unknown transform: {}", s),
+        };
+        Ok(transform)
+    }
+}
+
+impl Serialize for Transform {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Transform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_like_the_spec() {
+        // https://iceberg.apache.org/spec/#appendix-b-32-bit-hash-requirements
+        let hash = |value: Literal| murmur3_32(&bucket_bytes(&value).unwrap()) as i32;
+        assert_eq!(hash(Literal::Int(34)), 2017239379);
+        assert_eq!(hash(Literal::Long(34)), 2017239379);
+        assert_eq!(hash(Literal::Decimal(1420)), -500754589);
+        assert_eq!(hash(Literal::Date(17486)), -653330422);
+        assert_eq!(hash(Literal::Timestamp(1510871468000000)), -2047944441);
+        assert_eq!(hash(Literal::String("iceberg".to_string())), 1210000089);
+        assert_eq!(hash(Literal::Uuid(0xf79c3e09_677c_4bbd_a479_3f349cb785e7)), 1488055340);
+        assert_eq!(hash(Literal::Binary(vec![0, 1, 2, 3])), -188683207);
+
+        assert_eq!(
+            Transform::Bucket(16).apply(Some(&Literal::Int(34))).unwrap(),
+            Some(Literal::Int(2017239379 % 16))
+        );
+        assert!(Transform::Bucket(16).apply(Some(&Literal::Double(1.0))).is_err());
+    }
+
+    #[test]
+    fn applies_transforms() {
+        let apply = |transform: Transform, value: Literal| transform.apply(Some(&value)).unwrap().unwrap();
+        assert_eq!(apply(Transform::Truncate(10), Literal::Int(-1)), Literal::Int(-10));
+        assert_eq!(apply(Transform::Truncate(10), Literal::Long(19)), Literal::Long(10));
+        assert_eq!(apply(Transform::Truncate(50), Literal::Decimal(1065)), Literal::Decimal(1050));
+        assert_eq!(
+            apply(Transform::Truncate(3), Literal::String("iceberg".to_string())),
+            Literal::String("ice".to_string())
+        );
+
+        // 2006-06-11 and 2006-06-11T10:30:00
+        let date = Literal::Date(13310);
+        let timestamp = Literal::Timestamp(13310 * MICROS_PER_DAY + 10 * MICROS_PER_HOUR + 1_800_000_000);
+        assert_eq!(apply(Transform::Year, date.clone()), Literal::Int(36));
+        assert_eq!(apply(Transform::Month, date.clone()), Literal::Int(36 * 12 + 5));
+        assert_eq!(apply(Transform::Day, timestamp.clone()), date);
+        assert_eq!(apply(Transform::Hour, timestamp.clone()), Literal::Int(13310 * 24 + 10));
+        assert_eq!(apply(Transform::Year, Literal::Date(-1)), Literal::Int(-1));
+        assert_eq!(Transform::Void.apply(Some(&date)).unwrap(), None);
+        assert_eq!(Transform::Day.apply(None).unwrap(), None);
+
+        let human = |transform: Transform, source: PrimitiveType, value: Literal| {
+            transform.to_human_string(&source, transform.apply(Some(&value)).unwrap().as_ref())
+        };
+        assert_eq!(human(Transform::Year, PrimitiveType::Date, date.clone()), "2006");
+        assert_eq!(human(Transform::Month, PrimitiveType::Date, date.clone()), "2006-06");
+        assert_eq!(human(Transform::Day, PrimitiveType::Timestamp, timestamp.clone()), "2006-06-11");
+        assert_eq!(human(Transform::Hour, PrimitiveType::Timestamp, timestamp.clone()), "2006-06-11-10");
+        assert_eq!(
+            human(Transform::Identity, PrimitiveType::Timestamp, timestamp),
+            "2006-06-11T10:30:00"
+        );
+        let decimal = PrimitiveType::Decimal { precision: 9, scale: 2 };
+        assert_eq!(human(Transform::Identity, decimal, Literal::Decimal(-5)), "-0.05");
+        assert_eq!(Transform::Identity.to_human_string(&PrimitiveType::Int, None), "null");
+    }
+
+    #[test]
+    fn rejects_what_transforms_cannot_represent() {
+        assert_eq!("bucket[16]".parse::<Transform>().unwrap(), Transform::Bucket(16));
+        assert!("bucket[0]".parse::<Transform>().is_err());
+        assert!("truncate[0]".parse::<Transform>().is_err());
+
+        // Dates and hours past the ends of i32 are in the body, not the table
+        let invalid = |transform: Transform, value: Literal| {
+            transform.apply(Some(&value)).unwrap_err().downcast::<InvalidBody>().is_ok()
+        };
+        assert!(invalid(Transform::Year, Literal::Date(i32::MAX)));
+        assert!(invalid(Transform::Day, Literal::Date(i32::MAX)));
+        assert!(invalid(Transform::Hour, Literal::Timestamp(i64::MAX)));
+        let far_date = Some(Literal::Date(i32::MAX));
+        assert_eq!(Transform::Identity.to_human_string(&PrimitiveType::Date, far_date.as_ref()), i32::MAX.to_string());
+    }
+}
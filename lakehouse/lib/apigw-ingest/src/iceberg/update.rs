@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use crate::iceberg::metadata::{Snapshot, TableMetadata};
+
+// Table changes, in the shape the REST catalog spec uses for commits so they can be sent as-is.
+// https://github.com/apache/iceberg/blob/main/open-api/rest-catalog-open-api.yaml
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum TableUpdate {
+    AddSnapshot {
+        snapshot: Snapshot,
+    },
+    SetSnapshotRef {
+        #[serde(rename = "ref-name")]
+        ref_name: String,
+        #[serde(rename = "snapshot-id")]
+        snapshot_id: i64,
+        #[serde(rename = "type")]
+        ref_type: String,
+    },
+    RemoveSnapshots {
+        #[serde(rename = "snapshot-ids")]
+        snapshot_ids: Vec<i64>,
+    },
+}
+
+impl TableUpdate {
+    /// Makes `snapshot_id` the head of the main branch, i.e. the table's current snapshot.
+    pub fn set_main_branch(snapshot_id: i64) -> Self {
+        TableUpdate::SetSnapshotRef {
+            ref_name: "main".to_string(),
+            snapshot_id,
+            ref_type: "branch".to_string(),
+        }
+    }
+}
+
+/// What must still hold for the table when an update is applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TableRequirement {
+    AssertTableUuid {
+        uuid: String,
+    },
+    AssertRefSnapshotId {
+        #[serde(rename = "ref")]
+        ref_name: String,
+        #[serde(rename = "snapshot-id")]
+        snapshot_id: Option<i64>,
+    },
+}
+
+impl TableRequirement {
+    /// The requirements that make an update built on `base` fail if anyone else committed since.
+    pub fn for_base(base: &TableMetadata) -> Vec<TableRequirement> {
+        vec![
+            TableRequirement::AssertTableUuid {
+                uuid: base.table_uuid.clone(),
+            },
+            TableRequirement::AssertRefSnapshotId {
+                ref_name: "main".to_string(),
+                snapshot_id: base.current_snapshot_id,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_like_the_rest_spec() {
+        let update = serde_json::to_value(TableUpdate::set_main_branch(42)).unwrap();
+        assert_eq!(
+            update,
+            serde_json::json!({"action": "set-snapshot-ref", "ref-name": "main", "snapshot-id": 42, "type": "branch"})
+        );
+        let update = serde_json::to_value(TableUpdate::RemoveSnapshots { snapshot_ids: vec![1, 2] }).unwrap();
+        assert_eq!(update, serde_json::json!({"action": "remove-snapshots", "snapshot-ids": [1, 2]}));
+
+        let requirement = serde_json::to_value(TableRequirement::AssertRefSnapshotId {
+            ref_name: "main".to_string(),
+            snapshot_id: None,
+        })
+        .unwrap();
+        assert_eq!(
+            requirement,
+            serde_json::json!({"type": "assert-ref-snapshot-id", "ref": "main", "snapshot-id": null})
+        );
+    }
+}
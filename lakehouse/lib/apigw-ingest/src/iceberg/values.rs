@@ -0,0 +1,114 @@
+// Single values of Iceberg primitive types, used for partition tuples, and the nested values
+// records are made of once they've been read from a request body
+
+use crate::iceberg::schema::PrimitiveType;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Literal {
+    Boolean(bool),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// Days from the unix epoch
+    Date(i32),
+    /// Microseconds from midnight
+    Time(i64),
+    /// Microseconds from the unix epoch, without a zone
+    Timestamp(i64),
+    /// Microseconds from the unix epoch, UTC
+    TimestampTz(i64),
+    String(String),
+    Uuid(u128),
+    Fixed(Vec<u8>),
+    Binary(Vec<u8>),
+    /// Unscaled value, the scale comes from the type
+    Decimal(i128),
+}
+
+impl Literal {
+    /// Single-value binary serialization, used for bounds and partition summaries.
+    /// https://iceberg.apache.org/spec/#binary-single-value-serialization
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Literal::Boolean(v) => vec![*v as u8],
+            Literal::Int(v) | Literal::Date(v) => v.to_le_bytes().to_vec(),
+            Literal::Long(v) | Literal::Time(v) | Literal::Timestamp(v) | Literal::TimestampTz(v) => {
+                v.to_le_bytes().to_vec()
+            }
+            Literal::Float(v) => v.to_le_bytes().to_vec(),
+            Literal::Double(v) => v.to_le_bytes().to_vec(),
+            Literal::String(v) => v.as_bytes().to_vec(),
+            Literal::Uuid(v) => v.to_be_bytes().to_vec(),
+            Literal::Fixed(v) | Literal::Binary(v) => v.clone(),
+            Literal::Decimal(v) => {
+                // minimum number of big-endian two's complement bytes
+                let bytes = v.to_be_bytes();
+                let sign = if *v < 0 { 0xff } else { 0x00 };
+                let mut start = 0;
+                while start < 15 && bytes[start] == sign && (bytes[start + 1] & 0x80) == (sign & 0x80) {
+                    start += 1;
+                }
+                bytes[start..].to_vec()
+            }
+        }
+    }
+
+    /// The value of a `primitive` from its single-value binary serialization, or `None` if the
+    /// bytes don't hold one.
+    pub fn from_bytes(primitive: &PrimitiveType, bytes: &[u8]) -> Option<Literal> {
+        let literal = match primitive {
+            PrimitiveType::Boolean => Literal::Boolean(*bytes.first()? != 0),
+            PrimitiveType::Int => Literal::Int(i32::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Date => Literal::Date(i32::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Long => Literal::Long(i64::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Time => Literal::Time(i64::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Timestamp => Literal::Timestamp(i64::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Timestamptz => Literal::TimestampTz(i64::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Float => Literal::Float(f32::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Double => Literal::Double(f64::from_le_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::String => Literal::String(String::from_utf8(bytes.to_vec()).ok()?),
+            PrimitiveType::Uuid => Literal::Uuid(u128::from_be_bytes(bytes.try_into().ok()?)),
+            PrimitiveType::Fixed(_) => Literal::Fixed(bytes.to_vec()),
+            PrimitiveType::Binary => Literal::Binary(bytes.to_vec()),
+            PrimitiveType::Decimal { .. } => {
+                if bytes.is_empty() || bytes.len() > 16 {
+                    return None;
+                }
+                // sign extend the big-endian two's complement bytes
+                let sign = if bytes[0] & 0x80 != 0 { 0xff } else { 0x00 };
+                let mut extended = [sign; 16];
+                extended[16 - bytes.len()..].copy_from_slice(bytes);
+                Literal::Decimal(i128::from_be_bytes(extended))
+            }
+        };
+        Some(literal)
+    }
+
+    pub fn is_nan(&self) -> bool {
+        match self {
+            Literal::Float(v) => v.is_nan(),
+            Literal::Double(v) => v.is_nan(),
+            _ => false,
+        }
+    }
+}
+
+/// A value of any Iceberg type. Struct values hold their fields in the order of the struct type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Primitive(Literal),
+    Struct(Vec<Value>),
+    List(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+}
+
+/// One row of a table, with a value for every top-level field of the schema, in schema order.
+pub type Record = Vec<Value>;
@@ -0,0 +1,11 @@
+pub mod catalog;
+pub mod commit;
+pub mod compact;
+pub mod delete;
+pub mod error;
+pub mod formats;
+pub mod iceberg;
+pub mod maintenance;
+pub mod storage;
+#[cfg(test)]
+mod testing;
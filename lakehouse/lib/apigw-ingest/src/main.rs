@@ -1,20 +1,28 @@
 use std::env;
-use arrow2::array::Array;
-use arrow2::chunk::Chunk;
-use arrow2::datatypes::{Schema, Field};
+use anyhow::{anyhow, Context as _};
 use async_once::AsyncOnce;
-use arrow2::io::json::read;
-use arrow2::datatypes::DataType::{Int16, Int64, Int8, LargeUtf8, Utf8, Struct};
-use arrow2::io::parquet::write::{CompressionOptions, Encoding, FileSink, to_parquet_schema, transverse, Version, WriteOptions};
-use aws_config::{SdkConfig};
-use aws_sdk_s3::types::ByteStream;
-use futures::SinkExt;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use apigw_ingest::catalog::{self, Catalog, TableIdent};
+use apigw_ingest::commit;
+use apigw_ingest::delete::{self, Deleted};
+use apigw_ingest::error::{self, IngestError};
+use apigw_ingest::formats::encoding::{self, ContentEncoding};
+use apigw_ingest::formats::{Format, InvalidBody, ReadOptions};
+use apigw_ingest::iceberg::deletes::EqualityDeletes;
+use apigw_ingest::iceberg::expression::Predicate;
+use apigw_ingest::iceberg::parquet;
+use apigw_ingest::iceberg::partition::Partition;
+use apigw_ingest::iceberg::manifest::{DataContentType, DataFile, DataFileFormat};
+use apigw_ingest::iceberg::metadata::Snapshot;
+use apigw_ingest::storage::{self, ObjectStore};
 use lambda_http::aws_lambda_events::event;
 use lambda_http::Body;
-use lambda_http::http::{HeaderMap};
+use lambda_http::http::{HeaderMap, HeaderValue, Method};
+use lambda_http::http::header::{ALLOW, CONTENT_ENCODING, CONTENT_TYPE, RETRY_AFTER};
+use serde_json::json;
 use lambda_runtime::{Error, LambdaEvent, run, service_fn};
-use tokio::fs::File;
-use tokio_util::compat::TokioAsyncReadCompatExt;
+use std::sync::Arc;
 use uuid::Uuid;
 // use log::error;
 // use serde_json::Value;
@@ -27,106 +35,247 @@ use crate::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
 extern crate lazy_static;
 
 // HANDLE THE DATA INGESTION
-// 1. Read the incoming JSON (this will be a book reviews schema)
-// 2. Convert the incoming JSON to Parquet
-// 3. Write parquet file to the s3://dotsdb-lakehouse-data/books/data folder
+// 1. Read the incoming JSON, NDJSON, CSV, Avro or Arrow as records of the table's schema
+// 2. Convert the records to Parquet
+// 3. Write parquet files to the table's data folder, e.g. s3://dotsdb-lakehouse-data/books/data,
+//    under hashed prefixes when write.object-storage.enabled is set
 
-// TELL ICEBERG THAT DATA WAS INSERTED PER SPEC - https://iceberg.apache.org/spec/#specification
-// 1. Create a manifest file that references the newly created parquet file
-// 2. Create a snapshot of the manifest file in the metadata and update the metadata.json
-//      - Must also update Glue with the new metadata.json file when this gets updated
-// 3. Write the manifest.avro to the s3 metadata folder
-// 4. Write the metadata.json to the s3 metadata folder
+// TELL ICEBERG THAT DATA WAS INSERTED - see commit.rs
 
+// DELETE requests delete the rows matching a predicate instead - see delete.rs
+
+// Configuration errors are kept rather than panicking, so every request gets a proper 500
 lazy_static! (
-    static ref AWS_CONFIG: AsyncOnce<SdkConfig> = AsyncOnce::new(async { aws_config::load_from_env().await });
-    static ref S3_CLIENT: AsyncOnce<aws_sdk_s3::Client> = AsyncOnce::new(async { aws_sdk_s3::Client::new(AWS_CONFIG.get().await) });
+    static ref STORAGE: AsyncOnce<anyhow::Result<Arc<dyn ObjectStore>>> = AsyncOnce::new(async { storage::from_env().await });
+    static ref CATALOG: AsyncOnce<anyhow::Result<Box<dyn Catalog>>> = AsyncOnce::new(async {
+        let storage = STORAGE.get().await.as_ref().map_err(|err| anyhow!("Invalid storage configuration: {:#}", err))?;
+        catalog::from_env(storage.clone()).await
+    });
 );
 
 
-async fn write_chunk(path: &str, schema: Schema, chunk: Chunk<Box<dyn Array>>) -> Result<(), anyhow::Error> {
-    let options = WriteOptions {
-        write_statistics: false,
-        compression: CompressionOptions::Uncompressed,
-        version: Version::V2
+pub async fn function_handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    match table_context().await {
+        Ok(context) => Ok(handle_request(&context, event.payload).await),
+        Err(err) => Ok(error_response(err)),
+    }
+}
+
+/// Ingests into or deletes from the table of `context`, depending on the request's method.
+async fn handle_request(context: &TableContext<'_>, request: ApiGatewayProxyRequest) -> ApiGatewayProxyResponse {
+    let result = match request.http_method {
+        Method::POST => ingest(context, request).await.map(|snapshot| json!({ "message": "Success", "snapshot_id": snapshot.snapshot_id })),
+        Method::DELETE => delete(context, request).await.map(|deleted| {
+            let snapshot_id = deleted.snapshot.map(|snapshot| snapshot.snapshot_id);
+            json!({ "message": "Success", "snapshot_id": snapshot_id, "deleted_rows": deleted.deleted_rows })
+        }),
+        method => Err(IngestError::MethodNotAllowed(method.to_string())),
     };
+    match result {
+        Ok(body) => json_response(200, body),
+        Err(err) => error_response(err),
+    }
+}
 
-    let mut stream = futures::stream::iter(vec![Ok(chunk)].into_iter());
+fn error_response(err: IngestError) -> ApiGatewayProxyResponse {
+    if err.is_client_error() {
+        log::warn!("Rejected request: {}", err);
+    } else {
+        log::error!("Failed to handle request: {}", err);
+    }
+    let mut response = json_response(err.status_code().into(), err.to_json());
+    if let Some(retry_after) = err.retry_after() {
+        response.headers.insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    }
+    if let IngestError::MethodNotAllowed(_) = err {
+        response.headers.insert(ALLOW, HeaderValue::from_static("POST, DELETE"));
+    }
+    response
+}
 
-    let encodings: Vec<Vec<Encoding>> = schema
-        .fields
-        .iter()
-        .map(|f| transverse(&f.data_type, |_| Encoding::Plain))
-        .collect();
+/// How the records of a request are committed, from its `mode` query parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Added to the table as they are
+    Append,
+    /// Replace the rows with the same identifier fields, through equality deletes
+    Upsert,
+}
 
-    let file = File::create(path).await?.compat();
+/// Storage and catalog failures worth retrying, such as timeouts and 5xx responses, are temporary.
+/// Anything else, like a missing table or denied access, stays internal, and so do failures of
+/// the catalog while committing, which the commit reports as [`IngestError::CommitStateUnknown`].
+fn unavailable(err: anyhow::Error) -> IngestError {
+    match IngestError::from(err) {
+        IngestError::Internal(err) if error::is_transient(&err) => IngestError::Unavailable(err),
+        other => other,
+    }
+}
 
-    let mut sink = FileSink::try_new(file, schema, encodings, options)?;
-    sink.send_all(&mut stream).await?;
-    sink.close().await?;
+/// Partitioning and sorting fail on the table's metadata, like a transform that doesn't fit its
+/// source field, or on a value of the body that the transform can't represent.
+fn misconfigured(err: anyhow::Error) -> IngestError {
+    match err.downcast::<InvalidBody>() {
+        Ok(invalid) => IngestError::InvalidBody(invalid),
+        Err(err) => IngestError::Misconfigured(err),
+    }
+}
 
-    Ok(())
+/// The table requests go to, from `DOTSDB_NAMESPACE` and `DOTSDB_TABLE`, with the catalog and
+/// storage it's in and the limits on request bodies.
+struct TableContext<'a> {
+    table: TableIdent,
+    options: ReadOptions,
+    catalog: &'a dyn Catalog,
+    storage: &'a dyn ObjectStore,
 }
 
-pub async fn function_handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+async fn table_context() -> Result<TableContext<'static>, IngestError> {
+    let namespace = env::var("DOTSDB_NAMESPACE").context("DOTSDB_NAMESPACE is not set").map_err(IngestError::Misconfigured)?;
+    let name = env::var("DOTSDB_TABLE").context("DOTSDB_TABLE is not set").map_err(IngestError::Misconfigured)?;
+    let options = ReadOptions::from_env().map_err(IngestError::Misconfigured)?;
+    let catalog = CATALOG.get().await.as_ref().map_err(|err| IngestError::Misconfigured(anyhow!("{:#}", err)))?.as_ref();
+    let storage = STORAGE.get().await.as_ref().map_err(|err| IngestError::Misconfigured(anyhow!("{:#}", err)))?.as_ref();
+    Ok(TableContext { table: TableIdent::new(namespace, name), options, catalog, storage })
+}
 
-    let write_file_path = "/tmp/test.parquet";
-
-    // In the real world, Field and Schema will be generated by some sort of config as code, depending on the event source
-    let book_review_field = Field::new(
-        "book_review",
-        Struct(vec![
-            Field::new("marketplace", Utf8, true),
-            Field::new("customer_id", Utf8, true),
-            Field::new("review_id", Utf8, true),
-            Field::new("product_id", Utf8, true),
-            Field::new("product_parent", Utf8, true),
-            Field::new("product_title", Utf8, true),
-            Field::new("star_rating", Int8, true),
-            Field::new("helpful_votes", Int64, true),
-            Field::new("total_votes", Int64, true),
-            Field::new("vine", Utf8, true),
-            Field::new("verified_purchase", Utf8, true),
-            Field::new("review_headline", Utf8, true),
-            Field::new("review_body", LargeUtf8, true),
-            Field::new("review_date", Utf8, true),
-            Field::new("year", Int16, true),
-        ]),
-        true
-    );
-
-    let book_review_schema = Schema::from(vec![
-        book_review_field
-    ]);
-
-    let body = event.payload.body.unwrap_or_else(|| "".to_string());
-    let json_bytes = read::json_deserializer::parse(body.as_bytes()).unwrap();
-    let data_type = read::infer(&json_bytes)?;
-    let data = read::deserialize(&json_bytes, data_type).unwrap();
-    let chunk = Chunk::new(vec![data]);
-
-    write_chunk(write_file_path, book_review_schema, chunk).await.unwrap();
-
-    let body = ByteStream::from_path(write_file_path).await.expect("File not found.");
-    let s3 = S3_CLIENT.get().await;
-    s3
-        .put_object()
-        .bucket(env::var("DOTSDB_DATA_BUCKET").unwrap().to_string())
-        .key(Uuid::new_v4().to_string() + ".parquet") // "books/data/file.snappy.parquet" is ultimate goal
-        .body(body)
-        .send()
-        .await?;
-
-
-    let resp = ApiGatewayProxyResponse {
-        status_code: 200,
-        body: Option::from(Body::Text("Success".to_string())),
-        is_base64_encoded: Option::from(false),
-        headers: HeaderMap::new(),
-        multi_value_headers: HeaderMap::new()
+/// The bytes of the request body, which must be at most `DOTSDB_MAX_BODY_BYTES` as sent.
+fn decode_body(request: &ApiGatewayProxyRequest, options: &ReadOptions) -> Result<Vec<u8>, IngestError> {
+    let body = request.body.as_deref().unwrap_or_default();
+    if body.len() > options.max_body_bytes {
+        return Err(IngestError::PayloadTooLarge { limit: options.max_body_bytes });
+    }
+    // API Gateway base64 encodes binary bodies, such as Avro and Arrow
+    if request.is_base64_encoded.unwrap_or(false) {
+        Ok(BASE64.decode(body).map_err(|err| InvalidBody::new(format!("Request body is not valid base64: {}", err)))?)
+    } else {
+        Ok(body.as_bytes().to_vec())
+    }
+}
+
+async fn ingest(context: &TableContext<'_>, request: ApiGatewayProxyRequest) -> Result<Snapshot, IngestError> {
+    let &TableContext { ref table, ref options, catalog, storage } = context;
+
+    let mode = match request.query_string_parameters.first("mode") {
+        None | Some("append") => Mode::Append,
+        Some("upsert") => Mode::Upsert,
+        Some(mode) => return Err(IngestError::InvalidRequest(format!("Unsupported mode {}", mode))),
     };
 
-    Ok(resp)
+    let body = decode_body(&request, options)?;
+    let content_encoding = request.headers.get(CONTENT_ENCODING).and_then(|value| value.to_str().ok());
+    let encodings = ContentEncoding::parse_header(content_encoding).map_err(IngestError::UnsupportedEncoding)?;
+    let body = encoding::decode_body(&encodings, body, options.max_decompressed_bytes)?;
+
+    let content_type = request.headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    let format = Format::detect(content_type, &body)
+        .ok_or_else(|| IngestError::UnsupportedMediaType(content_type.unwrap_or_default().to_string()))?;
+
+    let base = catalog.load_table(table).await.map_err(unavailable)?;
+
+    // The records are read and written with the table's own schema, so every Parquet column
+    // is a table column with the same field id
+    let table_schema = base.metadata.current_schema()?;
+    let chunk = format.read_chunk(table_schema, &body, options)?;
+    if chunk.is_empty() {
+        return Err(InvalidBody::new("Request body has no records").into());
+    }
+    let properties = parquet::WriteProperties::from_properties(&base.metadata.properties);
+
+    let spec = base.metadata.default_spec().map_err(IngestError::Misconfigured)?;
+
+    // Upserts keep the last version of every row in the request, and delete older versions with
+    // an equality delete file on the identifier fields next to every data file. The deletes only
+    // apply within a partition, so the table must be partitioned by identifier fields alone.
+    let deletes = match mode {
+        Mode::Append => None,
+        Mode::Upsert => Some(
+            EqualityDeletes::identifier(table_schema)
+                .and_then(|deletes| deletes.check_partitioning(table_schema, spec).map(|_| deletes))
+                .map_err(|err| IngestError::InvalidRequest(format!("Can't upsert into the table: {}", err)))?,
+        ),
+    };
+    let chunk = match &deletes {
+        Some(deletes) => deletes.latest_rows(table_schema, chunk).map_err(IngestError::Misconfigured)?,
+        None => chunk,
+    };
+
+    // Parquet files per partition, sorted by the table's sort order and rolled over at the
+    // table's target file size, each described in the manifest of a new snapshot
+    let partitions = spec.split_chunk(table_schema, chunk).map_err(misconfigured)?;
+    let sort_order = base.metadata.default_sort_order().map_err(IngestError::Misconfigured)?;
+    let locations = base.metadata.location_provider();
+    let mut data_files = vec![];
+    let mut delete_files = vec![];
+    for Partition { values, chunk } in partitions {
+        let chunk = sort_order.sort_chunk(table_schema, chunk).map_err(misconfigured)?;
+        let partition_path = spec.partition_path(table_schema, &values).map_err(IngestError::Misconfigured)?;
+        if let Some(deletes) = &deletes {
+            let keys = deletes.project(table_schema, &chunk).map_err(IngestError::Misconfigured)?;
+            let next_location = || locations.new_data_location(&partition_path, &format!("{}-deletes.parquet", Uuid::new_v4()));
+            let written = parquet::write_chunk_files(storage, next_location, deletes.schema(), &properties, keys).await.map_err(unavailable)?;
+            for written in written {
+                let mut delete_file = DataFile::new(
+                    DataContentType::EqualityDeletes,
+                    written.location,
+                    DataFileFormat::Parquet,
+                    values.clone(),
+                    written.record_count as i64,
+                    written.file_size_in_bytes as i64,
+                )
+                .with_metrics(written.metrics);
+                delete_file.equality_ids = Some(deletes.equality_ids().to_vec());
+                delete_files.push(delete_file);
+            }
+        }
+        let next_location = || locations.new_data_location(&partition_path, &format!("{}.parquet", Uuid::new_v4()));
+        let written = parquet::write_chunk_files(storage, next_location, table_schema, &properties, chunk).await.map_err(unavailable)?;
+        for written in written {
+            let mut data_file = DataFile::new(
+                DataContentType::Data,
+                written.location,
+                DataFileFormat::Parquet,
+                values.clone(),
+                written.record_count as i64,
+                written.file_size_in_bytes as i64,
+            )
+            .with_metrics(written.metrics);
+            data_file.sort_order_id = (!sort_order.is_unsorted()).then_some(sort_order.order_id);
+            data_files.push(data_file);
+        }
+    }
+    match deletes {
+        Some(_) => commit::row_delta(storage, catalog, table, base, data_files, delete_files, vec![]).await.map_err(unavailable),
+        None => commit::append_files(storage, catalog, table, base, data_files).await.map_err(unavailable),
+    }
+}
+
+/// Deletes the rows matching the predicate of a body like `{"predicate": "customer_id = '10822695'"}`.
+async fn delete(context: &TableContext<'_>, request: ApiGatewayProxyRequest) -> Result<Deleted, IngestError> {
+    let &TableContext { ref table, ref options, catalog, storage } = context;
+
+    let body = decode_body(&request, options)?;
+    let predicate = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|body| body["predicate"].as_str().map(str::to_string))
+        .ok_or_else(|| IngestError::InvalidRequest(r#"Expected a body like {"predicate": "customer_id = '10822695'"}"#.to_string()))?;
+
+    let base = catalog.load_table(table).await.map_err(unavailable)?;
+    let predicate = Predicate::parse(base.metadata.current_schema()?, &predicate)
+        .map_err(|err| IngestError::InvalidRequest(format!("Invalid predicate: {:#}", err)))?;
+    delete::delete_rows(storage, catalog, table, base, &predicate).await.map_err(unavailable)
+}
+
+fn json_response(status_code: i64, body: serde_json::Value) -> ApiGatewayProxyResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    ApiGatewayProxyResponse {
+        status_code,
+        body: Option::from(Body::Text(body.to_string())),
+        is_base64_encoded: Option::from(false),
+        headers,
+        multi_value_headers: HeaderMap::new()
+    }
 }
 
 
@@ -145,13 +294,137 @@ async fn main() -> Result<(), Error> {
 
 #[cfg(test)]
 mod tests {
-    use lambda_http::http::header::{CONTENT_TYPE, HOST};
+    use std::collections::HashMap;
+    use apigw_ingest::catalog::TableCreation;
+    use apigw_ingest::catalog::filesystem::FileSystemCatalog;
+    use apigw_ingest::iceberg::arrow;
+    use apigw_ingest::iceberg::partition::{PartitionField, PartitionSpec};
+    use apigw_ingest::iceberg::transform::Transform;
+    use apigw_ingest::iceberg::schema::{NestedField, PrimitiveType, Schema as IcebergSchema, Type};
+    use apigw_ingest::iceberg::sort::SortOrder;
+    use apigw_ingest::storage::local::LocalStore;
+    use std::path::PathBuf;
+    use lambda_http::http::header::HOST;
     use super::*;
     use lambda_runtime::{Context, LambdaEvent};
 
+    // The books table as Handler.kt creates it
+    fn books_table() -> TableCreation {
+        let string = || Type::Primitive(PrimitiveType::String);
+        let int = || Type::Primitive(PrimitiveType::Int);
+        let mut schema = IcebergSchema::new(0, vec![
+            NestedField::optional(1, "marketplace", string()),
+            NestedField::optional(2, "customer_id", string()),
+            NestedField::required(3, "review_id", string()),
+            NestedField::optional(4, "product_id", string()),
+            NestedField::optional(5, "product_parent", string()),
+            NestedField::optional(6, "product_title", string()),
+            NestedField::optional(7, "star_rating", int()),
+            NestedField::optional(8, "helpful_votes", int()),
+            NestedField::optional(9, "total_votes", int()),
+            NestedField::optional(10, "vine", string()),
+            NestedField::optional(11, "verified_purchase", string()),
+            NestedField::optional(12, "review_headline", string()),
+            NestedField::optional(13, "review_body", string()),
+            NestedField::optional(14, "review_date", Type::Primitive(PrimitiveType::Date)),
+            NestedField::optional(15, "year", int()),
+        ]);
+        schema.identifier_field_ids = Some(vec![3]);
+        TableCreation {
+            schema,
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::new(),
+        }
+    }
+
+    /// A table in a warehouse of its own, that requests are sent to without the environment.
+    struct TestTable {
+        warehouse: PathBuf,
+        catalog: FileSystemCatalog,
+        table: TableIdent,
+    }
+
+    impl TestTable {
+        async fn create(creation: TableCreation) -> Self {
+            let warehouse = env::temp_dir().join(format!("dotsdb-{}", Uuid::new_v4()));
+            let catalog = FileSystemCatalog::new(&warehouse);
+            let table = TableIdent::new("dotsdb", "books");
+            catalog.create_table(&table, creation).await.unwrap();
+            TestTable { warehouse, catalog, table }
+        }
+
+        async fn respond(&self, request: ApiGatewayProxyRequest) -> ApiGatewayProxyResponse {
+            let context = TableContext { table: self.table.clone(), options: ReadOptions::default(), catalog: &self.catalog, storage: &LocalStore };
+            handle_request(&context, request).await
+        }
+
+        /// The status code and JSON body of the response to `request`.
+        async fn send(&self, request: ApiGatewayProxyRequest) -> (i64, serde_json::Value) {
+            let response = self.respond(request).await;
+            (response.status_code, serde_json::from_slice(&response.body.unwrap()).unwrap())
+        }
+
+        async fn current_snapshot(&self) -> Option<Snapshot> {
+            self.catalog.load_table(&self.table).await.unwrap().metadata.current_snapshot().cloned()
+        }
+    }
+
+    impl Drop for TestTable {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.warehouse);
+        }
+    }
+
+    fn ndjson(body: &str) -> ApiGatewayProxyRequest {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "application/x-ndjson".parse().unwrap());
+        ApiGatewayProxyRequest { http_method: Method::POST, headers, body: Some(body.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn only_transient_failures_are_unavailable() {
+        let timeout = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::TimedOut)).context("Failed to write a.parquet");
+        assert_eq!(unavailable(timeout).status_code(), 503);
+        let conflict = anyhow::Error::new(catalog::CommitConflict("books".to_string()));
+        assert_eq!(unavailable(conflict).status_code(), 503);
+        assert_eq!(unavailable(anyhow!("Glue table dotsdb.books not found")).status_code(), 500);
+
+        // The records may be in the table already, so the client isn't asked to send them again
+        let timeout = anyhow::Error::new(error::Transient("UpdateTable timed out".into()));
+        let unknown = unavailable(anyhow::Error::new(catalog::CommitStateUnknown(timeout)));
+        assert_eq!((unknown.status_code(), unknown.retry_after()), (500, None));
+    }
+
+    #[test]
+    fn decodes_bodies_up_to_the_limit() {
+        let options = ReadOptions { max_body_bytes: 16, ..Default::default() };
+        let request = |method: Method, body: &str, is_base64_encoded: bool| ApiGatewayProxyRequest {
+            http_method: method,
+            body: Some(body.to_string()),
+            is_base64_encoded: Some(is_base64_encoded),
+            ..Default::default()
+        };
+        assert_eq!(decode_body(&request(Method::POST, "[]", false), &options).unwrap(), b"[]");
+        assert_eq!(decode_body(&request(Method::POST, &BASE64.encode("[]"), true), &options).unwrap(), b"[]");
+        assert_eq!(decode_body(&request(Method::POST, "not base64!", true), &options).unwrap_err().status_code(), 400);
+        // Deletes are held to the same limit as ingests
+        let predicate = json!({ "predicate": "customer_id = '10822695'" }).to_string();
+        assert_eq!(decode_body(&request(Method::DELETE, &predicate, false), &options).unwrap_err().status_code(), 413);
+    }
+
     #[tokio::test]
     async fn test_func() {
-        env::set_var("DOTSDB_DATA_BUCKET", "dotsdb-lakehouse-data");
+        // Run against a filesystem catalog and local storage, so no AWS account is needed
+        let warehouse = env::temp_dir().join(format!("dotsdb-{}", Uuid::new_v4()));
+        env::set_var("DOTSDB_CATALOG", "filesystem");
+        env::set_var("DOTSDB_STORAGE", "local");
+        env::set_var("DOTSDB_WAREHOUSE", &warehouse);
+        env::set_var("DOTSDB_NAMESPACE", "dotsdb");
+        env::set_var("DOTSDB_TABLE", "books");
+        let table = TableIdent::new("dotsdb", "books");
+        FileSystemCatalog::new(&warehouse).create_table(&table, books_table()).await.unwrap();
         let context = Context::default();
 
         let mut headers = HeaderMap::new();
@@ -159,9 +432,12 @@ mod tests {
         headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
 
         // Example data: https://github.com/awslabs/aws-lambda-rust-runtime/blob/f8706e332ee1732284c9b51c816df99d264bd39e/lambda-http/tests/data/apigw_proxy_request.json
-        let mut apigw_v2 = ApiGatewayProxyRequest::default();
-        apigw_v2.headers = headers;
-        apigw_v2.body = Option::from("[\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    }\n]".to_string());
+        let apigw_v2 = ApiGatewayProxyRequest {
+            http_method: Method::POST,
+            headers,
+            body: Option::from("[\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    },\n    {\n        \"marketplace\": \"US\",\n        \"customer_id\": \"10822695\",\n        \"review_id\": \"R2RRIALQ1UBYO8\",\n        \"product_id\": \"0385418493\",\n        \"product_parent\": \"610658517\",\n        \"product_title\": \"How the Irish Saved Civilization: The Untold Story of Ireland's Heroic Role From the Fall of Rome to the Rise of Medieval Europe (The Hinges of History)\",\n        \"star_rating\": 1,\n        \"helpful_votes\": 153,\n        \"total_votes\": 169,\n        \"vine\": \"N\",\n        \"verified_purchase\": \"N\",\n        \"review_headline\": \"Total Rubbish.\",\n        \"review_body\": \"The last reviwer is a bit daft. In some 70 years of reading History I have never read such lies, distortions, and incoherent gibberish. The author is CLEARLY appealing to ethnic sentiment over \\\"EVIDENCE AND FACTS.\\\" I suggest readers read the dozen or so \\\"Most Helpful Reviews.\\\" Those reviewers were very in depth and know their SUBJECT.\",\n        \"review_date\": \"2006-06-11\",\n        \"year\": 2006\n    }\n]".to_string()),
+            ..Default::default()
+        };
 
         let request = LambdaEvent::new(apigw_v2, context);
         let response = function_handler(request);
@@ -169,9 +445,134 @@ mod tests {
 
         let into_response = response.await;
         let body = into_response.unwrap().body.unwrap().to_vec();
-        let utf8_body = String::from_utf8(body).unwrap();
+        let json_body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!("Success", json_body["message"]);
+        let loaded = FileSystemCatalog::new(&warehouse).load_table(&table).await.unwrap();
+        let snapshot = loaded.metadata.current_snapshot().unwrap();
+        assert_eq!(Some(snapshot.snapshot_id), json_body["snapshot_id"].as_i64());
+        assert_eq!("15", snapshot.summary.properties["added-records"]);
+
+        // The Parquet columns are the table's columns
+        let data_dir = loaded.metadata.data_dir();
+        let data_file = std::fs::read_dir(&data_dir).unwrap().next().unwrap().unwrap().path();
+        let metadata = arrow2::io::parquet::read::read_metadata(&mut std::fs::File::open(data_file).unwrap()).unwrap();
+        let parquet_schema = arrow2::io::parquet::read::infer_schema(&metadata).unwrap();
+        let table_schema = loaded.metadata.current_schema().unwrap();
+        assert_eq!(
+            parquet_schema.fields.iter().map(|f| (f.name.as_str(), f.data_type.clone())).collect::<Vec<_>>(),
+            arrow::schema_to_arrow(table_schema).fields.iter().map(|f| (f.name.as_str(), f.data_type.clone())).collect::<Vec<_>>()
+        );
+
+        std::fs::remove_dir_all(warehouse).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_committing() {
+        let books = TestTable::create(books_table()).await;
+
+        // A body that doesn't match the schema is rejected with every problem in it
+        let invalid = ApiGatewayProxyRequest {
+            http_method: Method::POST,
+            body: Some(r#"[{"review_id": "R1", "star_rating": "five"}, {"review_date": "11/06/2006"}]"#.to_string()),
+            ..Default::default()
+        };
+        let (status_code, json_body) = books.send(invalid).await;
+        assert_eq!(status_code, 400);
+        let fields: Vec<_> = json_body["errors"].as_array().unwrap().iter().map(|e| (e["record"].as_u64().unwrap(), e["field"].as_str().unwrap())).collect();
+        assert_eq!(fields, [(0, "star_rating"), (1, "review_id"), (1, "review_date")]);
 
-        assert_eq!("Success", utf8_body);
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "application/xml".parse().unwrap());
+        let xml = ApiGatewayProxyRequest { http_method: Method::POST, headers, body: Some("<review/>".to_string()), ..Default::default() };
+        assert_eq!(books.send(xml).await.0, 415);
+
+        // Only POST ingests, so a GET with a body doesn't write anything
+        let mut get = ndjson("{\"review_id\": \"R1\"}\n");
+        get.http_method = Method::GET;
+        let response = books.respond(get).await;
+        assert_eq!((response.status_code, response.headers.get(ALLOW)), (405, Some(&HeaderValue::from_static("POST, DELETE"))));
+
+        // An empty body would commit an empty snapshot, or an empty delete file when upserting
+        let mut empty = ndjson("\n");
+        empty.query_string_parameters = HashMap::from([("mode".to_string(), "upsert".to_string())]).into();
+        let (status_code, json_body) = books.send(empty).await;
+        assert_eq!((status_code, json_body["message"].as_str()), (400, Some("Request body has no records")));
+
+        let mut merge = ndjson("{\"review_id\": \"R1\"}\n");
+        merge.query_string_parameters = HashMap::from([("mode".to_string(), "merge".to_string())]).into();
+        assert_eq!(books.send(merge).await.0, 400);
+
+        assert_eq!(books.current_snapshot().await, None);
     }
 
+    #[tokio::test]
+    async fn upserts_keep_the_last_version_of_every_row() {
+        let books = TestTable::create(books_table()).await;
+
+        let mut upsert = ndjson("{\"review_id\": \"R1\", \"star_rating\": 1}\n{\"review_id\": \"R7\"}\n{\"review_id\": \"R1\", \"star_rating\": 5}\n");
+        upsert.query_string_parameters = HashMap::from([("mode".to_string(), "upsert".to_string())]).into();
+        let (status_code, json_body) = books.send(upsert).await;
+        assert_eq!(status_code, 200);
+        let snapshot = books.current_snapshot().await.unwrap();
+        assert_eq!(Some(snapshot.snapshot_id), json_body["snapshot_id"].as_i64());
+        let summary = &snapshot.summary;
+        assert_eq!(summary.operation, apigw_ingest::iceberg::metadata::Operation::Overwrite);
+        assert_eq!(
+            ("2", "1", "2"),
+            (summary.properties["added-records"].as_str(), summary.properties["added-delete-files"].as_str(), summary.properties["added-equality-deletes"].as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn deletes_the_rows_matching_a_predicate() {
+        let books = TestTable::create(books_table()).await;
+        let reviews = ndjson("{\"review_id\": \"R1\", \"customer_id\": \"10822695\"}\n{\"review_id\": \"R2\", \"customer_id\": \"10822695\"}\n{\"review_id\": \"R3\", \"customer_id\": \"50486344\"}\n");
+        assert_eq!(books.send(reviews).await.0, 200);
+
+        // DELETE requests delete the rows matching a predicate with position deletes
+        let delete = |predicate: &str| ApiGatewayProxyRequest {
+            http_method: Method::DELETE,
+            body: Some(json!({ "predicate": predicate }).to_string()),
+            ..Default::default()
+        };
+        let (status_code, json_body) = books.send(delete("customer_id = '10822695'")).await;
+        assert_eq!((status_code, json_body["deleted_rows"].as_u64()), (200, Some(2)));
+        let snapshot = books.current_snapshot().await.unwrap();
+        assert_eq!(Some(snapshot.snapshot_id), json_body["snapshot_id"].as_i64());
+        assert_eq!("2", snapshot.summary.properties["added-position-deletes"]);
+
+        let (_, json_body) = books.send(delete("customer_id = '10822695'")).await;
+        assert_eq!((json_body["deleted_rows"].as_u64(), json_body["snapshot_id"].is_null()), (Some(0), true));
+        assert_eq!(books.send(delete("customer = '10822695'")).await.0, 400);
+    }
+
+    #[tokio::test]
+    async fn writes_a_data_file_per_partition() {
+        let mut creation = books_table();
+        creation.partition_spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![PartitionField { source_id: 14, field_id: 1000, name: "review_date_day".to_string(), transform: Transform::Day }],
+        };
+        let books_by_day = TestTable::create(creation).await;
+
+        // A partitioned table gets a data file per partition, under the partition's path
+        let reviews = "{\"review_id\": \"R1\", \"review_date\": \"2006-06-11\"}\n{\"review_id\": \"R2\"}\n{\"review_id\": \"R3\", \"review_date\": \"2006-06-11\"}\n";
+        assert_eq!(books_by_day.send(ndjson(reviews)).await.0, 200);
+        let summary = books_by_day.current_snapshot().await.unwrap().summary.properties;
+        assert_eq!(("2", "2"), (summary["added-data-files"].as_str(), summary["changed-partition-count"].as_str()));
+        let loaded = books_by_day.catalog.load_table(&books_by_day.table).await.unwrap();
+        let mut partitions = std::fs::read_dir(loaded.metadata.data_dir()).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect::<Vec<_>>();
+        partitions.sort();
+        assert_eq!(partitions, ["review_date_day=2006-06-11", "review_date_day=null"]);
+
+        // A review whose date changed would keep its old version in the old partition
+        let mut upsert = ndjson(reviews);
+        upsert.query_string_parameters = HashMap::from([("mode".to_string(), "upsert".to_string())]).into();
+        assert_eq!(books_by_day.send(upsert).await.0, 400);
+
+        // A date the day transform can't represent is a problem of the body, not of the table
+        let (status_code, json_body) = books_by_day.send(ndjson("{\"review_id\": \"R4\", \"review_date\": 2147483647}\n")).await;
+        assert_eq!((status_code, json_body["message"].as_str()), (400, Some("Date 2147483647 is out of range")));
+    }
 }
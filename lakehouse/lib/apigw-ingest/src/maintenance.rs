@@ -0,0 +1,487 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use log::warn;
+
+use crate::catalog::{Catalog, CommitConflict, LoadedTable, TableIdent};
+use crate::commit::RetryPolicy;
+use crate::iceberg::location::LocationProvider;
+use crate::iceberg::manifest::ManifestStatus;
+use crate::iceberg::manifest_list::{read_manifest_list, ManifestFile};
+use crate::iceberg::metadata::TableMetadata;
+use crate::iceberg::scan::read_manifest_entries;
+use crate::iceberg::update::TableUpdate;
+use crate::storage::ObjectStore;
+
+// KEEP THE TABLE'S METADATA FROM GROWING WITHOUT BOUND - run on a schedule by bin/maintenance.rs
+// Every ingest adds a snapshot, a manifest list, a manifest and a metadata file.
+// 1. Expire the snapshots older than history.expire.max-snapshot-age-ms, apart from the last
+//    history.expire.min-snapshots-to-keep of the main branch and those a branch or tag points at
+// 2. Delete the manifest lists, manifests, data and delete files only expired snapshots used
+// 3. Delete the files under the table's location that no metadata references, such as metadata
+//    files that dropped out of the metadata log, or files of failed writes. Only files older than
+//    any commit in progress are touched, and never those of other tables sharing a data path.
+
+/// How many objects are deleted at the same time.
+const DELETE_CONCURRENCY: usize = 16;
+
+/// Which snapshots to expire, configured with the same `history.expire.*` table properties as
+/// the Java implementation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpirePolicy {
+    pub max_snapshot_age: Duration,
+    pub min_snapshots_to_keep: usize,
+}
+
+impl Default for ExpirePolicy {
+    fn default() -> Self {
+        ExpirePolicy {
+            max_snapshot_age: Duration::from_secs(5 * 24 * 60 * 60),
+            min_snapshots_to_keep: 1,
+        }
+    }
+}
+
+impl ExpirePolicy {
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let default = ExpirePolicy::default();
+        let property = |key: &str| properties.get(key).and_then(|v| v.parse::<u64>().ok());
+        ExpirePolicy {
+            max_snapshot_age: property("history.expire.max-snapshot-age-ms")
+                .map_or(default.max_snapshot_age, Duration::from_millis),
+            min_snapshots_to_keep: property("history.expire.min-snapshots-to-keep")
+                .map_or(default.min_snapshots_to_keep, |v| v as usize),
+        }
+    }
+
+    /// The ids of the snapshots of `metadata` that have expired at `now_ms`.
+    pub fn expired_snapshot_ids(&self, metadata: &TableMetadata, now_ms: i64) -> Vec<i64> {
+        let mut kept = metadata.refs.values().map(|r| r.snapshot_id).collect::<HashSet<_>>();
+        kept.extend(metadata.current_snapshot_id);
+        let mut ancestor = metadata.current_snapshot();
+        for _ in 0..self.min_snapshots_to_keep {
+            let Some(snapshot) = ancestor else { break };
+            kept.insert(snapshot.snapshot_id);
+            ancestor = snapshot
+                .parent_snapshot_id
+                .and_then(|parent| metadata.snapshots.iter().find(|s| s.snapshot_id == parent));
+        }
+        let expire_before_ms = now_ms - self.max_snapshot_age.as_millis() as i64;
+        metadata
+            .snapshots
+            .iter()
+            .filter(|s| s.timestamp_ms < expire_before_ms && !kept.contains(&s.snapshot_id))
+            .map(|s| s.snapshot_id)
+            .collect()
+    }
+}
+
+/// What a maintenance run removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Removed {
+    pub snapshot_ids: Vec<i64>,
+    /// The locations of the deleted files
+    pub files: Vec<String>,
+}
+
+/// Removes the snapshots that have expired at `now_ms` by the table's `history.expire.*`
+/// properties, then deletes the files only they referenced.
+pub async fn expire_snapshots(
+    storage: &dyn ObjectStore,
+    catalog: &dyn Catalog,
+    table: &TableIdent,
+    mut base: LoadedTable,
+    now_ms: i64,
+) -> anyhow::Result<Removed> {
+    let retry_policy = RetryPolicy::from_properties(&base.metadata.properties);
+    let mut attempt = 1;
+    let snapshot_ids = loop {
+        let snapshot_ids =
+            ExpirePolicy::from_properties(&base.metadata.properties).expired_snapshot_ids(&base.metadata, now_ms);
+        if snapshot_ids.is_empty() {
+            return Ok(Removed::default());
+        }
+        let updates = vec![TableUpdate::RemoveSnapshots {
+            snapshot_ids: snapshot_ids.clone(),
+        }];
+        match catalog.commit_table(table, &base, updates).await {
+            Ok(_) => break snapshot_ids,
+            Err(err) if err.is::<CommitConflict>() && attempt <= retry_policy.num_retries => {
+                let wait = retry_policy.backoff(attempt);
+                warn!("{}, retrying in {:?}", err, wait);
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+                base = catalog.load_table(table).await?;
+            }
+            Err(err) => return Err(err),
+        }
+    };
+
+    // Snapshots committed since only add to the manifests of retained snapshots
+    let files = unreachable_files(storage, &base.metadata, &snapshot_ids).await?;
+    let files = delete_files(storage, files).await;
+    Ok(Removed { snapshot_ids, files })
+}
+
+/// The manifest lists of the `expired` snapshots of `metadata`, and the manifests and files
+/// in them that none of the other snapshots reference.
+async fn unreachable_files(
+    storage: &dyn ObjectStore,
+    metadata: &TableMetadata,
+    expired: &[i64],
+) -> anyhow::Result<Vec<String>> {
+    let (expired, retained): (Vec<_>, Vec<_>) = metadata
+        .snapshots
+        .iter()
+        .partition(|snapshot| expired.contains(&snapshot.snapshot_id));
+
+    // Manifests of retained snapshots have to be read, so not being able to is an error, while
+    // the manifest lists of expired snapshots may be gone after an earlier, failed cleanup
+    let mut retained_manifests = HashMap::new();
+    for snapshot in retained {
+        for manifest in read_manifest_list(&storage.get(&snapshot.manifest_list).await?)? {
+            retained_manifests.insert(manifest.manifest_path.clone(), manifest);
+        }
+    }
+    let mut files = vec![];
+    let mut expired_manifests = HashMap::new();
+    for snapshot in expired {
+        let manifests = match storage.get(&snapshot.manifest_list).await {
+            Ok(bytes) => read_manifest_list(&bytes)?,
+            Err(err) => {
+                warn!(
+                    "Skipping the manifest list of expired snapshot {}: {:#}",
+                    snapshot.snapshot_id, err
+                );
+                continue;
+            }
+        };
+        for manifest in manifests {
+            if !retained_manifests.contains_key(&manifest.manifest_path) {
+                expired_manifests.insert(manifest.manifest_path.clone(), manifest);
+            }
+        }
+        files.push(snapshot.manifest_list.clone());
+    }
+    if expired_manifests.is_empty() {
+        return Ok(files);
+    }
+
+    let live = live_files(storage, metadata, retained_manifests.values()).await?;
+    let mut unreachable = HashSet::new();
+    for manifest in expired_manifests.values() {
+        let entries = match read_manifest_entries(storage, metadata, manifest).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("Skipping expired manifest {}: {:#}", manifest.manifest_path, err);
+                continue;
+            }
+        };
+        for entry in entries {
+            if !live.contains(&entry.data_file.file_path) {
+                unreachable.insert(entry.data_file.file_path);
+            }
+        }
+        files.push(manifest.manifest_path.clone());
+    }
+    files.extend(unreachable);
+    Ok(files)
+}
+
+/// The data and delete files that `manifests` hold as added or existing.
+async fn live_files(
+    storage: &dyn ObjectStore,
+    metadata: &TableMetadata,
+    manifests: impl Iterator<Item = &ManifestFile>,
+) -> anyhow::Result<HashSet<String>> {
+    let mut live = HashSet::new();
+    for manifest in manifests {
+        for entry in read_manifest_entries(storage, metadata, manifest).await? {
+            if entry.status != ManifestStatus::Deleted {
+                live.insert(entry.data_file.file_path);
+            }
+        }
+    }
+    Ok(live)
+}
+
+/// Deletes the files of `table` that were last modified before `older_than_ms` and that none of
+/// its metadata references, returning them. Like the Java `DeleteOrphanFiles`, these are the files
+/// under the table's location and metadata directory. A data path outside the table's location
+/// may be shared with other tables, so only the files under the table's own prefixes of the
+/// object storage layout are looked at there. Files of commits in progress aren't referenced yet,
+/// so `older_than_ms` has to be well before any of them started.
+pub async fn delete_orphan_files(
+    storage: &dyn ObjectStore,
+    table: &LoadedTable,
+    older_than_ms: i64,
+) -> anyhow::Result<Vec<String>> {
+    let metadata = &table.metadata;
+    let mut referenced = HashSet::new();
+    referenced.insert(normalize(&table.metadata_location));
+    referenced.insert(normalize(&format!("{}/version-hint.text", metadata.metadata_dir())));
+    referenced.extend(
+        metadata
+            .metadata_log
+            .iter()
+            .map(|entry| normalize(&entry.metadata_file)),
+    );
+    let mut manifests = HashMap::new();
+    for snapshot in &metadata.snapshots {
+        referenced.insert(normalize(&snapshot.manifest_list));
+        for manifest in read_manifest_list(&storage.get(&snapshot.manifest_list).await?)? {
+            manifests.insert(manifest.manifest_path.clone(), manifest);
+        }
+    }
+    for manifest in manifests.values() {
+        referenced.insert(normalize(&manifest.manifest_path));
+        // Files deleted by a snapshot are still referenced until it expires
+        for entry in read_manifest_entries(storage, metadata, manifest).await? {
+            referenced.insert(normalize(&entry.data_file.file_path));
+        }
+    }
+
+    // The table's directories, leaving out those inside another one, and the data path outside
+    // them with the `<database>/<table>` part of its files
+    let mut prefixes: Vec<(String, Option<String>)> = vec![];
+    for dir in [metadata.location.clone(), metadata.metadata_dir()] {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        if !prefixes.iter().any(|(other, _)| prefix.starts_with(other.as_str())) {
+            prefixes.retain(|(other, _)| !other.starts_with(&prefix));
+            prefixes.push((prefix, None));
+        }
+    }
+    match metadata.location_provider() {
+        LocationProvider::ObjectStorage {
+            data_dir,
+            context: Some(context),
+        } => prefixes.push((format!("{}/", data_dir), Some(context))),
+        LocationProvider::Default { data_dir }
+            if !prefixes
+                .iter()
+                .any(|(prefix, _)| format!("{}/", data_dir).starts_with(prefix.as_str())) =>
+        {
+            warn!(
+                "Not looking for orphan files in {}, other tables' files may be there",
+                data_dir
+            );
+        }
+        _ => {}
+    }
+
+    let mut orphans = vec![];
+    for (prefix, context) in prefixes {
+        for object in storage.list(&prefix).await? {
+            let owned = context
+                .as_deref()
+                .is_none_or(|context| in_context(&object.location, &prefix, context));
+            if owned && object.last_modified_ms < older_than_ms && !referenced.contains(&normalize(&object.location)) {
+                orphans.push(object.location);
+            }
+        }
+    }
+    orphans.sort();
+    orphans.dedup();
+    Ok(delete_files(storage, orphans).await)
+}
+
+/// Whether `location`, under the object storage data path `prefix`, is at
+/// `<prefix><hash>/<context>/...` where the table with that `<database>/<table>` context writes.
+fn in_context(location: &str, prefix: &str, context: &str) -> bool {
+    location
+        .strip_prefix(prefix)
+        .and_then(|path| path.split_once('/'))
+        .and_then(|(_, path)| path.strip_prefix(context))
+        .is_some_and(|path| path.starts_with('/'))
+}
+
+/// `location` with the S3 schemes made the same and no `file://` scheme, so the locations
+/// metadata records and storage lists can be compared.
+fn normalize(location: &str) -> String {
+    for scheme in ["s3a://", "s3n://"] {
+        if let Some(path) = location.strip_prefix(scheme) {
+            return format!("s3://{}", path);
+        }
+    }
+    location.strip_prefix("file://").unwrap_or(location).to_string()
+}
+
+/// Deletes `files`, returning those that were deleted. Failures are only logged, the files are
+/// left for the next run.
+async fn delete_files(storage: &dyn ObjectStore, files: Vec<String>) -> Vec<String> {
+    stream::iter(files)
+        .map(|file| async move {
+            match storage.delete(&file).await {
+                Ok(()) => Some(file),
+                Err(err) => {
+                    warn!("Failed to delete {}: {:#}", file, err);
+                    None
+                }
+            }
+        })
+        .buffer_unordered(DELETE_CONCURRENCY)
+        .filter_map(|deleted| async move { deleted })
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::catalog::glue::tests::LocalGlue;
+    use crate::catalog::glue::GlueCatalog;
+    use crate::catalog::TableCreation;
+    use crate::commit::append_files;
+    use crate::iceberg::manifest::{DataContentType, DataFile, DataFileFormat};
+    use crate::iceberg::metadata::now_ms;
+    use crate::iceberg::partition::PartitionSpec;
+    use crate::iceberg::schema::{NestedField, PrimitiveType, Schema, Type};
+    use crate::iceberg::sort::SortOrder;
+    use crate::storage::memory::MemoryStore;
+
+    #[tokio::test]
+    async fn expires_snapshots_and_deletes_orphan_files() {
+        let storage = Arc::new(MemoryStore::new());
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let table = TableIdent::new("dotsdb", "books");
+        let creation = TableCreation {
+            schema: Schema::new(
+                0,
+                vec![NestedField::optional(
+                    1,
+                    "review_id",
+                    Type::Primitive(PrimitiveType::String),
+                )],
+            ),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::from([("history.expire.min-snapshots-to-keep".to_string(), "2".to_string())]),
+        };
+        let mut base = catalog.create_table(&table, creation).await.unwrap();
+        let data_dir = base.metadata.data_dir();
+        let mut snapshots = vec![];
+        for name in ["a", "b", "c", "d"] {
+            let location = format!("{}/{}.parquet", data_dir, name);
+            storage.put(&location, b"PAR1".to_vec()).await.unwrap();
+            let data_file = DataFile::new(DataContentType::Data, location, DataFileFormat::Parquet, vec![], 1, 4);
+            snapshots.push(
+                append_files(storage.as_ref(), &catalog, &table, base, vec![data_file])
+                    .await
+                    .unwrap(),
+            );
+            base = catalog.load_table(&table).await.unwrap();
+        }
+
+        // Nothing is old enough yet
+        let removed = expire_snapshots(storage.as_ref(), &catalog, &table, base.clone(), now_ms())
+            .await
+            .unwrap();
+        assert_eq!(removed, Removed::default());
+
+        // Six days on, all but the last two snapshots have expired. Their manifest lists go, but
+        // the later snapshots still hold their manifests and data files
+        let later = now_ms() + 6 * 24 * 60 * 60 * 1000;
+        let removed = expire_snapshots(storage.as_ref(), &catalog, &table, base, later)
+            .await
+            .unwrap();
+        assert_eq!(
+            removed.snapshot_ids,
+            [snapshots[0].snapshot_id, snapshots[1].snapshot_id]
+        );
+        let mut manifest_lists = vec![snapshots[0].manifest_list.clone(), snapshots[1].manifest_list.clone()];
+        manifest_lists.sort();
+        let mut files = removed.files;
+        files.sort();
+        assert_eq!(files, manifest_lists);
+        let loaded = catalog.load_table(&table).await.unwrap();
+        let kept = loaded
+            .metadata
+            .snapshots
+            .iter()
+            .map(|s| s.snapshot_id)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, [snapshots[2].snapshot_id, snapshots[3].snapshot_id]);
+        assert!(storage.get(&format!("{}/a.parquet", data_dir)).await.is_ok());
+
+        // Files nothing references are deleted once they're older than the cutoff
+        let orphan = format!("{}/orphan.parquet", data_dir);
+        storage.put(&orphan, b"PAR1".to_vec()).await.unwrap();
+        let deleted = delete_orphan_files(storage.as_ref(), &loaded, now_ms() - 60_000)
+            .await
+            .unwrap();
+        assert!(deleted.is_empty());
+        let deleted = delete_orphan_files(storage.as_ref(), &loaded, now_ms() + 1)
+            .await
+            .unwrap();
+        assert_eq!(deleted, std::slice::from_ref(&orphan));
+        assert!(storage.get(&orphan).await.is_err());
+        let loaded = catalog.load_table(&table).await.unwrap();
+        assert_eq!(loaded.metadata.current_snapshot_id, Some(snapshots[3].snapshot_id));
+        assert!(storage
+            .get(&loaded.metadata.current_snapshot().unwrap().manifest_list)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn keeps_other_tables_files_under_a_shared_data_path() {
+        let storage = Arc::new(MemoryStore::new());
+        let catalog = GlueCatalog::new(LocalGlue::default(), storage.clone(), Some("s3://bucket".to_string()));
+        let table = TableIdent::new("dotsdb", "books");
+        let creation = TableCreation {
+            schema: Schema::new(
+                0,
+                vec![NestedField::optional(
+                    1,
+                    "review_id",
+                    Type::Primitive(PrimitiveType::String),
+                )],
+            ),
+            partition_spec: PartitionSpec::unpartitioned(),
+            sort_order: SortOrder::unsorted(),
+            location: None,
+            properties: HashMap::from([
+                ("write.object-storage.enabled".to_string(), "true".to_string()),
+                ("write.object-storage.path".to_string(), "s3://shared/data".to_string()),
+            ]),
+        };
+        let base = catalog.create_table(&table, creation).await.unwrap();
+        let locations = base.metadata.location_provider();
+        let location = locations.new_data_location("", "a.parquet");
+        storage.put(&location, b"PAR1".to_vec()).await.unwrap();
+        let data_file = DataFile::new(
+            DataContentType::Data,
+            location.clone(),
+            DataFileFormat::Parquet,
+            vec![],
+            1,
+            4,
+        );
+        append_files(storage.as_ref(), &catalog, &table, base, vec![data_file])
+            .await
+            .unwrap();
+
+        // Another table writes to the same data path, under its own database and table
+        let orphan = locations.new_data_location("", "orphan.parquet");
+        let others = [
+            "s3://shared/data/0000abcd/dotsdb.db/books_v2/b.parquet",
+            "s3://shared/data/b.parquet",
+        ];
+        for location in [orphan.as_str()].into_iter().chain(others) {
+            storage.put(location, b"PAR1".to_vec()).await.unwrap();
+        }
+
+        let loaded = catalog.load_table(&table).await.unwrap();
+        let deleted = delete_orphan_files(storage.as_ref(), &loaded, now_ms() + 1)
+            .await
+            .unwrap();
+        assert_eq!(deleted, [orphan]);
+        for location in [location.as_str()].into_iter().chain(others) {
+            assert!(storage.get(location).await.is_ok(), "{} was deleted", location);
+        }
+    }
+}
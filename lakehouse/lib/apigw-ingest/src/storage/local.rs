@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use tokio::fs;
+use uuid::Uuid;
+
+use crate::storage::{BufferedWriter, ObjectMeta, ObjectStore, ObjectWriter};
+
+/// Objects as files on the local disk, addressed by absolute path or `file://` URI.
+#[derive(Debug, Default)]
+pub struct LocalStore;
+
+fn path_of(location: &str) -> PathBuf {
+    PathBuf::from(location.strip_prefix("file://").unwrap_or(location))
+}
+
+#[async_trait]
+impl ObjectStore for LocalStore {
+    async fn get(&self, location: &str) -> anyhow::Result<Vec<u8>> {
+        fs::read(path_of(location)).await.with_context(|| format!("Failed to read {}", location))
+    }
+
+    async fn put(&self, location: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let path = path_of(location);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        // Write next to the target and rename, so readers never see a partial object
+        let temp_path = path.with_file_name(format!(".{}.tmp", Uuid::new_v4()));
+        fs::write(&temp_path, bytes).await.with_context(|| format!("Failed to write {}", location))?;
+        fs::rename(&temp_path, &path).await.with_context(|| format!("Failed to write {}", location))?;
+        Ok(())
+    }
+
+    async fn delete(&self, location: &str) -> anyhow::Result<()> {
+        fs::remove_file(path_of(location)).await.with_context(|| format!("Failed to delete {}", location))
+    }
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectMeta>> {
+        // Walk from the directory the prefix is in, and keep the files under the prefix
+        let (scheme, path_prefix) = match prefix.strip_prefix("file://") {
+            Some(path) => ("file://", path),
+            None => ("", prefix),
+        };
+        let root = match path_prefix.ends_with('/') {
+            true => PathBuf::from(path_prefix),
+            false => PathBuf::from(path_prefix).parent().map(PathBuf::from).unwrap_or_default(),
+        };
+        let mut listed = vec![];
+        let mut dirs = vec![root];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err).with_context(|| format!("Failed to list {}", dir.display())),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                let path = entry.path();
+                if metadata.is_dir() {
+                    dirs.push(path);
+                } else if path.to_string_lossy().starts_with(path_prefix) {
+                    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+                    listed.push(ObjectMeta {
+                        location: format!("{}{}", scheme, path.display()),
+                        last_modified_ms: modified.as_millis() as i64,
+                    });
+                }
+            }
+        }
+        listed.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(listed)
+    }
+
+    fn writer<'a>(&'a self, location: &str) -> anyhow::Result<Box<dyn ObjectWriter + 'a>> {
+        Ok(Box::new(BufferedWriter::new(self, location)))
+    }
+}
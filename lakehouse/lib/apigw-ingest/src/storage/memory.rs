@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+
+use crate::iceberg::metadata::now_ms;
+use crate::storage::{BufferedWriter, ObjectMeta, ObjectStore, ObjectWriter};
+
+/// Objects kept in memory for the life of the process, for tests and dry runs.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// The bytes and last modified time of every object
+    objects: Mutex<HashMap<String, (Vec<u8>, i64)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MemoryStore {
+    async fn get(&self, location: &str) -> anyhow::Result<Vec<u8>> {
+        let objects = self.objects.lock().unwrap();
+        objects
+            .get(location)
+            .map(|(bytes, _)| bytes.clone())
+            .ok_or_else(|| anyhow!("Object {} not found", location))
+    }
+
+    async fn put(&self, location: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        self.objects.lock().unwrap().insert(location.to_string(), (bytes, now_ms()));
+        Ok(())
+    }
+
+    async fn delete(&self, location: &str) -> anyhow::Result<()> {
+        self.objects.lock().unwrap().remove(location);
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectMeta>> {
+        let objects = self.objects.lock().unwrap();
+        let mut listed = objects
+            .iter()
+            .filter(|(location, _)| location.starts_with(prefix))
+            .map(|(location, (_, last_modified_ms))| ObjectMeta {
+                location: location.clone(),
+                last_modified_ms: *last_modified_ms,
+            })
+            .collect::<Vec<_>>();
+        listed.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(listed)
+    }
+
+    fn writer<'a>(&'a self, location: &str) -> anyhow::Result<Box<dyn ObjectWriter + 'a>> {
+        Ok(Box::new(BufferedWriter::new(self, location)))
+    }
+}
@@ -0,0 +1,157 @@
+use std::env;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::io::AsyncWrite;
+use futures::{ready, FutureExt};
+
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+// Where data files, manifests and metadata files are read from and written to.
+// Locations are the full URIs/paths recorded in the table metadata, e.g. s3://bucket/key.
+
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    async fn get(&self, location: &str) -> anyhow::Result<Vec<u8>>;
+
+    async fn put(&self, location: &str, bytes: Vec<u8>) -> anyhow::Result<()>;
+
+    async fn delete(&self, location: &str) -> anyhow::Result<()>;
+
+    /// The objects whose locations start with `prefix`, at any depth.
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectMeta>>;
+
+    /// Starts writing a new object at `location`, e.g. to stream a Parquet file into it.
+    fn writer<'a>(&'a self, location: &str) -> anyhow::Result<Box<dyn ObjectWriter + 'a>>;
+}
+
+/// An object found by [`ObjectStore::list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectMeta {
+    pub location: String,
+    pub last_modified_ms: i64,
+}
+
+/// An object being written. Nothing shows up at its location until the writer is closed,
+/// and `abort` throws away whatever was written so far.
+#[async_trait]
+pub trait ObjectWriter: AsyncWrite + Send + Unpin {
+    /// The number of bytes written so far, i.e. the size of the object once closed.
+    fn bytes_written(&self) -> u64;
+
+    async fn abort(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+/// Collects the object in memory and puts it in one go on close, for stores where that's cheap.
+pub struct BufferedWriter<'a> {
+    store: &'a dyn ObjectStore,
+    location: String,
+    buffer: Vec<u8>,
+    bytes_written: u64,
+    closing: Option<BoxFuture<'a, anyhow::Result<()>>>,
+    closed: bool,
+}
+
+impl<'a> BufferedWriter<'a> {
+    pub fn new(store: &'a dyn ObjectStore, location: &str) -> Self {
+        BufferedWriter {
+            store,
+            location: location.to_string(),
+            buffer: vec![],
+            bytes_written: 0,
+            closing: None,
+            closed: false,
+        }
+    }
+}
+
+impl AsyncWrite for BufferedWriter<'_> {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.closing.is_some() || this.closed {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "writer closed")));
+        }
+        this.buffer.extend_from_slice(buf);
+        this.bytes_written += buf.len() as u64;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.closed {
+            return Poll::Ready(Ok(()));
+        }
+        if this.closing.is_none() {
+            let store = this.store;
+            let location = this.location.clone();
+            let bytes = std::mem::take(&mut this.buffer);
+            this.closing = Some(async move { store.put(&location, bytes).await }.boxed());
+        }
+        let result = ready!(this.closing.as_mut().unwrap().poll_unpin(cx));
+        this.closing = None;
+        this.closed = true;
+        Poll::Ready(result.map_err(io::Error::other))
+    }
+}
+
+#[async_trait]
+impl ObjectWriter for BufferedWriter<'_> {
+    fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    async fn abort(self: Box<Self>) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// The store selected by `DOTSDB_STORAGE`: `s3` (the default, optionally at an S3-compatible
+/// `DOTSDB_S3_ENDPOINT` such as MinIO), `local` (the local disk) or `memory`.
+pub async fn from_env() -> anyhow::Result<Arc<dyn ObjectStore>> {
+    let store: Arc<dyn ObjectStore> = match env::var("DOTSDB_STORAGE").as_deref().unwrap_or("s3") {
+        "s3" => Arc::new(s3::S3Store::from_env(env::var("DOTSDB_S3_ENDPOINT").ok().as_deref()).await?),
+        "local" => Arc::new(local::LocalStore),
+        "memory" => Arc::new(memory::MemoryStore::new()),
+        other => anyhow::bail!("Unknown storage type {}", other),
+    };
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::AsyncWriteExt;
+
+    use super::memory::MemoryStore;
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_objects_on_close() {
+        let store = MemoryStore::new();
+
+        let mut writer = store.writer("memory://data/a.parquet").unwrap();
+        writer.write_all(b"PAR1").await.unwrap();
+        writer.write_all(b"PAR1").await.unwrap();
+        assert!(store.get("memory://data/a.parquet").await.is_err());
+        writer.close().await.unwrap();
+        assert_eq!(writer.bytes_written(), 8);
+        assert_eq!(store.get("memory://data/a.parquet").await.unwrap(), b"PAR1PAR1");
+
+        let mut aborted = store.writer("memory://data/b.parquet").unwrap();
+        aborted.write_all(b"PAR1").await.unwrap();
+        aborted.abort().await.unwrap();
+        assert!(store.get("memory://data/b.parquet").await.is_err());
+
+        let listed = store.list("memory://data/").await.unwrap();
+        assert_eq!(listed.iter().map(|object| object.location.as_str()).collect::<Vec<_>>(), ["memory://data/a.parquet"]);
+    }
+}
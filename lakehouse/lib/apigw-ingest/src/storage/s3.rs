@@ -0,0 +1,525 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use aws_sdk_s3::model::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::types::ByteStream;
+use aws_sdk_s3::{Client, Endpoint};
+use futures::future::BoxFuture;
+use futures::io::AsyncWrite;
+use futures::{ready, FutureExt};
+use log::warn;
+
+use crate::error::{sdk_error, Transient};
+use crate::storage::{ObjectMeta, ObjectStore, ObjectWriter};
+
+/// Multipart upload parts must be at least 5 MiB, apart from the last one
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Splits an `s3://bucket/key` location into its bucket and key.
+pub fn parse_uri(uri: &str) -> anyhow::Result<(&str, &str)> {
+    let path = ["s3://", "s3a://", "s3n://"]
+        .iter()
+        .find_map(|scheme| uri.strip_prefix(scheme))
+        .ok_or_else(|| anyhow!("Not an S3 location: {}", uri))?;
+    path.split_once('/').ok_or_else(|| anyhow!("S3 location has no key: {}", uri))
+}
+
+/// Objects in S3, or in an S3-compatible store such as MinIO when given an endpoint.
+pub struct S3Store {
+    client: Client,
+}
+
+impl S3Store {
+    pub fn new(client: Client) -> Self {
+        S3Store { client }
+    }
+
+    pub async fn from_env(endpoint: Option<&str>) -> anyhow::Result<Self> {
+        let config = aws_config::load_from_env().await;
+        let mut builder = aws_sdk_s3::config::Builder::from(&config);
+        if let Some(endpoint) = endpoint {
+            builder = builder.endpoint_resolver(Endpoint::immutable(endpoint).context("Invalid S3 endpoint")?);
+        }
+        Ok(S3Store::new(Client::from_conf(builder.build())))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3Store {
+    async fn get(&self, location: &str) -> anyhow::Result<Vec<u8>> {
+        let (bucket, key) = parse_uri(location)?;
+        let object = self
+            .client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(sdk_error)
+            .with_context(|| format!("Failed to read {}", location))?;
+        let bytes = object
+            .body
+            .collect()
+            .await
+            .map_err(|err| Transient(err.into()))
+            .with_context(|| format!("Failed to read {}", location))?
+            .into_bytes();
+        Ok(bytes.to_vec())
+    }
+
+    async fn put(&self, location: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let (bucket, key) = parse_uri(location)?;
+        self.client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(ByteStream::from(bytes))
+            .send()
+            .await
+            .map_err(sdk_error)
+            .with_context(|| format!("Failed to write {}", location))?;
+        Ok(())
+    }
+
+    async fn delete(&self, location: &str) -> anyhow::Result<()> {
+        let (bucket, key) = parse_uri(location)?;
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(sdk_error)
+            .with_context(|| format!("Failed to delete {}", location))?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> anyhow::Result<Vec<ObjectMeta>> {
+        let (bucket, key_prefix) = parse_uri(prefix)?;
+        // Keys are listed as locations with the scheme of the prefix, s3a:// or otherwise
+        let bucket_uri = &prefix[..prefix.len() - key_prefix.len()];
+        let mut listed = vec![];
+        let mut continuation_token = None;
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(key_prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(sdk_error)
+                .with_context(|| format!("Failed to list {}", prefix))?;
+            for object in output.contents().unwrap_or_default() {
+                if let Some(key) = object.key() {
+                    let last_modified_ms = object.last_modified().and_then(|time| time.to_millis().ok());
+                    listed.push(ObjectMeta {
+                        location: format!("{}{}", bucket_uri, key),
+                        last_modified_ms: last_modified_ms.unwrap_or_default(),
+                    });
+                }
+            }
+            match output.next_continuation_token() {
+                Some(token) if output.is_truncated() => continuation_token = Some(token.to_string()),
+                _ => return Ok(listed),
+            }
+        }
+    }
+
+    fn writer<'a>(&'a self, location: &str) -> anyhow::Result<Box<dyn ObjectWriter + 'a>> {
+        let (bucket, key) = parse_uri(location)?;
+        Ok(Box::new(MultipartWriter::new(self.client.clone(), bucket, key)))
+    }
+}
+
+enum Step {
+    Created(String),
+    Uploaded(CompletedPart),
+}
+
+/// Streams an object to S3 in parts of [`PART_SIZE`], so it never has to fit in memory or on
+/// disk as a whole. Objects smaller than one part are written with a single PutObject on close.
+///
+/// At most one part is uploading while the next one fills up. If the writer is dropped before
+/// it is closed, the upload is aborted so S3 doesn't keep (and bill for) the parts.
+pub struct MultipartWriter {
+    client: Client,
+    bucket: String,
+    key: String,
+    buffer: Vec<u8>,
+    bytes_written: u64,
+    upload_id: Option<String>,
+    parts: Vec<CompletedPart>,
+    in_flight: Option<BoxFuture<'static, anyhow::Result<Step>>>,
+    closing: Option<BoxFuture<'static, anyhow::Result<()>>>,
+    closed: bool,
+}
+
+fn to_io_error(err: anyhow::Error) -> io::Error {
+    io::Error::other(err)
+}
+
+impl MultipartWriter {
+    pub fn new(client: Client, bucket: &str, key: &str) -> Self {
+        MultipartWriter {
+            client,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            buffer: Vec::with_capacity(PART_SIZE),
+            bytes_written: 0,
+            upload_id: None,
+            parts: vec![],
+            in_flight: None,
+            closing: None,
+            closed: false,
+        }
+    }
+
+    /// Creates the multipart upload, or uploads the buffer as the next part once it exists.
+    fn start_next_step(&mut self) {
+        let client = self.client.clone();
+        let bucket = self.bucket.clone();
+        let key = self.key.clone();
+        let future = match &self.upload_id {
+            None => async move {
+                let output = client
+                    .create_multipart_upload()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .map_err(sdk_error)
+                    .with_context(|| format!("Failed to start upload to s3://{}/{}", bucket, key))?;
+                let upload_id = output.upload_id().ok_or_else(|| anyhow!("S3 returned no upload id"))?;
+                Ok(Step::Created(upload_id.to_string()))
+            }
+            .boxed(),
+            Some(upload_id) => {
+                let part_number = self.parts.len() as i32 + 1;
+                let bytes = std::mem::replace(&mut self.buffer, Vec::with_capacity(PART_SIZE));
+                upload_part(client, bucket, key, upload_id.clone(), part_number, bytes)
+                    .map(|part| part.map(Step::Uploaded))
+                    .boxed()
+            }
+        };
+        self.in_flight = Some(future);
+    }
+
+    fn poll_in_flight(&mut self, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        if let Some(in_flight) = &mut self.in_flight {
+            let step = ready!(in_flight.poll_unpin(cx));
+            self.in_flight = None;
+            match step.map_err(to_io_error)? {
+                Step::Created(upload_id) => self.upload_id = Some(upload_id),
+                Step::Uploaded(part) => self.parts.push(part),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Uploads whatever is left and completes the upload.
+    fn finish(&mut self) -> BoxFuture<'static, anyhow::Result<()>> {
+        let client = self.client.clone();
+        let bucket = self.bucket.clone();
+        let key = self.key.clone();
+        let bytes = std::mem::take(&mut self.buffer);
+        let upload_id = self.upload_id.clone();
+        let mut parts = self.parts.clone();
+        async move {
+            let upload_id = match upload_id {
+                Some(upload_id) => upload_id,
+                None => {
+                    client
+                        .put_object()
+                        .bucket(&bucket)
+                        .key(&key)
+                        .body(ByteStream::from(bytes))
+                        .send()
+                        .await
+                        .map_err(sdk_error)
+                        .with_context(|| format!("Failed to write s3://{}/{}", bucket, key))?;
+                    return Ok(());
+                }
+            };
+            if !bytes.is_empty() || parts.is_empty() {
+                let part_number = parts.len() as i32 + 1;
+                parts.push(upload_part(client.clone(), bucket.clone(), key.clone(), upload_id.clone(), part_number, bytes).await?);
+            }
+            client
+                .complete_multipart_upload()
+                .bucket(&bucket)
+                .key(&key)
+                .upload_id(upload_id)
+                .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+                .send()
+                .await
+                .map_err(sdk_error)
+                .with_context(|| format!("Failed to complete upload to s3://{}/{}", bucket, key))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    /// Aborts the upload once the step in flight is done: a pending `CreateMultipartUpload` has
+    /// the id of the upload to abort, and a pending part has to land before the abort covers it.
+    fn abort_upload(&mut self) -> BoxFuture<'static, anyhow::Result<()>> {
+        let in_flight = self.in_flight.take();
+        let mut upload_id = self.upload_id.take();
+        let (client, bucket, key) = (self.client.clone(), self.bucket.clone(), self.key.clone());
+        async move {
+            if let Some(in_flight) = in_flight {
+                // The step's own failure doesn't matter anymore
+                if let Ok(Step::Created(created)) = in_flight.await {
+                    upload_id = Some(created);
+                }
+            }
+            match upload_id {
+                Some(upload_id) => abort_upload(client, bucket, key, upload_id).await,
+                None => Ok(()),
+            }
+        }
+        .boxed()
+    }
+}
+
+async fn upload_part(
+    client: Client,
+    bucket: String,
+    key: String,
+    upload_id: String,
+    part_number: i32,
+    bytes: Vec<u8>,
+) -> anyhow::Result<CompletedPart> {
+    let output = client
+        .upload_part()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(upload_id)
+        .part_number(part_number)
+        .body(ByteStream::from(bytes))
+        .send()
+        .await
+        .map_err(sdk_error)
+        .with_context(|| format!("Failed to upload part {} of s3://{}/{}", part_number, bucket, key))?;
+    Ok(CompletedPart::builder()
+        .set_e_tag(output.e_tag().map(str::to_string))
+        .part_number(part_number)
+        .build())
+}
+
+async fn abort_upload(client: Client, bucket: String, key: String, upload_id: String) -> anyhow::Result<()> {
+    client
+        .abort_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(upload_id)
+        .send()
+        .await
+        .map_err(sdk_error)
+        .with_context(|| format!("Failed to abort upload to s3://{}/{}", bucket, key))?;
+    Ok(())
+}
+
+impl AsyncWrite for MultipartWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.closing.is_some() || this.closed {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "writer closed")));
+        }
+        loop {
+            if this.in_flight.is_some() {
+                match this.poll_in_flight(cx) {
+                    Poll::Ready(result) => result?,
+                    // Keep filling the next part while the previous one uploads
+                    Poll::Pending if this.buffer.len() < PART_SIZE => break,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            if this.buffer.len() < PART_SIZE {
+                break;
+            }
+            this.start_next_step();
+        }
+        this.buffer.extend_from_slice(buf);
+        this.bytes_written += buf.len() as u64;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_in_flight(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.closed {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(this.poll_in_flight(cx))?;
+        if this.closing.is_none() {
+            this.closing = Some(this.finish());
+        }
+        let result = ready!(this.closing.as_mut().unwrap().poll_unpin(cx));
+        this.closing = None;
+        result.map_err(to_io_error)?;
+        this.closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[async_trait]
+impl ObjectWriter for MultipartWriter {
+    fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    async fn abort(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.closing = None;
+        match self.closed {
+            true => Ok(()),
+            false => self.abort_upload().await,
+        }
+    }
+}
+
+impl Drop for MultipartWriter {
+    fn drop(&mut self) {
+        if self.closed || (self.in_flight.is_none() && self.upload_id.is_none()) {
+            return;
+        }
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let abort = self.abort_upload();
+            runtime.spawn(async move {
+                if let Err(err) = abort.await {
+                    warn!("{:#}", err);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use aws_sdk_s3::config::retry::RetryConfig;
+    use aws_sdk_s3::{Config, Credentials, Region};
+    use futures::AsyncWriteExt;
+
+    use super::*;
+    use crate::testing::{MockServer, Received};
+
+    /// An S3 endpoint that starts uploads as `UP1`, and fails part uploads if `fail_parts`.
+    async fn mock_s3(fail_parts: bool) -> (MockServer, Client) {
+        let server = MockServer::start(move |request: &Received| {
+            let line = &request.request_line;
+            if line.starts_with("POST") && line.contains("?uploads") {
+                (200, "<InitiateMultipartUploadResult><UploadId>UP1</UploadId></InitiateMultipartUploadResult>".to_string())
+            } else if line.starts_with("PUT") && line.contains("partNumber=") && fail_parts {
+                (403, "<Error><Code>AccessDenied</Code><Message>Access Denied</Message></Error>".to_string())
+            } else if line.starts_with("DELETE") {
+                (204, String::new())
+            } else {
+                (200, "<CompleteMultipartUploadResult></CompleteMultipartUploadResult>".to_string())
+            }
+        })
+        .await;
+        let config = Config::builder()
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("key", "secret", None, None, "test"))
+            .endpoint_resolver(Endpoint::immutable(&server.uri).unwrap())
+            .retry_config(RetryConfig::disabled())
+            .build();
+        (server, Client::from_conf(config))
+    }
+
+    /// The method and query of every request, e.g. `PUT ?partNumber=1&uploadId=UP1`.
+    fn calls(server: &MockServer) -> Vec<String> {
+        server
+            .received()
+            .iter()
+            .map(|request| {
+                let mut parts = request.request_line.split(' ');
+                let method = parts.next().unwrap();
+                let query = parts.next().unwrap().split_once('?').map(|(_, query)| query).unwrap_or_default();
+                let query = query.split('&').filter(|param| !param.starts_with("x-id=")).collect::<Vec<_>>();
+                format!("{} ?{}", method, query.join("&")).trim_end_matches(" ?").to_string()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn writes_small_objects_with_one_put() {
+        let (server, client) = mock_s3(false).await;
+        let mut writer = MultipartWriter::new(client, "bucket", "a.parquet");
+        writer.write_all(b"PAR1").await.unwrap();
+        writer.close().await.unwrap();
+        assert_eq!(calls(&server), ["PUT"]);
+        assert_eq!(server.received()[0].body, b"PAR1");
+    }
+
+    #[tokio::test]
+    async fn uploads_parts_of_the_part_size() {
+        let (server, client) = mock_s3(false).await;
+        let mut writer = MultipartWriter::new(client, "bucket", "a.parquet");
+        writer.write_all(&vec![1; PART_SIZE]).await.unwrap();
+        writer.write_all(b"PAR1").await.unwrap();
+        writer.close().await.unwrap();
+        assert_eq!(writer.bytes_written(), PART_SIZE as u64 + 4);
+        assert_eq!(
+            calls(&server),
+            ["POST ?uploads", "PUT ?partNumber=1&uploadId=UP1", "PUT ?partNumber=2&uploadId=UP1", "POST ?uploadId=UP1"]
+        );
+        let received = server.received();
+        assert_eq!((received[1].body.len(), received[2].body.as_slice()), (PART_SIZE, &b"PAR1"[..]));
+    }
+
+    #[tokio::test]
+    async fn aborts_the_upload_when_a_part_fails() {
+        let (server, client) = mock_s3(true).await;
+        let mut writer = Box::new(MultipartWriter::new(client, "bucket", "a.parquet"));
+        writer.write_all(&vec![1; PART_SIZE]).await.unwrap();
+        // Starts the upload, then the first part while this is buffered
+        writer.write_all(b"PAR1").await.unwrap();
+        assert!(writer.close().await.is_err());
+        writer.abort().await.unwrap();
+        assert_eq!(
+            calls(&server),
+            ["POST ?uploads", "PUT ?partNumber=1&uploadId=UP1", "DELETE ?uploadId=UP1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn aborts_the_upload_once_the_step_in_flight_is_done() {
+        let (server, client) = mock_s3(false).await;
+        let mut writer = Box::new(MultipartWriter::new(client, "bucket", "a.parquet"));
+        writer.write_all(&vec![1; PART_SIZE]).await.unwrap();
+        // Only creating the upload is in flight, so its id isn't known yet
+        writer.start_next_step();
+        writer.abort().await.unwrap();
+        assert_eq!(calls(&server), ["POST ?uploads", "DELETE ?uploadId=UP1"]);
+    }
+
+    #[tokio::test]
+    async fn aborts_the_upload_when_dropped_mid_upload() {
+        let (server, client) = mock_s3(false).await;
+        let mut writer = MultipartWriter::new(client, "bucket", "a.parquet");
+        writer.write_all(&vec![1; PART_SIZE]).await.unwrap();
+        // The first part uploads while this is buffered
+        writer.write_all(b"PAR1").await.unwrap();
+        drop(writer);
+        for _ in 0..500 {
+            if server.received().len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            calls(&server),
+            ["POST ?uploads", "PUT ?partNumber=1&uploadId=UP1", "DELETE ?uploadId=UP1"]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// Helpers shared by the tests of several modules
+
+/// A request a [`MockServer`] received.
+#[derive(Debug, Clone)]
+pub struct Received {
+    /// e.g. `POST /catalog/v1/namespaces/dotsdb/tables/books HTTP/1.1`
+    pub request_line: String,
+    /// The header lines, lowercased
+    pub headers: String,
+    pub body: Vec<u8>,
+}
+
+type Respond = dyn Fn(&Received) -> (u16, String) + Send + Sync;
+
+/// A local HTTP server standing in for S3 or a catalog, answering every request with the status
+/// code and body `respond` returns for it, and keeping the requests it received.
+pub struct MockServer {
+    pub uri: String,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl MockServer {
+    pub async fn start(respond: impl Fn(&Received) -> (u16, String) + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(vec![]));
+        let requests = received.clone();
+        let respond: Arc<Respond> = Arc::new(respond);
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(answer(socket, requests.clone(), respond.clone()));
+            }
+        });
+        MockServer { uri, received }
+    }
+
+    /// A server answering its requests with `responses`, in order.
+    pub async fn with_responses(responses: Vec<(u16, String)>) -> Self {
+        let responses = Mutex::new(responses.into_iter());
+        MockServer::start(move |_| responses.lock().unwrap().next().expect("No response left")).await
+    }
+
+    pub fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+/// Reads one request off `socket` and answers it, closing the connection.
+async fn answer(mut socket: TcpStream, received: Arc<Mutex<Vec<Received>>>, respond: Arc<Respond>) {
+    let mut request = vec![];
+    let mut buffer = [0; 64 * 1024];
+    // The head, then as many bytes of body as it announces
+    let (head, length) = loop {
+        let read = socket.read(&mut buffer).await.unwrap();
+        if read == 0 {
+            return;
+        }
+        request.extend_from_slice(&buffer[..read]);
+        if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..end]).to_string();
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|n| n.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            break (head, length);
+        }
+    };
+    while request.len() < head.len() + 4 + length {
+        let read = socket.read(&mut buffer).await.unwrap();
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let (request_line, headers) = head.split_once("\r\n").unwrap_or((&head, ""));
+    let request = Received {
+        request_line: request_line.to_string(),
+        headers: headers.to_ascii_lowercase(),
+        body: request[head.len() + 4..].to_vec(),
+    };
+    let (status, body) = respond(&request);
+    received.lock().unwrap().push(request);
+    let response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await.unwrap();
+}
@@ -16,22 +16,26 @@ public class Handler {
         val catalog: Catalog by lazy { DotsDBGlueCatalog.createIcebergCatalog() }
         val namespace = Namespace.of("dotsdb") // TODO: add tf env var import
         val tableId = TableIdentifier.of(namespace, "books") // TODO: add tf env var if expanding beyond 1 table, would require loop and config
+        // review_id identifies a review, so the ingest lambda can upsert corrected reviews
         val schema = Schema(
-            Types.NestedField.optional(1, "marketplace", Types.StringType.get()),
-            Types.NestedField.optional(2, "customer_id", Types.StringType.get()),
-            Types.NestedField.optional(3, "review_id", Types.StringType.get()),
-            Types.NestedField.optional(4, "product_id", Types.StringType.get()),
-            Types.NestedField.optional(5, "product_parent", Types.StringType.get()),
-            Types.NestedField.optional(6, "product_title", Types.StringType.get()),
-            Types.NestedField.optional(7, "star_rating", Types.IntegerType.get()),
-            Types.NestedField.optional(8, "helpful_votes", Types.IntegerType.get()),
-            Types.NestedField.optional(9, "total_votes", Types.IntegerType.get()),
-            Types.NestedField.optional(10, "vine", Types.StringType.get()),
-            Types.NestedField.optional(11, "verified_purchase", Types.StringType.get()),
-            Types.NestedField.optional(12, "review_headline", Types.StringType.get()),
-            Types.NestedField.optional(13, "review_body", Types.StringType.get()),
-            Types.NestedField.optional(14, "review_date", Types.DateType.get()),
-            Types.NestedField.optional(15, "year", Types.IntegerType.get())
+            listOf(
+                Types.NestedField.optional(1, "marketplace", Types.StringType.get()),
+                Types.NestedField.optional(2, "customer_id", Types.StringType.get()),
+                Types.NestedField.required(3, "review_id", Types.StringType.get()),
+                Types.NestedField.optional(4, "product_id", Types.StringType.get()),
+                Types.NestedField.optional(5, "product_parent", Types.StringType.get()),
+                Types.NestedField.optional(6, "product_title", Types.StringType.get()),
+                Types.NestedField.optional(7, "star_rating", Types.IntegerType.get()),
+                Types.NestedField.optional(8, "helpful_votes", Types.IntegerType.get()),
+                Types.NestedField.optional(9, "total_votes", Types.IntegerType.get()),
+                Types.NestedField.optional(10, "vine", Types.StringType.get()),
+                Types.NestedField.optional(11, "verified_purchase", Types.StringType.get()),
+                Types.NestedField.optional(12, "review_headline", Types.StringType.get()),
+                Types.NestedField.optional(13, "review_body", Types.StringType.get()),
+                Types.NestedField.optional(14, "review_date", Types.DateType.get()),
+                Types.NestedField.optional(15, "year", Types.IntegerType.get())
+            ),
+            setOf(3)
         )
         // val spec = PartitionSpec.builderFor(schema)
         //     .day("review_date")